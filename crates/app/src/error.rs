@@ -0,0 +1,90 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+// Errors returned by the command entry points. Message-only helpers still build Strings; each
+// boundary wraps them in the variant that fits, leaving `Other` for platform and loader failures.
+#[derive(Debug)]
+pub enum AppError {
+    // Bad command line: unknown flags, missing or malformed values.
+    InvalidArgs(String),
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    // Input that was read but could not be understood (RGBA headers, event logs, fixture names).
+    Parse(String),
+    Fetch(String),
+    PlatformAbiMismatch {
+        runtime: u32,
+        expected: u32,
+    },
+    // One line per fixture/size whose hash differed.
+    GoldenMismatch(Vec<String>),
+    Other(String),
+}
+
+impl AppError {
+    pub fn io(action: &'static str, path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::Io {
+            action,
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidArgs(message)
+            | Self::Parse(message)
+            | Self::Fetch(message)
+            | Self::Other(message) => f.write_str(message),
+            Self::Io {
+                action,
+                path,
+                source,
+            } => write!(f, "failed to {action} {}: {source}", path.display()),
+            Self::PlatformAbiMismatch { runtime, expected } => write!(
+                f,
+                "platform ABI mismatch: runtime={runtime}, expected={expected}. If Zig is not installed, use headless mode."
+            ),
+            Self::GoldenMismatch(failures) => {
+                write!(f, "golden mismatches:")?;
+                for failure in failures {
+                    write!(f, "\n- {failure}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_name_the_action_and_path() {
+        let err = AppError::io(
+            "read",
+            "missing.html",
+            io::Error::new(io::ErrorKind::NotFound, "not found"),
+        );
+        assert_eq!(err.to_string(), "failed to read missing.html: not found");
+        assert!(err.source().is_some());
+
+        let err = AppError::GoldenMismatch(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(err.to_string(), "golden mismatches:\n- a\n- b");
+        assert!(err.source().is_none());
+    }
+}
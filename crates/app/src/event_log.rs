@@ -0,0 +1,290 @@
+use crate::error::AppError;
+use platform_abi::PlatformEvent;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+const EVENT_LOG_HEADER: &str = "browser-event-log 1";
+
+// Everything one windowed frame consumed: its time since the loop started, the platform events
+// polled for it, and whether a pending document arrived just before it was stepped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoggedFrame {
+    pub elapsed: Duration,
+    pub events: Vec<PlatformEvent>,
+    pub document_loaded: bool,
+}
+
+// Text format: the header line, then per frame `frame <elapsed_ns>`, one
+// `event <kind> <key_code> <modifiers> <width> <height> <x> <y>` line per event, and `loaded` when
+// the document arrived during that frame. Event lines without the pointer `<x> <y>` (logs written
+// before mouse events existed) read back with both at 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventLog {
+    pub frames: Vec<LoggedFrame>,
+}
+
+impl EventLog {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let text =
+            fs::read_to_string(path).map_err(|err| AppError::io("read event log", path, err))?;
+        Self::parse(&text).map_err(|err| AppError::Parse(format!("{}: {err}", path.display())))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        if lines.next().map(|(_, line)| line) != Some(EVENT_LOG_HEADER) {
+            return Err(format!("event log must start with '{EVENT_LOG_HEADER}'"));
+        }
+
+        let mut frames: Vec<LoggedFrame> = Vec::new();
+        for (number, line) in lines {
+            let mut fields = line.split_whitespace();
+            let entry = fields.next().unwrap_or_default();
+            if entry == "frame" {
+                let [elapsed_ns] = parse_fields(number, fields)?;
+                frames.push(LoggedFrame {
+                    elapsed: Duration::from_nanos(elapsed_ns),
+                    ..LoggedFrame::default()
+                });
+                continue;
+            }
+
+            let frame = frames
+                .last_mut()
+                .ok_or_else(|| format!("line {number}: '{entry}' before the first frame"))?;
+            match entry {
+                "event" => {
+                    let fields: Vec<&str> = fields.collect();
+                    let (fields, pointer) = fields.split_at(fields.len().min(5));
+                    let [kind, key_code, modifiers, width, height] =
+                        parse_fields(number, fields.iter().copied())?;
+                    let [x, y] = if pointer.is_empty() {
+                        [0, 0]
+                    } else {
+                        parse_fields(number, pointer.iter().copied())?
+                    };
+                    frame.events.push(PlatformEvent {
+                        struct_size: std::mem::size_of::<PlatformEvent>() as u32,
+                        kind,
+                        key_code,
+                        width,
+                        height,
+                        modifiers,
+                        x,
+                        y,
+                    });
+                }
+                "loaded" => {
+                    let [] = parse_fields::<u32, 0>(number, fields)?;
+                    frame.document_loaded = true;
+                }
+                other => return Err(format!("line {number}: unknown entry '{other}'")),
+            }
+        }
+        Ok(Self { frames })
+    }
+}
+
+// Writes frames as they happen and flushes each one, so a crash still leaves every frame
+// before it in the log.
+// `path` only names the log in write errors.
+pub struct EventRecorder<W: Write> {
+    out: W,
+    path: PathBuf,
+}
+
+impl EventRecorder<BufWriter<File>> {
+    pub fn create(path: &Path) -> Result<Self, AppError> {
+        let file = File::create(path).map_err(|err| AppError::io("create event log", path, err))?;
+        Self::new(BufWriter::new(file), path)
+    }
+}
+
+impl<W: Write> EventRecorder<W> {
+    pub fn new(mut out: W, path: impl Into<PathBuf>) -> Result<Self, AppError> {
+        let path = path.into();
+        writeln!(out, "{EVENT_LOG_HEADER}")
+            .map_err(|err| AppError::io("write event log", &path, err))?;
+        Ok(Self { out, path })
+    }
+
+    pub fn record(&mut self, frame: &LoggedFrame) -> Result<(), AppError> {
+        self.write_frame(frame)
+            .and_then(|()| self.out.flush())
+            .map_err(|err| AppError::io("write event log", &self.path, err))
+    }
+
+    fn write_frame(&mut self, frame: &LoggedFrame) -> std::io::Result<()> {
+        writeln!(self.out, "frame {}", frame.elapsed.as_nanos())?;
+        for event in &frame.events {
+            writeln!(
+                self.out,
+                "event {} {} {} {} {} {} {}",
+                event.kind,
+                event.key_code,
+                event.modifiers,
+                event.width,
+                event.height,
+                event.x,
+                event.y
+            )?;
+        }
+        if frame.document_loaded {
+            writeln!(self.out, "loaded")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_fields<'a, T: FromStr, const N: usize>(
+    number: usize,
+    fields: impl Iterator<Item = &'a str>,
+) -> Result<[T; N], String> {
+    let values = fields
+        .map(|field| {
+            field
+                .parse()
+                .map_err(|_| format!("line {number}: invalid value '{field}'"))
+        })
+        .collect::<Result<Vec<T>, String>>()?;
+    let count = values.len();
+    values
+        .try_into()
+        .map_err(|_| format!("line {number}: expected {N} values, got {count}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use platform_abi::{
+        PLATFORM_EVENT_KEY_DOWN, PLATFORM_EVENT_MOUSE_MOVE, PLATFORM_EVENT_RESIZE, PLATFORM_KEY_TAB,
+    };
+
+    #[test]
+    fn recorded_frames_parse_back() {
+        let log = EventLog {
+            frames: vec![
+                LoggedFrame {
+                    elapsed: Duration::from_nanos(16_666_667),
+                    ..LoggedFrame::default()
+                },
+                LoggedFrame {
+                    elapsed: Duration::from_millis(40),
+                    events: vec![
+                        PlatformEvent {
+                            struct_size: std::mem::size_of::<PlatformEvent>() as u32,
+                            kind: PLATFORM_EVENT_KEY_DOWN,
+                            key_code: PLATFORM_KEY_TAB,
+                            modifiers: 1,
+                            ..PlatformEvent::default()
+                        },
+                        PlatformEvent {
+                            struct_size: std::mem::size_of::<PlatformEvent>() as u32,
+                            kind: PLATFORM_EVENT_RESIZE,
+                            width: 320,
+                            height: 200,
+                            ..PlatformEvent::default()
+                        },
+                        PlatformEvent {
+                            struct_size: std::mem::size_of::<PlatformEvent>() as u32,
+                            kind: PLATFORM_EVENT_MOUSE_MOVE,
+                            x: 40,
+                            y: -3,
+                            ..PlatformEvent::default()
+                        },
+                    ],
+                    document_loaded: true,
+                },
+            ],
+        };
+
+        let mut out = Vec::new();
+        let mut recorder = EventRecorder::new(&mut out, "events.log").unwrap();
+        for frame in &log.frames {
+            recorder.record(frame).unwrap();
+        }
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.starts_with("browser-event-log 1\nframe 16666667\n"),
+            "{text}"
+        );
+        assert_eq!(EventLog::parse(&text), Ok(log));
+    }
+
+    #[test]
+    fn parse_reports_bad_lines() {
+        assert!(EventLog::parse("frame 1\n").is_err());
+        let err = EventLog::parse("browser-event-log 1\nloaded\n").unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+        let err = EventLog::parse("browser-event-log 1\nframe 1\nevent 1 2\n").unwrap_err();
+        assert!(err.contains("expected 5 values, got 2"), "{err}");
+        let old = EventLog::parse("browser-event-log 1\nframe 1\nevent 4 0 0 320 200\n").unwrap();
+        assert_eq!(
+            (old.frames[0].events[0].width, old.frames[0].events[0].x),
+            (320, 0)
+        );
+        let err = EventLog::parse("browser-event-log 1\nframe soon\n").unwrap_err();
+        assert!(err.contains("invalid value 'soon'"), "{err}");
+    }
+
+    #[test]
+    fn load_separates_read_failures_from_format_errors() {
+        let path = std::env::temp_dir().join(format!("browser-events-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let err = EventLog::load(&path).unwrap_err();
+        assert!(matches!(err, AppError::Io { .. }), "{err:?}");
+        assert!(
+            err.to_string().starts_with("failed to read event log"),
+            "{err}"
+        );
+
+        fs::write(&path, "browser-event-log 1\nloaded\n").unwrap();
+        let err = EventLog::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(
+            matches!(&err, AppError::Parse(message) if message.contains("line 2")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn recorder_failures_are_io_errors_naming_the_log() {
+        let dir = std::env::temp_dir().join(format!("browser-no-such-dir-{}", std::process::id()));
+        let err = EventRecorder::create(&dir.join("events.log"))
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string().starts_with("failed to create event log"),
+            "{err}"
+        );
+
+        struct Full;
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::StorageFull.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let err = EventRecorder::new(Full, "events.log").err().unwrap();
+        match &err {
+            AppError::Io { action, path, .. } => {
+                assert_eq!(
+                    (*action, path.as_path()),
+                    ("write event log", Path::new("events.log"))
+                );
+            }
+            other => panic!("expected an io error, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+pub const DEFAULT_MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+pub const DEFAULT_MAX_REDIRECTS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FetchOptions {
+    pub max_body_bytes: usize,
+    pub max_redirects: u32,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub truncated: bool,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedPage {
+    pub url: String,
+    pub content_type: Option<String>,
+    pub text: String,
+    pub truncated: bool,
+}
+
+pub fn is_http_url(input: &str) -> bool {
+    input.starts_with("http://")
+}
+
+pub fn fetch_url(url: &str, options: &FetchOptions) -> Result<FetchedPage, String> {
+    fetch_with(url, options, |url| http_get(url, options.max_body_bytes))
+}
+
+// Follows up to `max_redirects` 301/302 hops; `get` performs a single request.
+pub fn fetch_with(
+    url: &str,
+    options: &FetchOptions,
+    mut get: impl FnMut(&str) -> Result<HttpResponse, String>,
+) -> Result<FetchedPage, String> {
+    let mut url = url.to_string();
+    let mut redirects = 0;
+    loop {
+        let response = get(&url)?;
+        match response.status {
+            301 | 302 => {
+                if redirects == options.max_redirects {
+                    return Err(format!(
+                        "too many redirects (limit {}) fetching {url}",
+                        options.max_redirects
+                    ));
+                }
+                let location = response
+                    .header("location")
+                    .ok_or_else(|| format!("redirect from {url} has no Location header"))?;
+                url = resolve_location(&url, location)?;
+                redirects += 1;
+            }
+            200..=299 => {
+                return Ok(FetchedPage {
+                    content_type: response.header("content-type").map(str::to_string),
+                    text: String::from_utf8_lossy(&response.body).into_owned(),
+                    truncated: response.truncated,
+                    url,
+                });
+            }
+            status => return Err(format!("GET {url} failed with status {status}")),
+        }
+    }
+}
+
+// Reads a full HTTP/1.x response, keeping at most `max_body_bytes` of the body.
+pub fn read_response(reader: impl Read, max_body_bytes: usize) -> Result<HttpResponse, String> {
+    let mut reader = BufReader::new(reader);
+    let mut status_line = String::new();
+    reader
+        .read_line(&mut status_line)
+        .map_err(|err| format!("failed to read status line: {err}"))?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| format!("malformed status line: {}", status_line.trim_end()))?;
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|err| format!("failed to read headers: {err}"))?;
+        let line = line.trim_end();
+        if read == 0 || line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<u64>().ok());
+    let limit = content_length
+        .unwrap_or(u64::MAX)
+        .min(max_body_bytes as u64);
+
+    let mut body = Vec::new();
+    reader
+        .by_ref()
+        .take(limit)
+        .read_to_end(&mut body)
+        .map_err(|err| format!("failed to read body: {err}"))?;
+    let truncated = match content_length {
+        Some(length) => length > body.len() as u64 && body.len() == max_body_bytes,
+        None => body.len() == max_body_bytes && reader.fill_buf().is_ok_and(|buf| !buf.is_empty()),
+    };
+
+    Ok(HttpResponse {
+        status,
+        headers,
+        body,
+        truncated,
+    })
+}
+
+fn http_get(url: &str, max_body_bytes: usize) -> Result<HttpResponse, String> {
+    let (host, port, path) = split_url(url)?;
+    let mut stream = TcpStream::connect((host, port))
+        .map_err(|err| format!("failed to connect to {host}:{port}: {err}"))?;
+    let timeout = Some(Duration::from_secs(10));
+    stream
+        .set_read_timeout(timeout)
+        .and_then(|()| stream.set_write_timeout(timeout))
+        .map_err(|err| format!("failed to configure socket: {err}"))?;
+
+    // HTTP/1.0 keeps the body un-chunked and closes the connection after the response.
+    let request = format!(
+        "GET {path} HTTP/1.0\r\nHost: {host}\r\nUser-Agent: browser\r\nAccept: text/html, */*\r\n\r\n"
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|err| format!("failed to send request to {host}: {err}"))?;
+    read_response(stream, max_body_bytes)
+}
+
+fn split_url(url: &str) -> Result<(&str, u16, &str), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("unsupported url (only http:// is supported): {url}"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| format!("invalid port in url: {url}"))?,
+        ),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(format!("missing host in url: {url}"));
+    }
+    Ok((host, port, path))
+}
+
+fn resolve_location(base: &str, location: &str) -> Result<String, String> {
+    if location.contains("://") {
+        return Ok(location.to_string());
+    }
+    let (host, port, path) = split_url(base)?;
+    let origin = if port == 80 {
+        format!("http://{host}")
+    } else {
+        format!("http://{host}:{port}")
+    };
+    if location.starts_with('/') {
+        return Ok(format!("{origin}{location}"));
+    }
+    let directory = &path[..path.rfind('/').map_or(0, |index| index + 1)];
+    Ok(format!("{origin}{directory}{location}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect(location: &str) -> HttpResponse {
+        HttpResponse {
+            status: 302,
+            headers: vec![("Location".to_string(), location.to_string())],
+            body: Vec::new(),
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn follows_redirects_to_final_page() {
+        let mut requested = Vec::new();
+        let page = fetch_with(
+            "http://example.test/start",
+            &FetchOptions::default(),
+            |url| {
+                requested.push(url.to_string());
+                Ok(match url {
+                    "http://example.test/start" => redirect("/moved"),
+                    "http://example.test/moved" => redirect("http://other.test:8080/final"),
+                    _ => HttpResponse {
+                        status: 200,
+                        headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+                        body: b"hello".to_vec(),
+                        truncated: false,
+                    },
+                })
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            requested,
+            [
+                "http://example.test/start",
+                "http://example.test/moved",
+                "http://other.test:8080/final"
+            ]
+        );
+        assert_eq!(page.url, "http://other.test:8080/final");
+        assert_eq!(page.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(page.text, "hello");
+    }
+
+    #[test]
+    fn stops_at_redirect_limit() {
+        let options = FetchOptions {
+            max_redirects: 3,
+            ..FetchOptions::default()
+        };
+        let mut requests = 0;
+        let err = fetch_with("http://loop.test/", &options, |_| {
+            requests += 1;
+            Ok(redirect("/again"))
+        })
+        .unwrap_err();
+
+        assert_eq!(requests, 4);
+        assert!(err.contains("too many redirects"), "{err}");
+    }
+
+    #[test]
+    fn caps_body_at_max_size() {
+        let raw = b"HTTP/1.0 200 OK\r\nContent-Type: text/html\r\nContent-Length: 26\r\n\r\nabcdefghijklmnopqrstuvwxyz";
+        let response = read_response(&raw[..], 10).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"abcdefghij");
+        assert!(response.truncated);
+
+        let no_length = b"HTTP/1.0 200 OK\r\n\r\nabcdefghijklmnopqrstuvwxyz";
+        let response = read_response(&no_length[..], 10).unwrap();
+        assert_eq!(response.body, b"abcdefghij");
+        assert!(response.truncated);
+
+        let response = read_response(&raw[..], 64).unwrap();
+        assert_eq!(response.body.len(), 26);
+        assert!(!response.truncated);
+    }
+}
@@ -1,4 +1,6 @@
-use platform_abi::{PlatformConfig, PlatformEvent, PlatformFrame};
+use std::ffi::c_char;
+
+use platform_abi::{PlatformConfig, PlatformDirtyRect, PlatformEvent, PlatformFrame};
 
 #[cfg(not(platform_stub))]
 unsafe extern "C" {
@@ -6,6 +8,12 @@ unsafe extern "C" {
     pub fn platform_init_window(config: *const PlatformConfig) -> u8;
     pub fn platform_poll_event(out_event: *mut PlatformEvent) -> u8;
     pub fn platform_present_frame(frame: *const PlatformFrame) -> u8;
+    pub fn platform_present_frame_partial(
+        frame: *const PlatformFrame,
+        rects: *const PlatformDirtyRect,
+        rect_count: u32,
+    ) -> u8;
+    pub fn platform_set_title(title_utf8: *const c_char) -> u8;
     pub fn platform_shutdown();
 }
 
@@ -33,6 +41,22 @@ pub unsafe extern "C" fn platform_present_frame(_frame: *const PlatformFrame) ->
     0
 }
 
+#[cfg(platform_stub)]
+#[no_mangle]
+pub unsafe extern "C" fn platform_present_frame_partial(
+    _frame: *const PlatformFrame,
+    _rects: *const PlatformDirtyRect,
+    _rect_count: u32,
+) -> u8 {
+    0
+}
+
+#[cfg(platform_stub)]
+#[no_mangle]
+pub unsafe extern "C" fn platform_set_title(_title_utf8: *const c_char) -> u8 {
+    0
+}
+
 #[cfg(platform_stub)]
 #[no_mangle]
 pub unsafe extern "C" fn platform_shutdown() {}
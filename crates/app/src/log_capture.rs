@@ -0,0 +1,123 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    sync::{Arc, Mutex, OnceLock},
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+static GLOBAL_CAPTURE: OnceLock<LogCapture> = OnceLock::new();
+
+// Keeps the most recent `capacity` formatted events; older lines are dropped first.
+#[derive(Debug, Clone)]
+pub struct LogCapture {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl LogCapture {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    // Registers this buffer as the one `captured_logs` reads; only the first call wins.
+    pub fn install_global(self) -> Self {
+        GLOBAL_CAPTURE.get_or_init(|| self).clone()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines
+            .lock()
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn push(&self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        while lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+pub fn captured_logs() -> Vec<String> {
+    GLOBAL_CAPTURE
+        .get()
+        .map(LogCapture::lines)
+        .unwrap_or_default()
+}
+
+impl<S: Subscriber> Layer<S> for LogCapture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        self.push(format!(
+            "{} {}: {}{}",
+            metadata.level(),
+            metadata.target(),
+            visitor.message,
+            visitor.fields
+        ));
+    }
+}
+
+#[derive(Default)]
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::info;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn keeps_only_the_most_recent_lines() {
+        let capture = LogCapture::with_capacity(2);
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            info!("first");
+            info!(frame = 2, "second");
+            info!(name = "x", "third");
+        });
+
+        let lines = capture.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("second frame=2"), "{lines:?}");
+        assert!(lines[1].ends_with("third name=x"), "{lines:?}");
+        assert!(lines[1].starts_with("INFO "), "{lines:?}");
+    }
+}
@@ -1,32 +1,66 @@
+mod error;
+mod event_log;
+mod fetch;
 mod ffi;
+mod log_capture;
 
-use engine::{render_document, DisplayCommand};
+use engine::{
+    clickable_regions, debug_tokens, document_title, parse_css_color, relayout, render_document,
+    DisplayCommand, Document, LayoutBox, ScriptSnippet, TextAlign, TextDirection,
+};
 use engine_loop::Scheduler;
+use error::AppError;
+use event_log::{EventLog, EventRecorder, LoggedFrame};
+use fetch::{fetch_url, is_http_url, FetchOptions};
 #[cfg(feature = "process-split")]
 use ipc::{BrowserToContent, InProcessTransport};
+use log_capture::{captured_logs, LogCapture};
 use platform_abi::{
-    PlatformConfig, PlatformEvent, PlatformFrame, PLATFORM_ABI_VERSION, PLATFORM_EVENT_KEY_DOWN,
-    PLATFORM_EVENT_QUIT, PLATFORM_EVENT_RESIZE, PLATFORM_FALSE, PLATFORM_KEY_ESCAPE,
-    PLATFORM_KEY_S,
+    PlatformConfig, PlatformDirtyRect, PlatformEvent, PlatformFrame, PLATFORM_ABI_VERSION,
+    PLATFORM_EVENT_KEY_DOWN, PLATFORM_EVENT_MOUSE_MOVE, PLATFORM_EVENT_QUIT, PLATFORM_EVENT_RESIZE,
+    PLATFORM_FALSE, PLATFORM_KEY_1, PLATFORM_KEY_3, PLATFORM_KEY_ENTER, PLATFORM_KEY_ESCAPE,
+    PLATFORM_KEY_H, PLATFORM_KEY_I, PLATFORM_KEY_S, PLATFORM_KEY_TAB, PLATFORM_MODIFIER_SHIFT,
+};
+use renderer::{
+    frame_hash, luma, DirtyRect, DrawRect, DrawText, OverlayCorner, OverlayInfo, Pattern, Renderer,
+    RgbaHeader, Rotation, Spotlight,
 };
-use renderer::{DrawRect, DrawText, OverlayInfo, Pattern, Renderer};
-use script_host::{ScriptError, ScriptHost, StubScriptHost};
+use script_host::{console_log_args, ScriptError, ScriptHost, StubScriptHost};
 use std::{
     ffi::CString,
     fs,
     mem::MaybeUninit,
     path::{Path, PathBuf},
-    sync::atomic::{AtomicBool, Ordering},
-    time::Instant,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::Level;
+use tracing::{debug, info, trace, warn};
+use tracing_subscriber::{
+    filter::Targets, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
 };
-use tracing::{debug, info, warn};
-use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, Clone)]
 enum Command {
     Run(RunArgs),
     Headless(HeadlessArgs),
     Golden(GoldenArgs),
+    Dump(DumpArgs),
+    Diff(DiffArgs),
+    Replay(ReplayArgs),
+}
+
+// Set by `--verbose`/`--quiet` ahead of the subcommand; the last one given wins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Verbosity {
+    #[default]
+    Normal,
+    Verbose,
+    Quiet,
 }
 
 #[derive(Debug, Clone)]
@@ -35,16 +69,93 @@ struct RunArgs {
     input: Option<PathBuf>,
     width: u32,
     height: u32,
+    rotation: Rotation,
+    background: Option<[u8; 4]>,
+    fetch: FetchOptions,
+    overlay_corner: OverlayCorner,
+    hash_frames: Option<u64>,
+    record_events: Option<PathBuf>,
+    script_host: ScriptHostChoice,
+    spotlight: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ReplayArgs {
+    run: RunArgs,
+    events: PathBuf,
 }
 
 #[derive(Debug, Clone)]
 struct HeadlessArgs {
-    input: PathBuf,
+    source: HeadlessSource,
     width: u32,
     height: u32,
     frame: u64,
     out_rgba: PathBuf,
     out_meta: Option<PathBuf>,
+    grayscale: bool,
+    background: Option<[u8; 4]>,
+    fetch: FetchOptions,
+    script_host: ScriptHostChoice,
+    with_header: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HeadlessSource {
+    Document(PathBuf),
+    Pattern(Pattern),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct HeadlessRenderOptions {
+    grayscale: bool,
+    background: Option<[u8; 4]>,
+    script_host: ScriptHostChoice,
+}
+
+// Which `ScriptHost` runs a document's inline scripts; `None` skips them entirely.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ScriptHostChoice {
+    #[default]
+    Stub,
+    Log,
+    None,
+}
+
+impl ScriptHostChoice {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "stub" => Ok(Self::Stub),
+            "log" => Ok(Self::Log),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "invalid value for --script-host: {value} (expected: stub|log|none)"
+            )),
+        }
+    }
+
+    fn create(self) -> Option<Box<dyn ScriptHost>> {
+        match self {
+            Self::Stub => Some(Box::new(StubScriptHost::default())),
+            Self::Log => Some(Box::new(LoggingScriptHost)),
+            Self::None => None,
+        }
+    }
+}
+
+// Logs each `console.log` call's arguments instead of running the script.
+#[derive(Debug, Default)]
+struct LoggingScriptHost;
+
+impl ScriptHost for LoggingScriptHost {
+    fn execute(&mut self, scripts: &[ScriptSnippet]) -> Result<(), ScriptError> {
+        for script in scripts {
+            for args in console_log_args(&script.code) {
+                info!(node_id = script.node_id, args, "console.log");
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,14 +165,72 @@ struct GoldenArgs {
     golden_dir: PathBuf,
     width: u32,
     height: u32,
+    sizes: Vec<(u32, u32)>,
     frame: u64,
+    filter: Option<String>,
+    list: bool,
+    report: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+struct DumpArgs {
+    input: PathBuf,
+    fetch: FetchOptions,
 }
 
+#[derive(Debug, Clone)]
+struct DiffArgs {
+    a: PathBuf,
+    b: PathBuf,
+    width: u32,
+    height: u32,
+    out: Option<PathBuf>,
+}
+
+const DIFF_HIGHLIGHT: [u8; 4] = [255, 0, 0, 255];
+
 #[derive(Debug, Clone)]
 struct DocumentScene {
-    html: String,
+    document: Document,
     rects: Vec<DrawRect>,
     texts: Vec<DrawText>,
+    links: Vec<(LayoutBox, String)>,
+    focused_link: Option<usize>,
+    title: Option<String>,
+}
+
+impl DocumentScene {
+    // Reuses the parsed document so resizes skip tokenizing and parsing.
+    fn relayout(&mut self, width: u32, height: u32) {
+        let (layout, display_list) = relayout(&self.document, width, height);
+        (self.rects, self.texts) = display_commands_to_scene(&display_list.commands);
+        self.links = clickable_regions(&self.document, &layout);
+        self.focused_link = self
+            .focused_link
+            .filter(|_| !self.links.is_empty())
+            .map(|index| index.min(self.links.len() - 1));
+    }
+
+    fn cycle_link_focus(&mut self, backwards: bool) {
+        self.focused_link = advance_focus(self.focused_link, self.links.len(), backwards);
+    }
+
+    fn focused_link(&self) -> Option<&(LayoutBox, String)> {
+        self.focused_link.and_then(|index| self.links.get(index))
+    }
+
+    fn focus_outline(&self) -> Option<[DrawRect; 4]> {
+        self.focused_link().map(|(layout_box, _)| {
+            draw_rect(
+                layout_box.x,
+                layout_box.y,
+                layout_box.width,
+                layout_box.height,
+                FOCUS_OUTLINE_COLOR,
+            )
+            .outline(2)
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -70,28 +239,60 @@ struct CustomizationState {
     selected_font_index: usize,
 }
 
+const FOCUS_OUTLINE_COLOR: [u8; 4] = [255, 170, 0, 255];
+
 static SCRIPT_HOST_UNSUPPORTED_WARNED: AtomicBool = AtomicBool::new(false);
 
 fn main() {
     if let Err(err) = try_main() {
         eprintln!("browser failed: {err}");
+        let recent = captured_logs();
+        if !recent.is_empty() {
+            eprintln!("recent log lines:");
+            for line in recent {
+                eprintln!("  {line}");
+            }
+        }
         std::process::exit(1);
     }
 }
 
-fn try_main() -> Result<(), String> {
-    init_tracing();
-    let command = parse_cli(std::env::args().skip(1))?;
+fn try_main() -> Result<(), AppError> {
+    let (verbosity, args) = parse_global_flags(std::env::args().skip(1));
+    init_tracing(verbosity);
+    let command = parse_cli(args.into_iter())?;
     process_split_bootstrap();
 
     match command {
         Command::Run(args) => run_windowed(args),
         Command::Headless(args) => run_headless(args),
         Command::Golden(args) => run_golden(args),
+        Command::Dump(args) => run_dump(args),
+        Command::Diff(args) => run_diff(args),
+        Command::Replay(args) => run_replay(args),
+    }
+}
+
+// Splits the leading global flags off the command line; anything after them is left for
+// `parse_cli`, so `run --verbose` is still a run flag error.
+fn parse_global_flags(args: impl Iterator<Item = String>) -> (Verbosity, Vec<String>) {
+    let mut verbosity = Verbosity::Normal;
+    let mut args = args.peekable();
+    while let Some(flag) = args.next_if(|arg| arg == "--verbose" || arg == "--quiet") {
+        verbosity = if flag == "--verbose" {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Quiet
+        };
     }
+    (verbosity, args.collect())
+}
+
+fn parse_cli(args: impl Iterator<Item = String>) -> Result<Command, AppError> {
+    parse_command(args).map_err(AppError::InvalidArgs)
 }
 
-fn parse_cli(args: impl Iterator<Item = String>) -> Result<Command, String> {
+fn parse_command(args: impl Iterator<Item = String>) -> Result<Command, String> {
     let mut args: Vec<String> = args.collect();
 
     if args.is_empty() {
@@ -100,38 +301,54 @@ fn parse_cli(args: impl Iterator<Item = String>) -> Result<Command, String> {
             input: default_document_input_path(),
             width: 960,
             height: 540,
+            rotation: Rotation::Deg0,
+            background: None,
+            fetch: FetchOptions::default(),
+            overlay_corner: OverlayCorner::TopLeft,
+            hash_frames: None,
+            record_events: None,
+            script_host: ScriptHostChoice::default(),
+            spotlight: false,
         }));
     }
 
     let command = args.remove(0);
     match command.as_str() {
-        "run" => parse_run_args(args.into_iter()),
+        "run" => parse_run_args(args.into_iter()).map(Command::Run),
         "headless" => parse_headless_args(args.into_iter()),
         "golden" => parse_golden_args(args.into_iter()),
+        "dump" => parse_dump_args(args.into_iter()),
+        "diff" => parse_diff_args(args.into_iter()),
+        "replay" => parse_replay_args(args.into_iter()),
         flag if flag.starts_with("--") => {
-            parse_run_args(std::iter::once(flag.to_string()).chain(args))
+            parse_run_args(std::iter::once(flag.to_string()).chain(args)).map(Command::Run)
         }
         other => Err(format!(
-            "unknown command '{other}' (expected: run|headless|golden)"
+            "unknown command '{other}' (expected: run|headless|golden|dump|diff|replay)"
         )),
     }
 }
 
-fn parse_run_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+fn parse_run_args(args: impl Iterator<Item = String>) -> Result<RunArgs, String> {
     let mut pattern = Pattern::Gradient;
     let mut input = None;
     let mut pattern_only = false;
     let mut width = 960_u32;
     let mut height = 540_u32;
+    let mut rotation = Rotation::Deg0;
+    let mut background = None;
+    let mut fetch = FetchOptions::default();
+    let mut overlay_corner = OverlayCorner::TopLeft;
+    let mut hash_frames = None;
+    let mut record_events = None;
+    let mut script_host = ScriptHostChoice::default();
+    let mut spotlight = false;
 
     let mut args = args.peekable();
     while let Some(arg) = args.next() {
         match arg.as_str() {
             "--pattern" => {
-                let value = next_arg(&mut args, "--pattern")?;
-                pattern = Pattern::parse(&value).ok_or_else(|| {
-                    format!("unknown pattern '{value}' (expected: gradient|solid|rects)")
-                })?;
+                pattern = parse_pattern(&next_arg(&mut args, "--pattern")?)?;
             }
             "--input" => {
                 input = Some(PathBuf::from(next_arg(&mut args, "--input")?));
@@ -145,6 +362,33 @@ fn parse_run_args(args: impl Iterator<Item = String>) -> Result<Command, String>
             "--height" => {
                 height = parse_u32(&next_arg(&mut args, "--height")?, "--height")?;
             }
+            "--rotate" => {
+                let value = next_arg(&mut args, "--rotate")?;
+                rotation = parse_u32(&value, "--rotate")
+                    .ok()
+                    .and_then(Rotation::from_degrees)
+                    .ok_or_else(|| {
+                        format!("invalid value for --rotate: {value} (expected: 0|90|180|270)")
+                    })?;
+            }
+            "--bg" => {
+                background = Some(parse_color(&next_arg(&mut args, "--bg")?, "--bg")?);
+            }
+            "--max-body" | "--max-redirects" => parse_fetch_flag(&arg, &mut args, &mut fetch)?,
+            "--overlay-corner" => {
+                overlay_corner = parse_overlay_corner(&next_arg(&mut args, "--overlay-corner")?)?;
+            }
+            "--hash-frames" => {
+                let value = next_arg(&mut args, "--hash-frames")?;
+                hash_frames = Some(parse_u64(&value, "--hash-frames")?);
+            }
+            "--record-events" => {
+                record_events = Some(PathBuf::from(next_arg(&mut args, "--record-events")?));
+            }
+            "--script-host" => {
+                script_host = ScriptHostChoice::parse(&next_arg(&mut args, "--script-host")?)?;
+            }
+            "--spotlight" => spotlight = true,
             _ => return Err(format!("unknown run flag '{arg}'")),
         }
     }
@@ -155,21 +399,45 @@ fn parse_run_args(args: impl Iterator<Item = String>) -> Result<Command, String>
         input.or_else(default_document_input_path)
     };
 
-    Ok(Command::Run(RunArgs {
+    // Headless output may be empty, but the platform cannot open a zero-sized window.
+    if width == 0 || height == 0 {
+        return Err(format!(
+            "run needs a non-zero window size (got {width}x{height})"
+        ));
+    }
+    if hash_frames.is_some() && record_events.is_some() {
+        return Err("--record-events needs a window and cannot be used with --hash-frames".into());
+    }
+
+    Ok(RunArgs {
         pattern,
         input,
         width,
         height,
-    }))
+        rotation,
+        background,
+        fetch,
+        overlay_corner,
+        hash_frames,
+        record_events,
+        script_host,
+        spotlight,
+    })
 }
 
 fn parse_headless_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
     let mut input = None;
+    let mut pattern = None;
     let mut out_rgba = None;
     let mut out_meta = None;
     let mut width = 960_u32;
     let mut height = 540_u32;
     let mut frame = 0_u64;
+    let mut grayscale = false;
+    let mut background = None;
+    let mut fetch = FetchOptions::default();
+    let mut script_host = ScriptHostChoice::default();
+    let mut with_header = false;
 
     let mut args = args.peekable();
     while let Some(arg) = args.next() {
@@ -177,6 +445,9 @@ fn parse_headless_args(args: impl Iterator<Item = String>) -> Result<Command, St
             "--input" => {
                 input = Some(PathBuf::from(next_arg(&mut args, "--input")?));
             }
+            "--pattern" => {
+                pattern = Some(parse_pattern(&next_arg(&mut args, "--pattern")?)?);
+            }
             "--out" | "--out-rgba" => {
                 out_rgba = Some(PathBuf::from(next_arg(&mut args, "--out-rgba")?));
             }
@@ -192,21 +463,44 @@ fn parse_headless_args(args: impl Iterator<Item = String>) -> Result<Command, St
             "--frame" => {
                 frame = parse_u64(&next_arg(&mut args, "--frame")?, "--frame")?;
             }
+            "--grayscale" => grayscale = true,
+            "--with-header" => with_header = true,
+            "--bg" => {
+                background = Some(parse_color(&next_arg(&mut args, "--bg")?, "--bg")?);
+            }
+            "--max-body" | "--max-redirects" => parse_fetch_flag(&arg, &mut args, &mut fetch)?,
+            "--script-host" => {
+                script_host = ScriptHostChoice::parse(&next_arg(&mut args, "--script-host")?)?;
+            }
             _ => return Err(format!("unknown headless flag '{arg}'")),
         }
     }
 
-    let input = input.ok_or_else(|| "headless requires --input <path>".to_string())?;
+    let source = match (input, pattern) {
+        (Some(input), None) => HeadlessSource::Document(input),
+        (None, Some(pattern)) => HeadlessSource::Pattern(pattern),
+        (Some(_), Some(_)) => {
+            return Err("headless --input and --pattern are mutually exclusive".to_string())
+        }
+        (None, None) => {
+            return Err("headless requires --input <path> or --pattern <name>".to_string())
+        }
+    };
     let out_rgba = out_rgba
         .ok_or_else(|| "headless requires --out-rgba <path> (or --out <path>)".to_string())?;
 
     Ok(Command::Headless(HeadlessArgs {
-        input,
+        source,
         width,
         height,
         frame,
         out_rgba,
         out_meta,
+        grayscale,
+        background,
+        fetch,
+        script_host,
+        with_header,
     }))
 }
 
@@ -216,7 +510,11 @@ fn parse_golden_args(args: impl Iterator<Item = String>) -> Result<Command, Stri
     let mut golden_dir = PathBuf::from("tests/golden");
     let mut width = 960_u32;
     let mut height = 540_u32;
+    let mut sizes = Vec::new();
     let mut frame = 0_u64;
+    let mut filter = None;
+    let mut list = false;
+    let mut report = None;
 
     let mut args = args.peekable();
     while let Some(arg) = args.next() {
@@ -237,6 +535,16 @@ fn parse_golden_args(args: impl Iterator<Item = String>) -> Result<Command, Stri
             "--frame" => {
                 frame = parse_u64(&next_arg(&mut args, "--frame")?, "--frame")?;
             }
+            "--sizes" => {
+                sizes = parse_sizes(&next_arg(&mut args, "--sizes")?)?;
+            }
+            "--filter" => {
+                filter = Some(next_arg(&mut args, "--filter")?);
+            }
+            "--list" => list = true,
+            "--report" => {
+                report = Some(PathBuf::from(next_arg(&mut args, "--report")?));
+            }
             _ => return Err(format!("unknown golden flag '{arg}'")),
         }
     }
@@ -247,72 +555,182 @@ fn parse_golden_args(args: impl Iterator<Item = String>) -> Result<Command, Stri
         golden_dir,
         width,
         height,
+        sizes,
         frame,
+        filter,
+        list,
+        report,
     }))
 }
 
-fn run_windowed(args: RunArgs) -> Result<(), String> {
-    let title = CString::new("Browser")
-        .map_err(|_| "window title contains interior null byte".to_string())?;
+fn parse_dump_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut input = None;
+    let mut fetch = FetchOptions::default();
+
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => {
+                input = Some(PathBuf::from(next_arg(&mut args, "--input")?));
+            }
+            "--max-body" | "--max-redirects" => parse_fetch_flag(&arg, &mut args, &mut fetch)?,
+            _ => return Err(format!("unknown dump flag '{arg}'")),
+        }
+    }
+
+    let input = input.ok_or_else(|| "dump requires --input <path>".to_string())?;
+    Ok(Command::Dump(DumpArgs { input, fetch }))
+}
 
-    let mut width = args.width;
-    let mut height = args.height;
+fn parse_diff_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut a = None;
+    let mut b = None;
+    let mut width = None;
+    let mut height = None;
+    let mut out = None;
 
-    let mut document_scene = if let Some(input) = &args.input {
-        let html = fs::read_to_string(input)
-            .map_err(|err| format!("failed to read {}: {err}", input.display()))?;
-        Some(build_document_scene(&html, width, height))
-    } else {
-        None
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--a" => a = Some(PathBuf::from(next_arg(&mut args, "--a")?)),
+            "--b" => b = Some(PathBuf::from(next_arg(&mut args, "--b")?)),
+            "--width" => {
+                width = Some(parse_u32(&next_arg(&mut args, "--width")?, "--width")?);
+            }
+            "--height" => {
+                height = Some(parse_u32(&next_arg(&mut args, "--height")?, "--height")?);
+            }
+            "--out" => out = Some(PathBuf::from(next_arg(&mut args, "--out")?)),
+            _ => return Err(format!("unknown diff flag '{arg}'")),
+        }
+    }
+
+    Ok(Command::Diff(DiffArgs {
+        a: a.ok_or_else(|| "diff requires --a <path>".to_string())?,
+        b: b.ok_or_else(|| "diff requires --b <path>".to_string())?,
+        width: width.ok_or_else(|| "diff requires --width <px>".to_string())?,
+        height: height.ok_or_else(|| "diff requires --height <px>".to_string())?,
+        out,
+    }))
+}
+
+// `replay --events <log>` takes the run flags the session was recorded with.
+fn parse_replay_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut events = None;
+    let mut run_flags = Vec::new();
+
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--events" => events = Some(PathBuf::from(next_arg(&mut args, "--events")?)),
+            "--hash-frames" | "--record-events" => {
+                return Err(format!("{arg} is not supported by replay"));
+            }
+            _ => run_flags.push(arg),
+        }
+    }
+
+    Ok(Command::Replay(ReplayArgs {
+        run: parse_run_args(run_flags.into_iter())?,
+        events: events.ok_or_else(|| "replay requires --events <path>".to_string())?,
+    }))
+}
+
+// Shown until a document with a non-empty `<title>` loads.
+const DEFAULT_WINDOW_TITLE: &str = "Browser";
+
+fn run_windowed(args: RunArgs) -> Result<(), AppError> {
+    if let Some(frames) = args.hash_frames {
+        for hash in windowless_frame_hashes(&args, frames)? {
+            println!("{hash:016x}");
+        }
+        return Ok(());
+    }
+
+    let title = CString::new(DEFAULT_WINDOW_TITLE)
+        .map_err(|_| AppError::Other("window title contains interior null byte".to_string()))?;
+    let mut window_title = DEFAULT_WINDOW_TITLE.to_string();
+
+    // Local files load before the window opens; URLs are fetched while a loading frame shows.
+    let mut pending_document = None;
+    let document_scene = match &args.input {
+        Some(input) if input.to_str().is_some_and(is_http_url) => {
+            pending_document = Some(PendingDocument::spawn(input.clone(), args.fetch));
+            None
+        }
+        Some(input) => {
+            let html = load_input(input, &args.fetch)?;
+            Some(build_document_scene(
+                &html,
+                args.width,
+                args.height,
+                args.script_host,
+            ))
+        }
+        None => None,
     };
 
+    // Width/height are the logical (unrotated) size; the window matches the rotated output.
+    let (window_width, window_height) = if args.rotation.swaps_axes() {
+        (args.height, args.width)
+    } else {
+        (args.width, args.height)
+    };
     let config = PlatformConfig {
         struct_size: std::mem::size_of::<PlatformConfig>() as u32,
         abi_version: PLATFORM_ABI_VERSION,
-        width,
-        height,
+        width: window_width,
+        height: window_height,
         title_utf8: title.as_ptr(),
     };
 
     let runtime_abi = unsafe { ffi::platform_get_abi_version() };
     if runtime_abi != PLATFORM_ABI_VERSION {
-        return Err(format!(
-            "platform ABI mismatch: runtime={runtime_abi}, expected={PLATFORM_ABI_VERSION}. If Zig is not installed, use headless mode."
-        ));
+        return Err(AppError::PlatformAbiMismatch {
+            runtime: runtime_abi,
+            expected: PLATFORM_ABI_VERSION,
+        });
     }
 
     let initialized = unsafe { ffi::platform_init_window(&config as *const PlatformConfig) };
     if initialized == PLATFORM_FALSE {
-        return Err("platform_init_window returned false".to_string());
+        return Err(AppError::Other(
+            "platform_init_window returned false".to_string(),
+        ));
     }
 
-    let mut renderer = Renderer::new(width, height);
-    renderer.set_pattern(args.pattern);
-    let overlay_enabled = true;
-    let mut customization = CustomizationState {
-        open: false,
-        selected_font_index: renderer.current_font_index(),
-    };
+    let mut renderer = windowed_renderer(&args);
+    let mut state = WindowState::new(&args, &renderer, document_scene);
+    if let (Some(input), Some(_)) = (&args.input, &pending_document) {
+        state.loading = Some(format!("Loading {}", input.display()));
+    }
     info!(
         current_font = renderer.current_font_name(),
         total_fonts = renderer.font_count(),
         "font catalog ready"
     );
 
+    let mut recorder = args
+        .record_events
+        .as_deref()
+        .map(EventRecorder::create)
+        .transpose()?;
     let mut scheduler = Scheduler::new(60).with_max_updates_per_frame(4);
-    let mut last_tick = Instant::now();
-    let mut simulation_time_seconds = 0.0_f32;
-    let mut running = true;
+    let started = Instant::now();
+    let mut last_tick = started;
+    let mut events = Vec::new();
+    let mut outcome = Ok(());
 
     info!(
-        width,
-        height,
+        width = state.width,
+        height = state.height,
         ?args.pattern,
-        has_document = document_scene.is_some(),
+        has_document = state.document_scene.is_some(),
         "starting runtime"
     );
 
-    while running {
+    loop {
+        events.clear();
         loop {
             let mut event = MaybeUninit::<PlatformEvent>::zeroed();
             unsafe {
@@ -322,95 +740,61 @@ fn run_windowed(args: RunArgs) -> Result<(), String> {
             if has_event == PLATFORM_FALSE {
                 break;
             }
+            events.push(unsafe { event.assume_init() });
+        }
 
-            let event = unsafe { event.assume_init() };
-            match event.kind {
-                PLATFORM_EVENT_QUIT => running = false,
-                PLATFORM_EVENT_KEY_DOWN => {
-                    if customization.open {
-                        if event.key_code == PLATFORM_KEY_ESCAPE {
-                            customization.open = false;
-                        }
-                    } else if event.key_code == PLATFORM_KEY_S {
-                        customization.open = true;
-                        customization.selected_font_index = renderer.current_font_index();
-                    }
+        let mut document_loaded = false;
+        if let Some(result) = pending_document.as_ref().and_then(PendingDocument::poll) {
+            pending_document = None;
+            match result {
+                Ok(html) => {
+                    state.document_loaded(&html);
+                    document_loaded = true;
                 }
-                PLATFORM_EVENT_RESIZE => {
-                    if event.width > 0
-                        && event.height > 0
-                        && (event.width != width || event.height != height)
-                    {
-                        width = event.width;
-                        height = event.height;
-                        renderer.resize(width, height);
-                        if let Some(scene) = &mut document_scene {
-                            *scene = build_document_scene(&scene.html, width, height);
-                        }
-                        debug!(width, height, "resized");
-                    }
+                Err(err) => {
+                    outcome = Err(err);
+                    break;
                 }
-                _ => {}
             }
         }
 
-        if !running {
-            break;
-        }
-
         let now = Instant::now();
         let dt = now.saturating_duration_since(last_tick);
         last_tick = now;
 
-        let timing = scheduler.advance_with_fixed_updates(dt, |step| {
-            simulation_time_seconds += step.as_secs_f32();
-        });
-        let time_seconds = simulation_time_seconds;
-
-        let overlay = OverlayInfo {
-            frame_index: timing.frame_index,
-            fps: timing.fps,
-            width,
-            height,
-        };
-        let overlay = overlay_enabled.then_some(overlay);
-
-        let framebuffer = if let Some(scene) = &document_scene {
-            let (rects, texts) = if customization.open {
-                let (popup_rects, popup_texts) = build_customization_popup(
-                    &renderer,
-                    width,
-                    height,
-                    customization.selected_font_index,
-                );
-                let mut merged_rects = scene.rects.clone();
-                let mut merged_texts = scene.texts.clone();
-                merged_rects.extend(popup_rects);
-                merged_texts.extend(popup_texts);
-                (merged_rects, merged_texts)
-            } else {
-                (scene.rects.clone(), scene.texts.clone())
+        if let Some(recorder) = &mut recorder {
+            let frame = LoggedFrame {
+                elapsed: now.saturating_duration_since(started),
+                events: events.clone(),
+                document_loaded,
             };
+            if let Err(err) = recorder.record(&frame) {
+                outcome = Err(err);
+                break;
+            }
+        }
 
-            renderer.render_display_list(
-                timing.frame_index,
-                time_seconds,
-                &rects,
-                &texts,
-                overlay,
-            )
-        } else {
-            renderer.render_pattern(timing.frame_index, time_seconds, overlay)
+        let PresentDecision::Present { width, height } =
+            step_frame(&mut state, &mut renderer, &mut scheduler, dt, &events)
+        else {
+            break;
         };
 
-        debug!(
-            frame = timing.frame_index,
-            dt_seconds = timing.dt_seconds,
-            fps = timing.fps,
-            fixed_updates = timing.fixed_updates,
-            "frame timing"
-        );
+        let document_title = state
+            .document_scene
+            .as_ref()
+            .and_then(|scene| scene.title.as_deref())
+            .unwrap_or(DEFAULT_WINDOW_TITLE);
+        if document_title != window_title {
+            match set_window_title(document_title) {
+                Ok(true) => debug!(title = document_title, "window title updated"),
+                Ok(false) => warn!(title = document_title, "platform_set_title returned false"),
+                Err(err) => warn!(%err, "window title not updated"),
+            }
+            window_title = document_title.to_string();
+        }
 
+        let framebuffer = renderer.framebuffer();
         let frame = PlatformFrame {
             struct_size: std::mem::size_of::<PlatformFrame>() as u32,
             width,
@@ -419,23 +803,355 @@ fn run_windowed(args: RunArgs) -> Result<(), String> {
             pixels_rgba8: framebuffer.as_ptr(),
         };
 
-        let presented = unsafe { ffi::platform_present_frame(&frame as *const PlatformFrame) };
-        if presented == PLATFORM_FALSE {
-            running = false;
+        if !frame_matches_buffer(&frame, framebuffer.len()) {
+            warn!(
+                width = frame.width,
+                height = frame.height,
+                stride_bytes = frame.stride_bytes,
+                buffer_len = framebuffer.len(),
+                "framebuffer does not match frame header; skipping present"
+            );
+            continue;
+        }
+
+        if !present_frame_partial(&frame, renderer.last_dirty_rects()) {
+            break;
         }
     }
 
     unsafe { ffi::platform_shutdown() };
+    outcome
+}
+
+fn windowed_renderer(args: &RunArgs) -> Renderer {
+    Renderer::builder()
+        .width(args.width)
+        .height(args.height)
+        .pattern(args.pattern)
+        .rotation(args.rotation)
+        .clear_color(args.background)
+        .build()
+}
+
+// Runs the windowed frame loop with no platform window (so it also works under the stub
+// platform): no events and a fixed 60 Hz step, hashing each frame as it would be presented.
+fn windowless_frame_hashes(args: &RunArgs, frames: u64) -> Result<Vec<u64>, AppError> {
+    let document_scene = match &args.input {
+        Some(input) => {
+            let html = load_input(input, &args.fetch)?;
+            Some(build_document_scene(
+                &html,
+                args.width,
+                args.height,
+                args.script_host,
+            ))
+        }
+        None => None,
+    };
+    let mut renderer = windowed_renderer(args);
+    let mut state = WindowState::new(args, &renderer, document_scene);
+    let mut scheduler = Scheduler::new(60).with_max_updates_per_frame(4);
+    let dt = Duration::from_secs(1) / 60;
+
+    let mut hashes = Vec::new();
+    for _ in 0..frames {
+        if step_frame(&mut state, &mut renderer, &mut scheduler, dt, &[]) == PresentDecision::Stop {
+            break;
+        }
+        hashes.push(frame_hash(renderer.framebuffer()));
+    }
+    Ok(hashes)
+}
+
+fn run_replay(args: ReplayArgs) -> Result<(), AppError> {
+    let log = EventLog::load(&args.events)?;
+    for hash in replay_frame_hashes(&args.run, &log)? {
+        println!("{hash:016x}");
+    }
     Ok(())
 }
 
-fn run_headless(args: HeadlessArgs) -> Result<(), String> {
-    let html = fs::read_to_string(&args.input)
-        .map_err(|err| format!("failed to read {}: {err}", args.input.display()))?;
+// Steps a recorded session through the windowed frame loop with no platform window, using the
+// recorded events and frame times. A fetched document is applied on the frame it arrived in.
+fn replay_frame_hashes(args: &RunArgs, log: &EventLog) -> Result<Vec<u64>, AppError> {
+    let html = args
+        .input
+        .as_deref()
+        .map(|input| load_input(input, &args.fetch))
+        .transpose()?;
+    let fetched = args
+        .input
+        .as_ref()
+        .filter(|input| input.to_str().is_some_and(is_http_url));
+    let document_scene = html
+        .as_deref()
+        .filter(|_| fetched.is_none())
+        .map(|html| build_document_scene(html, args.width, args.height, args.script_host));
+    let mut renderer = windowed_renderer(args);
+    let mut state = WindowState::new(args, &renderer, document_scene);
+    if let Some(input) = fetched {
+        state.loading = Some(format!("Loading {}", input.display()));
+    }
+    let mut scheduler = Scheduler::new(60).with_max_updates_per_frame(4);
+
+    let mut hashes = Vec::new();
+    let mut previous = Duration::ZERO;
+    for frame in &log.frames {
+        if let Some(html) = html.as_deref().filter(|_| frame.document_loaded) {
+            state.document_loaded(html);
+        }
+        let dt = frame.elapsed.saturating_sub(previous);
+        previous = frame.elapsed;
+        if step_frame(&mut state, &mut renderer, &mut scheduler, dt, &frame.events)
+            == PresentDecision::Stop
+        {
+            break;
+        }
+        hashes.push(frame_hash(renderer.framebuffer()));
+    }
+    Ok(hashes)
+}
+
+// A document fetched on a worker thread so the window keeps drawing while it loads.
+struct PendingDocument {
+    receiver: mpsc::Receiver<Result<String, AppError>>,
+}
+
+impl PendingDocument {
+    fn spawn(input: PathBuf, fetch: FetchOptions) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(load_input(&input, &fetch));
+        });
+        Self { receiver }
+    }
+
+    fn poll(&self) -> Option<Result<String, AppError>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(AppError::Other(
+                "document loader exited without a result".to_string(),
+            ))),
+        }
+    }
+}
+
+struct WindowState {
+    width: u32,
+    height: u32,
+    rotation: Rotation,
+    overlay_corner: OverlayCorner,
+    overlay_enabled: bool,
+    document_scene: Option<DocumentScene>,
+    // Message for the loading frame, shown until `document_loaded` is called.
+    loading: Option<String>,
+    customization: CustomizationState,
+    script_host: ScriptHostChoice,
+    spotlight: bool,
+    // Last pointer position from a mouse event, in presented pixels.
+    pointer: Option<(i32, i32)>,
+}
+
+impl WindowState {
+    fn new(args: &RunArgs, renderer: &Renderer, document_scene: Option<DocumentScene>) -> Self {
+        Self {
+            width: args.width,
+            height: args.height,
+            rotation: args.rotation,
+            overlay_corner: args.overlay_corner,
+            overlay_enabled: true,
+            document_scene,
+            loading: None,
+            customization: CustomizationState {
+                open: false,
+                selected_font_index: renderer.current_font_index(),
+            },
+            script_host: args.script_host,
+            spotlight: args.spotlight,
+            pointer: None,
+        }
+    }
+
+    // With --spotlight, a soft circle around the pointer once it has moved over the window.
+    fn spotlight(&self) -> Option<Spotlight> {
+        let (x, y) = self.pointer.filter(|_| self.spotlight)?;
+        Some(Spotlight {
+            x,
+            y,
+            radius: (self.width.min(self.height) / 6).max(24),
+        })
+    }
+
+    fn document_loaded(&mut self, html: &str) {
+        self.document_scene = Some(build_document_scene(
+            html,
+            self.width,
+            self.height,
+            self.script_host,
+        ));
+        self.loading = None;
+    }
+
+    // Flips whether the HUD overlay is drawn and returns the new state.
+    fn toggle_overlay(&mut self) -> bool {
+        self.overlay_enabled = !self.overlay_enabled;
+        self.overlay_enabled
+    }
+
+    fn handle_event(&mut self, renderer: &mut Renderer, event: &PlatformEvent) {
+        match event.kind {
+            PLATFORM_EVENT_KEY_DOWN => {
+                if self.customization.open {
+                    if event.key_code == PLATFORM_KEY_ESCAPE {
+                        self.customization.open = false;
+                    }
+                } else if event.key_code == PLATFORM_KEY_S {
+                    self.customization.open = true;
+                    self.customization.selected_font_index = renderer.current_font_index();
+                } else if event.key_code == PLATFORM_KEY_TAB {
+                    if let Some(scene) = &mut self.document_scene {
+                        scene.cycle_link_focus(event.modifiers & PLATFORM_MODIFIER_SHIFT != 0);
+                        debug!(focused_link = ?scene.focused_link, "link focus moved");
+                    }
+                } else if event.key_code == PLATFORM_KEY_ENTER {
+                    if let Some((_, href)) = self
+                        .document_scene
+                        .as_ref()
+                        .and_then(DocumentScene::focused_link)
+                    {
+                        info!(href, "link activated");
+                    }
+                } else if event.key_code == PLATFORM_KEY_I {
+                    renderer.set_invert(!renderer.is_inverted());
+                    debug!(inverted = renderer.is_inverted(), "color inversion toggled");
+                } else if event.key_code == PLATFORM_KEY_H {
+                    let overlay = self.toggle_overlay();
+                    debug!(overlay, "overlay toggled");
+                } else if let Some(pattern) = pattern_for_key(event.key_code, renderer.pattern()) {
+                    renderer.set_pattern(pattern);
+                    debug!(?pattern, "pattern selected");
+                }
+            }
+            PLATFORM_EVENT_RESIZE
+                if event.width > 0
+                    && event.height > 0
+                    && renderer.presented_size() != (event.width, event.height) =>
+            {
+                (self.width, self.height) = if self.rotation.swaps_axes() {
+                    (event.height, event.width)
+                } else {
+                    (event.width, event.height)
+                };
+                renderer.resize(self.width, self.height);
+                if let Some(scene) = &mut self.document_scene {
+                    scene.relayout(self.width, self.height);
+                }
+                debug!(width = self.width, height = self.height, "resized");
+            }
+            PLATFORM_EVENT_MOUSE_MOVE => self.pointer = Some((event.x, event.y)),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PresentDecision {
+    Stop,
+    // The frame is in `renderer.framebuffer()` at this presented (rotated) size.
+    Present { width: u32, height: u32 },
+}
+
+// One iteration of the windowed loop minus polling and presenting, so tests can drive it
+// with synthetic events and frame times.
+fn step_frame(
+    state: &mut WindowState,
+    renderer: &mut Renderer,
+    scheduler: &mut Scheduler,
+    dt: Duration,
+    events: &[PlatformEvent],
+) -> PresentDecision {
+    for event in events {
+        if event.kind == PLATFORM_EVENT_QUIT {
+            return PresentDecision::Stop;
+        }
+        state.handle_event(renderer, event);
+    }
+
+    let timing = scheduler.advance(dt);
+    let time_seconds = timing.total_seconds;
+
+    let overlay = OverlayInfo::builder()
+        .frame_index(timing.frame_index)
+        .fps(timing.fps)
+        .width(state.width)
+        .height(state.height)
+        .corner(state.overlay_corner)
+        .build();
+    let overlay = state.overlay_enabled.then_some(overlay);
+    renderer.set_spotlight(state.spotlight());
+
+    if let Some(scene) = &state.document_scene {
+        let mut rects = scene.rects.clone();
+        let mut texts = scene.texts.clone();
+        rects.extend(scene.focus_outline().into_iter().flatten());
+        if state.customization.open {
+            let (popup_rects, popup_texts) = build_customization_popup(
+                renderer,
+                state.width,
+                state.height,
+                state.customization.selected_font_index,
+            );
+            rects.extend(popup_rects);
+            texts.extend(popup_texts);
+        }
+
+        renderer.render_display_list(timing.frame_index, time_seconds, &rects, &texts, overlay);
+    } else if let Some(message) = &state.loading {
+        renderer.render_loading(timing.frame_index, message);
+    } else {
+        renderer.render_pattern(timing.frame_index, time_seconds, overlay);
+    }
+
+    trace!(
+        frame = timing.frame_index,
+        dt = timing.dt_seconds,
+        fps = timing.fps,
+        fixed_updates = timing.fixed_updates,
+        "frame timing"
+    );
 
-    let buffer = render_headless_buffer(&html, args.width, args.height, args.frame);
+    let (width, height) = renderer.presented_size();
+    PresentDecision::Present { width, height }
+}
+
+fn run_headless(args: HeadlessArgs) -> Result<(), AppError> {
+    let options = HeadlessRenderOptions {
+        grayscale: args.grayscale,
+        background: args.background,
+        script_host: args.script_host,
+    };
+    let buffer = match &args.source {
+        HeadlessSource::Document(input) => {
+            let html = load_input(input, &args.fetch)?;
+            render_headless_buffer(&html, args.width, args.height, args.frame, &options)
+        }
+        HeadlessSource::Pattern(pattern) => Renderer::builder()
+            .width(args.width)
+            .height(args.height)
+            .pattern(*pattern)
+            .grayscale(options.grayscale)
+            .build()
+            .render_pattern(args.frame, args.frame as f32 / 60.0, None)
+            .to_vec(),
+    };
 
-    write_file_with_parents(&args.out_rgba, &buffer)?;
+    if args.with_header {
+        let header = RgbaHeader::for_frame(args.width, args.height, &buffer);
+        write_file_with_parents(&args.out_rgba, &[&header.to_bytes()[..], &buffer].concat())?;
+    } else {
+        write_file_with_parents(&args.out_rgba, &buffer)?;
+    }
 
     if let Some(out_meta) = &args.out_meta {
         let metadata = format!(
@@ -458,57 +1174,173 @@ fn run_headless(args: HeadlessArgs) -> Result<(), String> {
     Ok(())
 }
 
-fn run_golden(args: GoldenArgs) -> Result<(), String> {
-    fs::create_dir_all(&args.golden_dir)
-        .map_err(|err| format!("failed to create {}: {err}", args.golden_dir.display()))?;
+fn run_dump(args: DumpArgs) -> Result<(), AppError> {
+    let html = load_input(&args.input, &args.fetch)?;
+    print!("{}", debug_tokens(&engine::tokenize(&html)));
+    Ok(())
+}
 
-    let fixtures = collect_fixtures(&args.fixture_dir)?;
-    if fixtures.is_empty() {
-        return Err(format!(
-            "no fixtures found in {}",
-            args.fixture_dir.display()
-        ));
-    }
+fn run_diff(args: DiffArgs) -> Result<(), AppError> {
+    let a = read_rgba(&args.a, args.width, args.height)?;
+    let b = read_rgba(&args.b, args.width, args.height)?;
+    let differing = count_differing_pixels(&a, &b);
+    println!(
+        "{differing} of {} pixels differ ({}x{})",
+        a.len() / 4,
+        args.width,
+        args.height
+    );
 
-    let mut failures = Vec::new();
+    // Raw RGBA like headless output: differing pixels in red over a dimmed grayscale of `a`.
+    if let Some(out) = &args.out {
+        write_file_with_parents(out, &diff_heatmap(&a, &b))?;
+        info!(path = %out.display(), differing, "diff heatmap written");
+    }
+    Ok(())
+}
+
+fn read_rgba(path: &Path, width: u32, height: u32) -> Result<Vec<u8>, AppError> {
+    let bytes = fs::read(path).map_err(|err| AppError::io("read", path, err))?;
+    decode_rgba(bytes, width, height)
+        .map_err(|err| AppError::Parse(format!("{}: {err}", path.display())))
+}
+
+// Accepts raw RGBA or `headless --with-header` output; a header must agree with the size asked for.
+fn decode_rgba(bytes: Vec<u8>, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let expected = u64::from(width) * u64::from(height) * 4;
+    // A raw frame whose first pixel happens to spell the magic is still exactly frame-sized.
+    if RgbaHeader::is_present(&bytes) && bytes.len() as u64 != expected {
+        let (header, pixels) = RgbaHeader::parse(&bytes)?;
+        if (header.width, header.height) != (width, height) {
+            return Err(format!(
+                "header says {}x{}, expected {width}x{height}",
+                header.width, header.height
+            ));
+        }
+        return Ok(pixels.to_vec());
+    }
+    if bytes.len() as u64 != expected {
+        return Err(format!(
+            "{} bytes, expected {expected} for {width}x{height} rgba8",
+            bytes.len()
+        ));
+    }
+    Ok(bytes)
+}
+
+fn count_differing_pixels(a: &[u8], b: &[u8]) -> usize {
+    a.chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .filter(|(left, right)| left != right)
+        .count()
+}
+
+fn diff_heatmap(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .flat_map(|(left, right)| {
+            if left != right {
+                return DIFF_HIGHLIGHT;
+            }
+            // Dimmed so the red stands out.
+            let dim = luma(left) / 3;
+            [dim, dim, dim, 255]
+        })
+        .collect()
+}
+
+fn run_golden(args: GoldenArgs) -> Result<(), AppError> {
+    let fixtures = filter_fixtures(collect_fixtures(&args.fixture_dir)?, args.filter.as_deref());
+    if args.list {
+        print!("{}", golden_list(&fixtures));
+        return Ok(());
+    }
 
-    for fixture in fixtures {
-        let fixture_name = fixture
-            .file_stem()
-            .and_then(|stem| stem.to_str())
-            .ok_or_else(|| format!("invalid fixture name: {}", fixture.display()))?;
+    fs::create_dir_all(&args.golden_dir)
+        .map_err(|err| AppError::io("create", &args.golden_dir, err))?;
 
-        let html = fs::read_to_string(&fixture)
-            .map_err(|err| format!("failed to read {}: {err}", fixture.display()))?;
-        let buffer = render_headless_buffer(&html, args.width, args.height, args.frame);
-        let hash = format!("{:016x}", fnv1a64(&buffer));
+    if fixtures.is_empty() {
+        return Err(AppError::InvalidArgs(match &args.filter {
+            Some(filter) => format!(
+                "no fixtures matching '{filter}' found in {}",
+                args.fixture_dir.display()
+            ),
+            None => format!("no fixtures found in {}", args.fixture_dir.display()),
+        }));
+    }
+    let fixture_count = fixtures.len();
+
+    // Without --sizes, hashes keep their unsuffixed `name.hash` key.
+    let sizes: Vec<(u32, u32, Option<String>)> = if args.sizes.is_empty() {
+        vec![(args.width, args.height, None)]
+    } else {
+        args.sizes
+            .iter()
+            .map(|&(width, height)| (width, height, Some(format!("{width}x{height}"))))
+            .collect()
+    };
 
-        let expected_path = args.golden_dir.join(format!("{fixture_name}.hash"));
+    let mut documents = Vec::with_capacity(fixtures.len());
+    for fixture in &fixtures {
+        let fixture_name = fixture_name(fixture).ok_or_else(|| {
+            AppError::Parse(format!("invalid fixture name: {}", fixture.display()))
+        })?;
+        let html = fs::read_to_string(fixture).map_err(|err| AppError::io("read", fixture, err))?;
+        documents.push((fixture_name, html));
+    }
+
+    let jobs: Vec<GoldenJob> = documents
+        .iter()
+        .flat_map(|(fixture_name, html)| {
+            sizes
+                .iter()
+                .map(move |(width, height, size_key)| GoldenJob {
+                    key: match size_key {
+                        Some(size_key) => format!("{fixture_name}@{size_key}"),
+                        None => fixture_name.to_string(),
+                    },
+                    fixture_name,
+                    html,
+                    width: *width,
+                    height: *height,
+                })
+        })
+        .collect();
+    let workers = std::thread::available_parallelism().map_or(1, usize::from);
+    let hashes = golden_hashes(&jobs, args.frame, workers);
+
+    let mut failures = Vec::new();
+    let mut report = Vec::with_capacity(jobs.len());
+    for (job, hash) in jobs.iter().zip(hashes) {
+        let GoldenJob {
+            key,
+            fixture_name,
+            width,
+            height,
+            ..
+        } = job;
+        let expected_path = args.golden_dir.join(format!("{key}.hash"));
         if args.update || !expected_path.exists() {
-            fs::write(&expected_path, format!("{hash}\n")).map_err(|err| {
-                format!(
-                    "failed to write expected hash {}: {err}",
-                    expected_path.display()
-                )
-            })?;
+            fs::write(&expected_path, format!("{hash}\n"))
+                .map_err(|err| AppError::io("write expected hash", &expected_path, err))?;
             info!(path = %expected_path.display(), hash, "golden updated");
+            report.push(GoldenReportEntry::new(key, &hash, &hash));
             continue;
         }
 
         let expected = fs::read_to_string(&expected_path)
-            .map_err(|err| format!("failed to read {}: {err}", expected_path.display()))?;
+            .map_err(|err| AppError::io("read", &expected_path, err))?;
         let expected = expected.trim();
+        report.push(GoldenReportEntry::new(key, expected, &hash));
         if expected != hash {
-            let actual_path = args.golden_dir.join(format!("{fixture_name}.actual.hash"));
-            fs::write(&actual_path, format!("{hash}\n")).map_err(|err| {
-                format!(
-                    "failed to write actual hash {}: {err}",
-                    actual_path.display()
-                )
-            })?;
+            let actual_path = args.golden_dir.join(format!("{key}.actual.hash"));
+            fs::write(&actual_path, format!("{hash}\n"))
+                .map_err(|err| AppError::io("write actual hash", &actual_path, err))?;
             failures.push(format!(
-                "{} expected={} actual={} (actual hash in {})",
+                "{} ({}x{}) expected={} actual={} (actual hash in {})",
                 fixture_name,
+                width,
+                height,
                 expected,
                 hash,
                 actual_path.display()
@@ -516,30 +1348,24 @@ fn run_golden(args: GoldenArgs) -> Result<(), String> {
         }
     }
 
+    if let Some(path) = &args.report {
+        write_file_with_parents(path, golden_report_json(&report).as_bytes())?;
+        info!(path = %path.display(), entries = report.len(), "golden report written");
+    }
+
     if failures.is_empty() {
-        info!(
-            count = fixtures_len(&args.fixture_dir)?,
-            "golden check passed"
-        );
+        info!(count = fixture_count, "golden check passed");
         return Ok(());
     }
 
-    Err(format!(
-        "golden mismatches:\n{}",
-        failures
-            .into_iter()
-            .map(|f| format!("- {f}"))
-            .collect::<Vec<_>>()
-            .join("\n")
-    ))
+    Err(AppError::GoldenMismatch(failures))
 }
 
-fn collect_fixtures(dir: &Path) -> Result<Vec<PathBuf>, String> {
+fn collect_fixtures(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
     let mut fixtures = Vec::new();
-    let entries =
-        fs::read_dir(dir).map_err(|err| format!("failed to read {}: {err}", dir.display()))?;
+    let entries = fs::read_dir(dir).map_err(|err| AppError::io("read", dir, err))?;
     for entry in entries {
-        let entry = entry.map_err(|err| format!("failed to read fixture entry: {err}"))?;
+        let entry = entry.map_err(|err| AppError::io("read fixture entry in", dir, err))?;
         let path = entry.path();
         if path.extension().and_then(|ext| ext.to_str()) == Some("html") {
             fixtures.push(path);
@@ -549,20 +1375,158 @@ fn collect_fixtures(dir: &Path) -> Result<Vec<PathBuf>, String> {
     Ok(fixtures)
 }
 
-fn fixtures_len(dir: &Path) -> Result<usize, String> {
-    Ok(collect_fixtures(dir)?.len())
+struct GoldenJob<'a> {
+    key: String,
+    fixture_name: &'a str,
+    html: &'a str,
+    width: u32,
+    height: u32,
 }
 
-fn render_headless_buffer(html: &str, width: u32, height: u32, frame: u64) -> Vec<u8> {
-    let scene = build_document_scene(html, width, height);
-    let mut renderer = Renderer::new(width, height);
-    let overlay = OverlayInfo {
-        frame_index: frame,
-        fps: 0.0,
-        width,
-        height,
+// One `--report` row per golden key (fixture plus `@WxH` when --sizes is used). Freshly written
+// goldens report their own hash as expected and pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GoldenReportEntry {
+    fixture: String,
+    expected: String,
+    actual: String,
+    passed: bool,
+}
+
+impl GoldenReportEntry {
+    fn new(fixture: &str, expected: &str, actual: &str) -> Self {
+        Self {
+            fixture: fixture.to_string(),
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+            passed: expected == actual,
+        }
+    }
+}
+
+// A JSON array in job order, one entry per line, so reports diff cleanly between runs.
+fn golden_report_json(entries: &[GoldenReportEntry]) -> String {
+    if entries.is_empty() {
+        return "[]\n".to_string();
+    }
+    let rows: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "  {{\"fixture\": {}, \"expected\": {}, \"actual\": {}, \"passed\": {}}}",
+                json_string(&entry.fixture),
+                json_string(&entry.expected),
+                json_string(&entry.actual),
+                entry.passed
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if ch.is_control() => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Renders every job and returns hashes in job order. Jobs are independent, so they are spread
+// round-robin over up to `workers` threads; comparison stays on the caller for ordered output.
+fn golden_hashes(jobs: &[GoldenJob<'_>], frame: u64, workers: usize) -> Vec<String> {
+    let render = |job: &GoldenJob<'_>| {
+        let buffer = render_headless_buffer(
+            job.html,
+            job.width,
+            job.height,
+            frame,
+            &HeadlessRenderOptions::default(),
+        );
+        format!("{:016x}", frame_hash(&buffer))
     };
 
+    let workers = workers.clamp(1, jobs.len().max(1));
+    if workers == 1 {
+        return jobs.iter().map(render).collect();
+    }
+
+    let mut hashes = vec![String::new(); jobs.len()];
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                scope.spawn(move || {
+                    jobs.iter()
+                        .enumerate()
+                        .skip(worker)
+                        .step_by(workers)
+                        .map(|(index, job)| (index, render(job)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (index, hash) in handle.join().expect("golden render worker panicked") {
+                hashes[index] = hash;
+            }
+        }
+    });
+    hashes
+}
+
+// `golden --list` output: one fixture name per line, in `collect_fixtures` order.
+fn golden_list(fixtures: &[PathBuf]) -> String {
+    fixtures
+        .iter()
+        .filter_map(|fixture| fixture_name(fixture))
+        .map(|name| format!("{name}\n"))
+        .collect()
+}
+
+fn fixture_name(fixture: &Path) -> Option<&str> {
+    fixture.file_stem().and_then(|stem| stem.to_str())
+}
+
+// Keeps fixtures whose name contains `filter`; order from `collect_fixtures` is preserved.
+fn filter_fixtures(fixtures: Vec<PathBuf>, filter: Option<&str>) -> Vec<PathBuf> {
+    match filter {
+        Some(filter) => fixtures
+            .into_iter()
+            .filter(|fixture| fixture_name(fixture).is_some_and(|name| name.contains(filter)))
+            .collect(),
+        None => fixtures,
+    }
+}
+
+fn render_headless_buffer(
+    html: &str,
+    width: u32,
+    height: u32,
+    frame: u64,
+    options: &HeadlessRenderOptions,
+) -> Vec<u8> {
+    let scene = build_document_scene(html, width, height, options.script_host);
+    let mut renderer = Renderer::builder()
+        .width(width)
+        .height(height)
+        .grayscale(options.grayscale)
+        .clear_color(options.background)
+        .build();
+    let overlay = OverlayInfo::builder()
+        .frame_index(frame)
+        .fps(0.0)
+        .width(width)
+        .height(height)
+        .build();
+
     renderer
         .render_display_list(
             frame,
@@ -574,11 +1538,28 @@ fn render_headless_buffer(html: &str, width: u32, height: u32, frame: u64) -> Ve
         .to_vec()
 }
 
-fn build_document_scene(html: &str, width: u32, height: u32) -> DocumentScene {
+fn build_document_scene(
+    html: &str,
+    width: u32,
+    height: u32,
+    script_host: ScriptHostChoice,
+) -> DocumentScene {
+    let mut host = script_host.create();
+    let host = host.as_deref_mut().map(|host| host as &mut dyn ScriptHost);
+    build_document_scene_with_host(html, width, height, host)
+}
+
+// Lays out `html` and hands its inline scripts to `host`; with no host they are not run.
+fn build_document_scene_with_host(
+    html: &str,
+    width: u32,
+    height: u32,
+    host: Option<&mut dyn ScriptHost>,
+) -> DocumentScene {
     let output = render_document(html, width, height);
 
-    let mut host = StubScriptHost::default();
-    if let Err(err) = host.execute(&output.scripts) {
+    let result = host.map_or(Ok(()), |host| host.execute(&output.scripts));
+    if let Err(err) = result {
         match err {
             ScriptError::Unsupported { script_count } => {
                 if !SCRIPT_HOST_UNSUPPORTED_WARNED.swap(true, Ordering::Relaxed) {
@@ -591,10 +1572,14 @@ fn build_document_scene(html: &str, width: u32, height: u32) -> DocumentScene {
     }
 
     let (rects, texts) = display_commands_to_scene(&output.display_list.commands);
+    let links = clickable_regions(&output.document, &output.layout);
     DocumentScene {
-        html: html.to_string(),
+        title: document_title(&output.document),
+        document: output.document,
         rects,
         texts,
+        links,
+        focused_link: None,
     }
 }
 
@@ -609,22 +1594,49 @@ fn display_commands_to_scene(commands: &[DisplayCommand]) -> (Vec<DrawRect>, Vec
                 width,
                 height,
                 color,
+                ..
             } => {
-                rects.push(DrawRect {
-                    x: *x as i32,
-                    y: *y as i32,
-                    width: *width as i32,
-                    height: *height as i32,
-                    color: *color,
-                });
+                rects.push(draw_rect(*x, *y, *width, *height, *color));
+            }
+            DisplayCommand::StrokeRect {
+                x,
+                y,
+                width,
+                height,
+                thickness,
+                color,
+                ..
+            } => {
+                let bounds = draw_rect(*x, *y, *width, *height, *color);
+                rects.extend(bounds.outline(draw_coord(*thickness)));
             }
-            DisplayCommand::DrawText { x, y, text, color } => {
+            DisplayCommand::DrawText {
+                x,
+                y,
+                text,
+                color,
+                underline,
+                direction,
+                align,
+                box_width,
+                ..
+            } => {
+                let align = match align {
+                    TextAlign::Start => None,
+                    TextAlign::Left => Some(renderer::TextAlign::Left),
+                    TextAlign::Center => Some(renderer::TextAlign::Center),
+                    TextAlign::Right => Some(renderer::TextAlign::Right),
+                };
                 texts.push(DrawText {
-                    x: *x as i32,
-                    y: *y as i32,
+                    x: draw_coord(*x),
+                    y: draw_coord(*y),
                     text: text.clone(),
                     color: *color,
                     scale: 2,
+                    underline: *underline,
+                    max_width: None,
+                    rtl: *direction == TextDirection::Rtl,
+                    align: align.map(|align| (align, *box_width)),
                 });
             }
         }
@@ -632,6 +1644,24 @@ fn display_commands_to_scene(commands: &[DisplayCommand]) -> (Vec<DrawRect>, Vec
     (rects, texts)
 }
 
+// Layout coordinates are u32 but drawing uses i32: offsets past i32::MAX clamp to it and the
+// extent shrinks so the far edge stays representable instead of wrapping negative.
+fn draw_rect(x: u32, y: u32, width: u32, height: u32, color: [u8; 4]) -> DrawRect {
+    let x = draw_coord(x);
+    let y = draw_coord(y);
+    DrawRect {
+        x,
+        y,
+        width: draw_coord(width).min(i32::MAX - x),
+        height: draw_coord(height).min(i32::MAX - y),
+        color,
+    }
+}
+
+fn draw_coord(value: u32) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
 fn build_customization_popup(
     renderer: &Renderer,
     width: u32,
@@ -675,6 +1705,10 @@ fn build_customization_popup(
         text: "Customization".to_string(),
         color: [236, 242, 255, 255],
         scale: 2,
+        underline: false,
+        max_width: None,
+        rtl: false,
+        align: None,
     });
     texts.push(DrawText {
         x: panel_x + 14,
@@ -682,6 +1716,10 @@ fn build_customization_popup(
         text: "Use menu: View > Settings. Esc closes panel.".to_string(),
         color: [205, 216, 240, 255],
         scale: 1,
+        underline: false,
+        max_width: None,
+        rtl: false,
+        align: None,
     });
 
     let total_fonts = renderer.font_count();
@@ -709,34 +1747,156 @@ fn build_customization_popup(
             " "
         };
         let name = renderer.font_name(font_index).unwrap_or("Unknown");
+        let label = format!("{marker} {:>4}  {name}", font_index);
         texts.push(DrawText {
             x: panel_x + 16,
             y: row_y + 2,
-            text: format!("{marker} {:>4}  {name}", font_index),
+            text: fit_text_to_width(renderer, &label, 1, (panel_width - 32) as f32),
             color: if font_index == selected {
                 [245, 249, 255, 255]
             } else {
                 [211, 224, 252, 255]
             },
             scale: 1,
+            underline: false,
+            max_width: None,
+            rtl: false,
+            align: None,
         });
     }
 
     (rects, texts)
 }
 
+fn fit_text_to_width(renderer: &Renderer, text: &str, scale: u32, max_width: f32) -> String {
+    if renderer.text_advance(text, scale) <= max_width {
+        return text.to_string();
+    }
+
+    let mut fitted = text.to_string();
+    while fitted.pop().is_some() {
+        let candidate = format!("{}...", fitted.trim_end());
+        if renderer.text_advance(&candidate, scale) <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+fn frame_matches_buffer(frame: &PlatformFrame, buffer_len: usize) -> bool {
+    frame.validate() && frame.byte_len() == buffer_len as u64
+}
+
+// Ok(false) when the platform could not apply the title (including the stub backend).
+fn set_window_title(title: &str) -> Result<bool, String> {
+    let title =
+        CString::new(title).map_err(|_| "window title contains interior null byte".to_string())?;
+    let applied = unsafe { ffi::platform_set_title(title.as_ptr()) };
+    Ok(applied != PLATFORM_FALSE)
+}
+
+// Presents only when the renderer reported changed regions; an unchanged frame leaves the
+// window showing the previous present and counts as success.
+fn present_frame_partial(frame: &PlatformFrame, dirty: &[DirtyRect]) -> bool {
+    if dirty.is_empty() {
+        return true;
+    }
+    let rects: Vec<PlatformDirtyRect> = dirty
+        .iter()
+        .map(|rect| PlatformDirtyRect {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+        })
+        .collect();
+    let presented = unsafe {
+        ffi::platform_present_frame_partial(
+            frame as *const PlatformFrame,
+            rects.as_ptr(),
+            rects.len() as u32,
+        )
+    };
+    presented != PLATFORM_FALSE
+}
+
+// Moves link focus forward or backward, wrapping at either end of `count` links.
+fn advance_focus(current: Option<usize>, count: usize, backwards: bool) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+    let next = match (current, backwards) {
+        (None, false) => 0,
+        (None, true) => count - 1,
+        (Some(index), false) => (index + 1) % count,
+        (Some(index), true) => (index.min(count - 1) + count - 1) % count,
+    };
+    Some(next)
+}
+
+// 1-3 pick a pattern directly. Escape and Enter keep their bindings even when the popup is
+// closed or no link is focused; Space and every other unbound key cycle.
+fn pattern_for_key(key_code: u32, current: Pattern) -> Option<Pattern> {
+    match key_code {
+        PLATFORM_KEY_1..=PLATFORM_KEY_3 => Pattern::from_index((key_code - PLATFORM_KEY_1) as u8),
+        PLATFORM_KEY_ESCAPE | PLATFORM_KEY_ENTER => None,
+        _ => Some(current.next()),
+    }
+}
+
+fn parse_pattern(value: &str) -> Result<Pattern, String> {
+    Pattern::parse(value).ok_or_else(|| {
+        let labels: Vec<&str> = Pattern::all().iter().map(Pattern::label).collect();
+        format!("unknown pattern '{value}' (expected: {})", labels.join("|"))
+    })
+}
+
+fn parse_overlay_corner(value: &str) -> Result<OverlayCorner, String> {
+    match value {
+        "top-left" => Ok(OverlayCorner::TopLeft),
+        "top-right" => Ok(OverlayCorner::TopRight),
+        "bottom-left" => Ok(OverlayCorner::BottomLeft),
+        "bottom-right" => Ok(OverlayCorner::BottomRight),
+        _ => Err(format!(
+            "unknown overlay corner '{value}' (expected: top-left|top-right|bottom-left|bottom-right)"
+        )),
+    }
+}
+
 fn parse_u32(value: &str, flag: &str) -> Result<u32, String> {
     value
         .parse::<u32>()
         .map_err(|_| format!("invalid value for {flag}: {value}"))
 }
 
+fn parse_color(value: &str, flag: &str) -> Result<[u8; 4], String> {
+    parse_css_color(value).ok_or_else(|| {
+        format!("invalid value for {flag}: {value} (expected: #rrggbb or rgb(r, g, b))")
+    })
+}
+
 fn parse_u64(value: &str, flag: &str) -> Result<u64, String> {
     value
         .parse::<u64>()
         .map_err(|_| format!("invalid value for {flag}: {value}"))
 }
 
+fn parse_sizes(value: &str) -> Result<Vec<(u32, u32)>, String> {
+    value
+        .split(',')
+        .map(|entry| {
+            let entry = entry.trim();
+            entry
+                .split_once('x')
+                .and_then(|(width, height)| {
+                    Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?))
+                })
+                .filter(|&(width, height)| width > 0 && height > 0)
+                .ok_or_else(|| format!("invalid value for --sizes: {entry} (expected WxH)"))
+        })
+        .collect()
+}
+
 fn next_arg(
     args: &mut std::iter::Peekable<impl Iterator<Item = String>>,
     flag: &str,
@@ -745,21 +1905,45 @@ fn next_arg(
         .ok_or_else(|| format!("missing value for {flag}"))
 }
 
-fn write_file_with_parents(path: &Path, bytes: &[u8]) -> Result<(), String> {
+fn write_file_with_parents(path: &Path, bytes: &[u8]) -> Result<(), AppError> {
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+        fs::create_dir_all(parent).map_err(|err| AppError::io("create", parent, err))?;
     }
-    fs::write(path, bytes).map_err(|err| format!("failed to write {}: {err}", path.display()))
+    fs::write(path, bytes).map_err(|err| AppError::io("write", path, err))
 }
 
-fn fnv1a64(bytes: &[u8]) -> u64 {
-    let mut hash = 0xcbf29ce484222325_u64;
-    for b in bytes {
-        hash ^= u64::from(*b);
-        hash = hash.wrapping_mul(0x100000001b3);
+// `--input` accepts either a local path or an http:// URL.
+fn load_input(input: &Path, options: &FetchOptions) -> Result<String, AppError> {
+    let url = input.to_str().filter(|value| is_http_url(value));
+    let Some(url) = url else {
+        return fs::read_to_string(input).map_err(|err| AppError::io("read", input, err));
+    };
+
+    let page = fetch_url(url, options).map_err(AppError::Fetch)?;
+    info!(url = %page.url, content_type = ?page.content_type, bytes = page.text.len(), "fetched document");
+    if page.truncated {
+        warn!(
+            url = %page.url,
+            max_body_bytes = options.max_body_bytes,
+            "response body truncated"
+        );
+    }
+    Ok(page.text)
+}
+
+fn parse_fetch_flag(
+    flag: &str,
+    args: &mut std::iter::Peekable<impl Iterator<Item = String>>,
+    fetch: &mut FetchOptions,
+) -> Result<(), String> {
+    let value = next_arg(args, flag)?;
+    match flag {
+        "--max-body" => {
+            fetch.max_body_bytes = usize::try_from(parse_u64(&value, flag)?).unwrap_or(usize::MAX);
+        }
+        _ => fetch.max_redirects = parse_u32(&value, flag)?,
     }
-    hash
+    Ok(())
 }
 
 fn default_document_input_path() -> Option<PathBuf> {
@@ -771,12 +1955,26 @@ fn default_document_input_path() -> Option<PathBuf> {
     }
 }
 
-fn init_tracing() {
-    let filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("browser=info"));
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .without_time()
+const LOG_CAPTURE_CAPACITY: usize = 64;
+
+// `--verbose`/`--quiet` override RUST_LOG. Debug lines are captured regardless of either so
+// failures can report recent context.
+fn init_tracing(verbosity: Verbosity) {
+    let filter = match verbosity {
+        Verbosity::Verbose => EnvFilter::new("browser=debug"),
+        Verbosity::Quiet => EnvFilter::new("browser=warn"),
+        Verbosity::Normal => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("browser=info"))
+        }
+    };
+    let capture = LogCapture::with_capacity(LOG_CAPTURE_CAPACITY).install_global();
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .without_time()
+                .with_filter(filter),
+        )
+        .with(capture.with_filter(Targets::new().with_target("browser", Level::DEBUG)))
         .init();
 }
 
@@ -794,6 +1992,7 @@ fn process_split_bootstrap() {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use platform_abi::PLATFORM_KEY_SPACE;
 
     #[test]
     fn parses_run_pattern_flag() {
@@ -818,6 +2017,62 @@ mod tests {
         assert_eq!(run.pattern, Pattern::Solid);
     }
 
+    #[test]
+    fn fit_text_elides_labels_wider_than_the_limit() {
+        let renderer = Renderer::new(16, 16);
+        assert_eq!(fit_text_to_width(&renderer, "short", 1, 1000.0), "short");
+
+        let long = "a font name far too long for the customization panel";
+        let limit = renderer.text_advance(long, 1) / 2.0;
+        let fitted = fit_text_to_width(&renderer, long, 1, limit);
+        assert!(fitted.ends_with("..."));
+        assert!(renderer.text_advance(&fitted, 1) <= limit);
+    }
+
+    #[test]
+    fn parses_background_flag() {
+        let command =
+            parse_cli(vec!["run", "--bg", "#102030"].into_iter().map(String::from)).unwrap();
+        let Command::Run(run) = command else {
+            panic!("expected run command");
+        };
+        assert_eq!(run.background, Some([0x10, 0x20, 0x30, 255]));
+
+        let err = parse_cli(
+            vec![
+                "headless", "--input", "a.html", "--out", "a.rgba", "--bg", "red",
+            ]
+            .into_iter()
+            .map(String::from),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("--bg"));
+    }
+
+    #[test]
+    fn parses_golden_sizes() {
+        assert_eq!(
+            parse_sizes("640x360,960x540").unwrap(),
+            vec![(640, 360), (960, 540)]
+        );
+        assert!(parse_sizes("640x").is_err());
+        assert!(parse_sizes("640").is_err());
+        assert!(parse_sizes("0x360").is_err());
+        assert!(parse_sizes("640x360,").is_err());
+    }
+
+    #[test]
+    fn parses_run_rotate_flag() {
+        let command =
+            parse_cli(vec!["run", "--rotate", "270"].into_iter().map(String::from)).unwrap();
+        let Command::Run(run) = command else {
+            panic!("expected run command");
+        };
+        assert_eq!(run.rotation, Rotation::Deg270);
+
+        assert!(parse_cli(vec!["run", "--rotate", "45"].into_iter().map(String::from)).is_err());
+    }
+
     #[test]
     fn parses_headless_required_flags() {
         let command = parse_cli(
@@ -840,6 +2095,8 @@ mod tests {
         assert_eq!(headless.height, 540);
         assert_eq!(headless.out_rgba, PathBuf::from("tests/golden/tmp.rgba"));
         assert_eq!(headless.out_meta, None);
+        assert!(!headless.grayscale);
+        assert_eq!(headless.background, None);
     }
 
     #[test]
@@ -869,6 +2126,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn zero_sized_viewport_is_empty_headless_but_rejected_for_run() {
+        let options = HeadlessRenderOptions::default();
+        let html = "<h1>Title</h1><p>Body</p>";
+        for (width, height) in [(0, 540), (960, 0), (0, 0)] {
+            assert!(render_headless_buffer(html, width, height, 0, &options).is_empty());
+        }
+
+        for size in [["--width", "0"], ["--height", "0"]] {
+            let err = parse_cli(["run"].into_iter().chain(size).map(String::from)).unwrap_err();
+            assert!(err.to_string().contains("non-zero window size"), "{err}");
+        }
+    }
+
+    #[test]
+    fn parses_headless_pattern_without_input() {
+        let command = parse_cli(
+            vec!["headless", "--pattern", "rects", "--out", "/tmp/p.rgba"]
+                .into_iter()
+                .map(String::from),
+        )
+        .unwrap();
+        let Command::Headless(headless) = command else {
+            panic!("expected headless command");
+        };
+        assert_eq!(headless.source, HeadlessSource::Pattern(Pattern::Rects));
+
+        let err = parse_cli(
+            vec![
+                "headless",
+                "--input",
+                "a.html",
+                "--pattern",
+                "solid",
+                "--out",
+                "/tmp/p.rgba",
+            ]
+            .into_iter()
+            .map(String::from),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("mutually exclusive"));
+    }
+
     #[test]
     fn converts_display_commands() {
         let commands = vec![DisplayCommand::FillRect {
@@ -877,6 +2178,7 @@ mod tests {
             width: 3,
             height: 4,
             color: [1, 2, 3, 4],
+            node_id: None,
         }];
 
         let (rects, texts) = display_commands_to_scene(&commands);
@@ -885,4 +2187,922 @@ mod tests {
         assert_eq!(rects[0].x, 1);
         assert_eq!(rects[0].height, 4);
     }
+
+    #[test]
+    fn fixture_scene_keeps_text_commands() {
+        let html = fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/fixtures/basic.html"
+        ))
+        .unwrap();
+        let scene = build_document_scene(&html, 960, 540, ScriptHostChoice::Stub);
+
+        assert!(!scene.rects.is_empty());
+        assert!(scene
+            .texts
+            .iter()
+            .any(|text| text.text == "Render baseline fixture"));
+    }
+
+    #[test]
+    fn heading_text_maps_to_scene_text() {
+        let scene = build_document_scene("<h1>Heading</h1>", 640, 360, ScriptHostChoice::Stub);
+
+        let heading = scene
+            .texts
+            .iter()
+            .find(|text| text.text == "Heading")
+            .expect("heading text in scene");
+        assert_eq!(heading.scale, 2);
+        assert_eq!(heading.color, [18, 24, 45, 255]);
+        assert!(!heading.underline);
+    }
+
+    #[test]
+    fn link_focus_advances_and_wraps() {
+        assert_eq!(advance_focus(None, 0, false), None);
+        assert_eq!(advance_focus(Some(2), 0, true), None);
+
+        assert_eq!(advance_focus(None, 3, false), Some(0));
+        assert_eq!(advance_focus(Some(0), 3, false), Some(1));
+        assert_eq!(advance_focus(Some(2), 3, false), Some(0));
+
+        assert_eq!(advance_focus(None, 3, true), Some(2));
+        assert_eq!(advance_focus(Some(0), 3, true), Some(2));
+        assert_eq!(advance_focus(Some(2), 3, true), Some(1));
+    }
+
+    #[test]
+    fn log_script_host_logs_console_calls_from_inline_scripts() {
+        let html = fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/fixtures/script.html"
+        ))
+        .unwrap();
+        let capture = LogCapture::with_capacity(8);
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            build_document_scene(&html, 640, 360, ScriptHostChoice::Log);
+            build_document_scene(&html, 640, 360, ScriptHostChoice::None);
+        });
+
+        let lines = capture.lines();
+        let logged: Vec<_> = lines
+            .iter()
+            .filter(|line| line.contains("console.log"))
+            .collect();
+        assert_eq!(logged.len(), 1, "{lines:?}");
+        assert!(logged[0].contains("window.answer"), "{}", logged[0]);
+
+        let Command::Headless(headless) = parse_cli(
+            [
+                "headless",
+                "--input",
+                "page.html",
+                "--out",
+                "page.rgba",
+                "--script-host",
+                "none",
+            ]
+            .into_iter()
+            .map(String::from),
+        )
+        .unwrap() else {
+            panic!("expected headless command");
+        };
+        assert_eq!(headless.script_host, ScriptHostChoice::None);
+        let err =
+            parse_cli(["run", "--script-host", "v8"].into_iter().map(String::from)).unwrap_err();
+        assert!(err.to_string().contains("stub|log|none"), "{err}");
+    }
+
+    #[test]
+    fn huge_layout_coordinates_clamp_instead_of_wrapping() {
+        let rect = draw_rect(u32::MAX, 10, 50, u32::MAX, [0, 0, 0, 255]);
+        assert_eq!((rect.x, rect.y), (i32::MAX, 10));
+        assert_eq!((rect.width, rect.height), (0, i32::MAX - 10));
+
+        let rect = draw_rect(100, 0, u32::MAX - 100, 20, [0, 0, 0, 255]);
+        assert_eq!(rect.width, i32::MAX - 100);
+
+        let html = "<h1>Wide</h1><p><a href=\"/x\">link</a></p>";
+        let width = u32::MAX - 3;
+        let scene = build_document_scene(html, width, 360, ScriptHostChoice::None);
+        for rect in &scene.rects {
+            assert!(rect.x >= 0 && rect.width >= 0, "{rect:?}");
+            assert!(rect.x.checked_add(rect.width).is_some(), "{rect:?}");
+        }
+        let mut renderer = Renderer::new(64, 48);
+        renderer.render_display_list(0, 0.0, &scene.rects, &scene.texts, None);
+    }
+
+    #[test]
+    fn translucent_element_blends_over_the_page_background() {
+        let html = "<body><div style=\"opacity:0.5;background-color:#ff0000\">x</div></body>";
+        let (width, height) = (320, 240);
+        let scene = build_document_scene(html, width, height, ScriptHostChoice::None);
+        let div = scene
+            .rects
+            .iter()
+            .find(|rect| rect.color == [255, 0, 0, 128])
+            .copied()
+            .unwrap();
+        let mut renderer = Renderer::new(width, height);
+        let pixels = renderer.render_display_list(0, 0.0, &scene.rects, &scene.texts, None);
+
+        let (x, y) = (
+            (div.x + div.width - 4) as u32,
+            (div.y + div.height / 2) as u32,
+        );
+        let index = ((y * width + x) * 4) as usize;
+        // Half red over the viewport background [245, 245, 248], not solid red.
+        assert_eq!(&pixels[index..index + 4], &[250, 122, 123, 255]);
+    }
+
+    #[test]
+    fn tab_focus_cycles_document_links() {
+        let mut scene = build_document_scene(
+            "<p><a href=\"/one\">One</a></p><p><a href=\"/two\">Two</a></p>",
+            640,
+            360,
+            ScriptHostChoice::Stub,
+        );
+        assert!(scene.focus_outline().is_none());
+
+        scene.cycle_link_focus(false);
+        assert_eq!(
+            scene.focused_link().map(|(_, href)| href.as_str()),
+            Some("/one")
+        );
+        scene.cycle_link_focus(false);
+        assert_eq!(
+            scene.focused_link().map(|(_, href)| href.as_str()),
+            Some("/two")
+        );
+        scene.cycle_link_focus(false);
+        assert_eq!(
+            scene.focused_link().map(|(_, href)| href.as_str()),
+            Some("/one")
+        );
+        scene.cycle_link_focus(true);
+        assert_eq!(
+            scene.focused_link().map(|(_, href)| href.as_str()),
+            Some("/two")
+        );
+
+        let outline = scene.focus_outline().unwrap();
+        assert!(outline.iter().all(|rect| rect.color == FOCUS_OUTLINE_COLOR));
+    }
+
+    fn test_window_state(renderer: &Renderer, html: Option<&str>) -> WindowState {
+        let args = RunArgs {
+            width: renderer.width(),
+            height: renderer.height(),
+            ..run_args(&[])
+        };
+        let scene =
+            html.map(|html| build_document_scene(html, args.width, args.height, args.script_host));
+        WindowState::new(&args, renderer, scene)
+    }
+
+    fn run_args(extra: &[&str]) -> RunArgs {
+        let cli = ["run"].iter().chain(extra).map(|arg| arg.to_string());
+        let Command::Run(run) = parse_cli(cli).unwrap() else {
+            panic!("expected run command");
+        };
+        run
+    }
+
+    // Fresh per-process directory under the system temp dir; callers remove it when done.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("browser-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn event(kind: u32, width: u32, height: u32) -> PlatformEvent {
+        PlatformEvent {
+            struct_size: std::mem::size_of::<PlatformEvent>() as u32,
+            kind,
+            width,
+            height,
+            ..PlatformEvent::default()
+        }
+    }
+
+    #[test]
+    fn step_frame_stops_on_quit() {
+        let mut renderer = Renderer::new(32, 24);
+        let mut state = test_window_state(&renderer, None);
+        let mut scheduler = Scheduler::new(60);
+        let dt = Duration::from_millis(16);
+
+        assert_eq!(
+            step_frame(&mut state, &mut renderer, &mut scheduler, dt, &[]),
+            PresentDecision::Present {
+                width: 32,
+                height: 24
+            }
+        );
+        let quit = event(PLATFORM_EVENT_QUIT, 0, 0);
+        assert_eq!(
+            step_frame(&mut state, &mut renderer, &mut scheduler, dt, &[quit]),
+            PresentDecision::Stop
+        );
+    }
+
+    #[test]
+    fn spotlight_follows_mouse_moves_when_enabled() {
+        let pointer = PlatformEvent {
+            x: 30,
+            y: 20,
+            ..event(PLATFORM_EVENT_MOUSE_MOVE, 0, 0)
+        };
+        let dt = Duration::from_millis(16);
+        for (flags, expected) in [
+            (&[][..], None),
+            (
+                &["--spotlight"][..],
+                Some(Spotlight {
+                    x: 30,
+                    y: 20,
+                    radius: 24,
+                }),
+            ),
+        ] {
+            let args = run_args(
+                &[
+                    &["--pattern-only", "--width", "160", "--height", "90"],
+                    flags,
+                ]
+                .concat(),
+            );
+            let mut renderer = windowed_renderer(&args);
+            let mut state = WindowState::new(&args, &renderer, None);
+            step_frame(&mut state, &mut renderer, &mut Scheduler::new(60), dt, &[]);
+            assert_eq!(renderer.spotlight(), None, "no pointer position yet");
+            step_frame(
+                &mut state,
+                &mut renderer,
+                &mut Scheduler::new(60),
+                dt,
+                &[pointer],
+            );
+            assert_eq!(renderer.spotlight(), expected, "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn bound_keys_keep_the_pattern_and_unbound_keys_cycle_it() {
+        let args = run_args(&["--pattern-only", "--width", "32", "--height", "24"]);
+        let mut renderer = windowed_renderer(&args);
+        let mut state = WindowState::new(&args, &renderer, None);
+        let key = |key_code| PlatformEvent {
+            key_code,
+            ..event(PLATFORM_EVENT_KEY_DOWN, 0, 0)
+        };
+        let start = renderer.pattern();
+
+        for key_code in [PLATFORM_KEY_ESCAPE, PLATFORM_KEY_ENTER] {
+            state.handle_event(&mut renderer, &key(key_code));
+            assert_eq!(renderer.pattern(), start, "key {key_code}");
+        }
+        state.handle_event(&mut renderer, &key(PLATFORM_KEY_SPACE));
+        assert_eq!(renderer.pattern(), start.next());
+        state.handle_event(&mut renderer, &key(0));
+        assert_eq!(renderer.pattern(), start.next().next());
+        state.handle_event(&mut renderer, &key(PLATFORM_KEY_3));
+        assert_eq!(renderer.pattern(), Pattern::Rects);
+    }
+
+    #[test]
+    fn h_key_hides_the_overlay_panel() {
+        let args = run_args(&["--pattern-only", "--width", "160", "--height", "90"]);
+        let mut renderer = windowed_renderer(&args);
+        let mut state = WindowState::new(&args, &renderer, None);
+        assert!(state.overlay_enabled);
+        assert!(!state.toggle_overlay());
+        assert!(state.toggle_overlay());
+
+        let dt = Duration::from_millis(250);
+        step_frame(&mut state, &mut renderer, &mut Scheduler::new(60), dt, &[]);
+        let with_overlay = renderer.framebuffer().to_vec();
+
+        let mut renderer = windowed_renderer(&args);
+        let hide = PlatformEvent {
+            key_code: PLATFORM_KEY_H,
+            ..event(PLATFORM_EVENT_KEY_DOWN, 0, 0)
+        };
+        step_frame(
+            &mut state,
+            &mut renderer,
+            &mut Scheduler::new(60),
+            dt,
+            &[hide],
+        );
+        assert!(!state.overlay_enabled);
+        let without_overlay = renderer.framebuffer().to_vec();
+
+        let timing = Scheduler::new(60).advance(dt);
+        let mut bare = windowed_renderer(&args);
+        let bare = bare
+            .render_pattern(timing.frame_index, timing.total_seconds, None)
+            .to_vec();
+        assert_eq!(without_overlay, bare);
+
+        // The panel sits in the top-left corner, so that is where the two frames differ.
+        let top_left = |frame: &[u8]| -> Vec<u8> {
+            frame
+                .chunks_exact(160 * 4)
+                .take(40)
+                .flat_map(|row| &row[..120 * 4])
+                .copied()
+                .collect()
+        };
+        assert_ne!(top_left(&with_overlay), top_left(&bare));
+        assert_eq!(top_left(&without_overlay), top_left(&bare));
+    }
+
+    #[test]
+    fn replaying_a_recorded_session_reproduces_frame_hashes() {
+        let args = run_args(&["--pattern-only", "--width", "48", "--height", "32"]);
+        let mut renderer = windowed_renderer(&args);
+        let mut state = WindowState::new(&args, &renderer, None);
+        let mut scheduler = Scheduler::new(60).with_max_updates_per_frame(4);
+        let key = |key_code| PlatformEvent {
+            key_code,
+            ..event(PLATFORM_EVENT_KEY_DOWN, 0, 0)
+        };
+        let steps = [
+            (Duration::from_millis(16), vec![]),
+            (Duration::from_millis(17), vec![key(PLATFORM_KEY_3)]),
+            (
+                Duration::from_millis(40),
+                vec![event(PLATFORM_EVENT_RESIZE, 64, 40)],
+            ),
+            (Duration::from_micros(16_667), vec![key(PLATFORM_KEY_I)]),
+            (
+                Duration::from_millis(16),
+                vec![event(PLATFORM_EVENT_QUIT, 0, 0)],
+            ),
+        ];
+
+        let mut out = Vec::new();
+        let mut recorder = EventRecorder::new(&mut out, "events.log").unwrap();
+        let mut elapsed = Duration::ZERO;
+        let mut hashes = Vec::new();
+        for (dt, events) in steps {
+            elapsed += dt;
+            let frame = LoggedFrame {
+                elapsed,
+                events,
+                document_loaded: false,
+            };
+            recorder.record(&frame).unwrap();
+            if step_frame(&mut state, &mut renderer, &mut scheduler, dt, &frame.events)
+                == PresentDecision::Stop
+            {
+                break;
+            }
+            hashes.push(frame_hash(renderer.framebuffer()));
+        }
+        assert_eq!(hashes.len(), 4);
+
+        let log = EventLog::parse(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(log.frames.len(), 5);
+        assert_eq!(replay_frame_hashes(&args, &log).unwrap(), hashes);
+    }
+
+    #[test]
+    fn global_log_flags_precede_the_subcommand() {
+        let parse = |cli: &[&str]| parse_global_flags(cli.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(
+            parse(&["run"]),
+            (Verbosity::Normal, vec!["run".to_string()])
+        );
+        assert_eq!(parse(&["--verbose", "dump"]).0, Verbosity::Verbose);
+        assert_eq!(parse(&["--quiet"]), (Verbosity::Quiet, Vec::new()));
+        assert_eq!(parse(&["--quiet", "--verbose"]).0, Verbosity::Verbose);
+        assert_eq!(parse(&["--verbose", "--quiet", "run"]).0, Verbosity::Quiet);
+
+        // After the subcommand they are ordinary (unknown) subcommand flags.
+        let (verbosity, rest) = parse(&["--quiet", "run", "--verbose"]);
+        assert_eq!(verbosity, Verbosity::Quiet);
+        assert_eq!(rest, ["run", "--verbose"]);
+        assert!(parse_cli(rest.into_iter()).is_err());
+
+        // With no subcommand the remaining flags still go to `run`.
+        let (_, rest) = parse(&["--verbose", "--width", "64"]);
+        let Command::Run(run) = parse_cli(rest.into_iter()).unwrap() else {
+            panic!("expected run command");
+        };
+        assert_eq!(run.width, 64);
+    }
+
+    #[test]
+    fn replay_takes_run_flags_and_an_event_log() {
+        let cli = [
+            "replay",
+            "--events",
+            "session.log",
+            "--pattern-only",
+            "--width",
+            "64",
+        ];
+        let Command::Replay(replay) = parse_cli(cli.map(String::from).into_iter()).unwrap() else {
+            panic!("expected replay command");
+        };
+        assert_eq!(replay.events, PathBuf::from("session.log"));
+        assert_eq!(replay.run.width, 64);
+        assert!(replay.run.input.is_none());
+
+        let missing = parse_cli(["replay", "--pattern-only"].map(String::from).into_iter());
+        assert!(missing.unwrap_err().to_string().contains("--events"));
+        let hashed = ["replay", "--events", "a.log", "--hash-frames", "2"];
+        assert!(parse_cli(hashed.map(String::from).into_iter()).is_err());
+
+        let run = run_args(&["--record-events", "session.log"]);
+        assert_eq!(run.record_events, Some(PathBuf::from("session.log")));
+    }
+
+    #[test]
+    fn step_frame_traces_frame_timing_fields() {
+        let mut renderer = Renderer::new(16, 12);
+        let mut state = test_window_state(&renderer, None);
+        let mut scheduler = Scheduler::new(60);
+        let capture = LogCapture::with_capacity(8);
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            step_frame(
+                &mut state,
+                &mut renderer,
+                &mut scheduler,
+                Duration::from_millis(250),
+                &[],
+            );
+        });
+
+        let lines = capture.lines();
+        let timing = lines
+            .iter()
+            .find(|line| line.contains("frame timing"))
+            .expect("frame timing line");
+        assert!(timing.starts_with("TRACE "), "{timing}");
+        for field in ["frame=1", "dt=0.25", "fps=0", "fixed_updates="] {
+            assert!(timing.contains(field), "{field} missing from {timing}");
+        }
+    }
+
+    #[test]
+    fn step_frame_shows_loading_until_document_arrives() {
+        let mut renderer = Renderer::new(64, 48);
+        let mut state = test_window_state(&renderer, None);
+        state.loading = Some("Loading http://example.test/".to_string());
+        let mut scheduler = Scheduler::new(60);
+        let dt = Duration::from_millis(16);
+
+        step_frame(&mut state, &mut renderer, &mut scheduler, dt, &[]);
+        let loading = renderer.framebuffer().to_vec();
+        let mut expected = Renderer::new(64, 48);
+        assert_eq!(
+            loading,
+            expected.render_loading(0, "Loading http://example.test/")
+        );
+
+        state.document_loaded("<p>Ready</p>");
+        assert!(state.loading.is_none());
+        step_frame(&mut state, &mut renderer, &mut scheduler, dt, &[]);
+        assert!(state.document_scene.is_some());
+        assert_ne!(renderer.framebuffer(), loading.as_slice());
+    }
+
+    #[test]
+    fn hash_frames_runs_the_frame_loop_without_a_window() {
+        let args = run_args(&[
+            "--pattern-only",
+            "--pattern",
+            "gradient",
+            "--width",
+            "48",
+            "--height",
+            "32",
+            "--hash-frames",
+            "4",
+        ]);
+        assert_eq!(args.hash_frames, Some(4));
+        assert!(args.input.is_none());
+        assert!(parse_cli(["run", "--hash-frames", "x"].map(String::from).into_iter()).is_err());
+
+        let hashes = windowless_frame_hashes(&args, 4).unwrap();
+        assert_eq!(hashes.len(), 4);
+        assert_eq!(windowless_frame_hashes(&args, 4).unwrap(), hashes);
+        // The gradient animates, so consecutive frames differ.
+        assert!(hashes.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn step_frame_applies_resize_events() {
+        let mut renderer = Renderer::new(32, 24);
+        let mut state = test_window_state(&renderer, Some("<p>Hello</p>"));
+        let mut scheduler = Scheduler::new(60);
+
+        let resize = event(PLATFORM_EVENT_RESIZE, 48, 40);
+        let decision = step_frame(
+            &mut state,
+            &mut renderer,
+            &mut scheduler,
+            Duration::from_millis(16),
+            &[resize],
+        );
+
+        assert_eq!(
+            decision,
+            PresentDecision::Present {
+                width: 48,
+                height: 40
+            }
+        );
+        assert_eq!((state.width, state.height), (48, 40));
+        assert_eq!(renderer.framebuffer().len(), 48 * 40 * 4);
+
+        // Zero-sized resizes (e.g. minimizing) are ignored.
+        let minimized = event(PLATFORM_EVENT_RESIZE, 0, 0);
+        step_frame(
+            &mut state,
+            &mut renderer,
+            &mut scheduler,
+            Duration::from_millis(16),
+            &[minimized],
+        );
+        assert_eq!((state.width, state.height), (48, 40));
+    }
+
+    #[cfg(platform_stub)]
+    #[test]
+    fn set_window_title_rejects_interior_nulls() {
+        assert_eq!(set_window_title("Example Domain"), Ok(false));
+        assert!(set_window_title("Bad\0title")
+            .unwrap_err()
+            .contains("interior null"));
+    }
+
+    #[cfg(platform_stub)]
+    #[test]
+    fn partial_present_skips_frames_without_dirty_rects() {
+        let buffer = [0_u8; 4 * 2 * 4];
+        let frame = PlatformFrame {
+            struct_size: std::mem::size_of::<PlatformFrame>() as u32,
+            width: 4,
+            height: 2,
+            stride_bytes: 16,
+            pixels_rgba8: buffer.as_ptr(),
+        };
+        // Nothing changed, so the stub platform is never asked to present.
+        assert!(present_frame_partial(&frame, &[]));
+
+        let dirty = DirtyRect {
+            x: 0,
+            y: 1,
+            width: 4,
+            height: 1,
+        };
+        assert!(!present_frame_partial(&frame, &[dirty]));
+    }
+
+    #[test]
+    fn present_requires_buffer_to_match_frame() {
+        let buffer = [0_u8; 16 * 2];
+        let frame = PlatformFrame {
+            struct_size: std::mem::size_of::<PlatformFrame>() as u32,
+            width: 4,
+            height: 3,
+            stride_bytes: 16,
+            pixels_rgba8: buffer.as_ptr(),
+        };
+        assert!(!frame_matches_buffer(&frame, buffer.len()));
+        assert!(frame_matches_buffer(
+            &PlatformFrame { height: 2, ..frame },
+            buffer.len()
+        ));
+        assert!(!frame_matches_buffer(
+            &PlatformFrame {
+                height: 2,
+                stride_bytes: 8,
+                ..frame
+            },
+            buffer.len()
+        ));
+    }
+
+    #[test]
+    fn parses_dump_input() {
+        let command = parse_cli(
+            vec!["dump", "--input", "tests/fixtures/basic.html"]
+                .into_iter()
+                .map(String::from),
+        )
+        .unwrap();
+
+        let Command::Dump(dump) = command else {
+            panic!("expected dump command");
+        };
+        assert_eq!(dump.input, PathBuf::from("tests/fixtures/basic.html"));
+        assert!(parse_cli(vec!["dump"].into_iter().map(String::from)).is_err());
+    }
+
+    #[test]
+    fn parses_diff_args() {
+        let command = parse_cli(
+            [
+                "diff",
+                "--a",
+                "a.rgba",
+                "--b",
+                "b.rgba",
+                "--width",
+                "4",
+                "--height",
+                "2",
+                "--out",
+                "heat.rgba",
+            ]
+            .into_iter()
+            .map(String::from),
+        )
+        .unwrap();
+        let Command::Diff(diff) = command else {
+            panic!("expected diff command");
+        };
+        assert_eq!((diff.a, diff.b), ("a.rgba".into(), "b.rgba".into()));
+        assert_eq!((diff.width, diff.height), (4, 2));
+        assert_eq!(diff.out, Some(PathBuf::from("heat.rgba")));
+
+        let err = parse_cli(
+            ["diff", "--a", "a.rgba", "--b", "b.rgba"]
+                .map(String::from)
+                .into_iter(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("--width"), "{err}");
+    }
+
+    #[test]
+    fn diff_reads_headered_rgba_and_rejects_truncated_files() {
+        let pixels: Vec<u8> = (0..4 * 6).map(|i| i as u8).collect();
+        assert_eq!(decode_rgba(pixels.clone(), 2, 3), Ok(pixels.clone()));
+        assert!(decode_rgba(pixels.clone(), 3, 3).is_err());
+
+        let header = RgbaHeader::for_frame(2, 3, &pixels);
+        let file = [&header.to_bytes()[..], &pixels].concat();
+        assert_eq!(decode_rgba(file.clone(), 2, 3), Ok(pixels));
+
+        let err = decode_rgba(file.clone(), 3, 2).unwrap_err();
+        assert!(err.contains("header says 2x3"), "{err}");
+        let err = decode_rgba(file[..file.len() - 1].to_vec(), 2, 3).unwrap_err();
+        assert!(err.contains("truncated"), "{err}");
+
+        let mut magic_first = file[file.len() - 24..].to_vec();
+        magic_first[..4].copy_from_slice(&file[..4]);
+        assert_eq!(decode_rgba(magic_first.clone(), 2, 3), Ok(magic_first));
+
+        let Command::Headless(headless) = parse_cli(
+            [
+                "headless",
+                "--pattern",
+                "rects",
+                "--out",
+                "p.rgba",
+                "--with-header",
+            ]
+            .into_iter()
+            .map(String::from),
+        )
+        .unwrap() else {
+            panic!("expected headless command");
+        };
+        assert!(headless.with_header);
+    }
+
+    #[test]
+    fn entry_points_report_typed_errors() {
+        let err = parse_cli(["headless", "--sparkle"].into_iter().map(String::from)).unwrap_err();
+        assert!(
+            matches!(&err, AppError::InvalidArgs(message) if message.contains("--sparkle")),
+            "{err:?}"
+        );
+
+        let missing = PathBuf::from("tests/fixtures/does-not-exist.html");
+        let err = run_headless(HeadlessArgs {
+            source: HeadlessSource::Document(missing.clone()),
+            width: 32,
+            height: 24,
+            frame: 0,
+            out_rgba: PathBuf::from("unused.rgba"),
+            out_meta: None,
+            grayscale: false,
+            background: None,
+            fetch: FetchOptions::default(),
+            script_host: ScriptHostChoice::None,
+            with_header: false,
+        })
+        .unwrap_err();
+        match &err {
+            AppError::Io {
+                action,
+                path,
+                source,
+            } => {
+                assert_eq!((*action, path), ("read", &missing));
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+            }
+            other => panic!("expected an io error, got {other:?}"),
+        }
+        assert!(err
+            .to_string()
+            .starts_with("failed to read tests/fixtures/does-not-exist.html"));
+    }
+
+    #[test]
+    fn diff_counts_and_highlights_changed_pixels() {
+        let a: Vec<u8> = (0..4 * 6).map(|i| i as u8 * 10).collect();
+        assert_eq!(count_differing_pixels(&a, &a), 0);
+        assert!(diff_heatmap(&a, &a)
+            .chunks_exact(4)
+            .all(|px| px != DIFF_HIGHLIGHT));
+
+        let mut b = a.clone();
+        b[4 * 3 + 3] ^= 0xff;
+        assert_eq!(count_differing_pixels(&a, &b), 1);
+        let heatmap = diff_heatmap(&a, &b);
+        assert_eq!(heatmap.len(), a.len());
+        let highlighted: Vec<usize> = heatmap
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, px)| *px == DIFF_HIGHLIGHT)
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(highlighted, [3]);
+        let dim = luma(&a[8..12]) / 3;
+        assert_eq!(heatmap[8..12], [dim, dim, dim, 255]);
+    }
+
+    #[test]
+    fn golden_filter_matches_fixture_name_substrings() {
+        let fixtures: Vec<PathBuf> = [
+            "fixtures/basic.html",
+            "fixtures/detailed.html",
+            "nested/script.html",
+        ]
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+
+        assert_eq!(filter_fixtures(fixtures.clone(), None), fixtures);
+        assert_eq!(
+            filter_fixtures(fixtures.clone(), Some("ai")),
+            vec![PathBuf::from("fixtures/detailed.html")]
+        );
+        // Directory names are not part of the match.
+        assert!(filter_fixtures(fixtures, Some("nest")).is_empty());
+    }
+
+    #[test]
+    fn golden_list_names_are_sorted() {
+        let command = parse_cli(
+            vec!["golden", "--list", "--filter", "i"]
+                .into_iter()
+                .map(String::from),
+        )
+        .unwrap();
+        let Command::Golden(golden) = command else {
+            panic!("expected golden command");
+        };
+        assert!(golden.list);
+        assert_eq!(golden.filter.as_deref(), Some("i"));
+
+        let dir = scratch_dir("golden-list");
+        for name in [
+            "zeta.html",
+            "mid.html",
+            "alpha.html",
+            "index.html",
+            "notes.txt",
+        ] {
+            fs::write(dir.join(name), "<p>x</p>").unwrap();
+        }
+        let fixtures = collect_fixtures(&dir).unwrap();
+        assert_eq!(golden_list(&fixtures), "alpha\nindex\nmid\nzeta\n");
+        let filtered = filter_fixtures(fixtures, golden.filter.as_deref());
+        assert_eq!(golden_list(&filtered), "index\nmid\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parallel_golden_hashes_match_sequential() {
+        let documents = [
+            ("heading", "<h1>Title</h1><p>Body</p>"),
+            ("list", "<ul><li>One</li><li>Two</li></ul>"),
+            ("table", "<table><tr><td>a</td><td>b</td></tr></table>"),
+        ];
+        let jobs: Vec<GoldenJob> = documents
+            .iter()
+            .flat_map(|&(fixture_name, html)| {
+                [(160, 90), (200, 120)].map(|(width, height)| GoldenJob {
+                    key: format!("{fixture_name}@{width}x{height}"),
+                    fixture_name,
+                    html,
+                    width,
+                    height,
+                })
+            })
+            .collect();
+
+        let sequential = golden_hashes(&jobs, 3, 1);
+        assert_eq!(sequential.len(), jobs.len());
+        assert_eq!(golden_hashes(&jobs, 3, 4), sequential);
+        assert_eq!(golden_hashes(&jobs, 3, 64), sequential);
+    }
+
+    #[test]
+    fn golden_report_flags_each_fixture() {
+        let dir = scratch_dir("golden-report");
+        let (fixture_dir, golden_dir) = (dir.join("fixtures"), dir.join("golden"));
+        fs::create_dir_all(&fixture_dir).unwrap();
+        fs::write(fixture_dir.join("zeta.html"), "<ul><li>One</li></ul>").unwrap();
+        fs::write(fixture_dir.join("alpha.html"), "<h1>Title</h1>").unwrap();
+        let report_path = dir.join("out/report.json");
+        let golden = |update: bool| {
+            let mut args = vec![
+                "golden".to_string(),
+                "--fixture-dir".to_string(),
+                fixture_dir.display().to_string(),
+                "--golden-dir".to_string(),
+                golden_dir.display().to_string(),
+                "--width".to_string(),
+                "160".to_string(),
+                "--height".to_string(),
+                "90".to_string(),
+                "--report".to_string(),
+                report_path.display().to_string(),
+            ];
+            if update {
+                args.push("--update".to_string());
+            }
+            let Command::Golden(args) = parse_cli(args.into_iter()).unwrap() else {
+                panic!("expected golden command");
+            };
+            run_golden(args)
+        };
+        // Reads the report back as (fixture, expected, actual, passed) rows.
+        let rows = || -> Vec<(String, String, String, bool)> {
+            let report = fs::read_to_string(&report_path).unwrap();
+            let lines: Vec<&str> = report.lines().collect();
+            assert_eq!((lines[0], lines[lines.len() - 1]), ("[", "]"), "{report}");
+            lines[1..lines.len() - 1]
+                .iter()
+                .map(|line| {
+                    let fields: Vec<&str> = line.split('"').collect();
+                    let passed = line.trim_end_matches(',').ends_with("\"passed\": true}");
+                    (
+                        fields[3].to_string(),
+                        fields[7].to_string(),
+                        fields[11].to_string(),
+                        passed,
+                    )
+                })
+                .collect()
+        };
+
+        golden(true).unwrap();
+        let fresh = rows();
+        assert_eq!(fresh.len(), 2);
+        assert_eq!(
+            (fresh[0].0.as_str(), fresh[1].0.as_str()),
+            ("alpha", "zeta")
+        );
+        assert!(fresh.iter().all(|row| row.1 == row.2 && row.3), "{fresh:?}");
+
+        // A stale hash fails the run, and the report is still written with both rows.
+        fs::write(golden_dir.join("zeta.hash"), "0000000000000000\n").unwrap();
+        fs::remove_file(&report_path).unwrap();
+        let err = golden(false).unwrap_err();
+        assert!(matches!(&err, AppError::GoldenMismatch(failures) if failures.len() == 1));
+        let stale = rows();
+        assert_eq!(stale[0], fresh[0]);
+        assert_eq!(
+            stale[1],
+            (
+                "zeta".to_string(),
+                "0000000000000000".to_string(),
+                fresh[1].2.clone(),
+                false
+            )
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(golden_report_json(&[]), "[]\n");
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), r#""a\"b\\c\n\u0001""#);
+    }
 }
@@ -1,15 +1,113 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+};
+
 pub type NodeId = usize;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
-    StartTag { name: String },
-    EndTag { name: String },
+    StartTag {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    EndTag {
+        name: String,
+    },
     Text(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ElementData {
     pub tag_name: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl ElementData {
+    pub fn new(tag_name: impl Into<String>) -> Self {
+        Self {
+            tag_name: tag_name.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Looks up a declaration in the inline `style` attribute; the last one wins.
+    pub fn style(&self, property: &str) -> Option<&str> {
+        self.attr("style")?
+            .rsplit(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(property))
+            .map(|(_, value)| value.trim())
+    }
+
+    // `display: flex` in the default row direction; column flex still stacks like a block.
+    pub fn is_flex_row(&self) -> bool {
+        self.style("display")
+            .is_some_and(|display| display.eq_ignore_ascii_case("flex"))
+            && self
+                .style("flex-direction")
+                .is_none_or(|direction| direction.eq_ignore_ascii_case("row"))
+    }
+
+    // `<template>` content is parsed but inert, like `hidden` and `display: none` subtrees.
+    pub fn is_hidden(&self) -> bool {
+        self.tag_name == "template"
+            || self.attr("hidden").is_some()
+            || (self.tag_name == "input"
+                && self
+                    .attr("type")
+                    .is_some_and(|kind| kind.trim().eq_ignore_ascii_case("hidden")))
+            || self
+                .style("display")
+                .is_some_and(|display| display.eq_ignore_ascii_case("none"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextDirection {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+// Inline `text-align`. `Start` (no declaration) keeps the direction's edge; the others place
+// each line inside the text box.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Start,
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "start" => Some(Self::Start),
+            "left" => Some(Self::Left),
+            "center" => Some(Self::Center),
+            "right" => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+impl TextDirection {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ltr" => Some(Self::Ltr),
+            "rtl" => Some(Self::Rtl),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,6 +129,95 @@ pub struct Document {
     pub nodes: Vec<Node>,
 }
 
+impl Document {
+    // Parent chain of `node_id`, nearest first, excluding the node itself.
+    pub fn ancestors(&self, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(
+            self.nodes.get(node_id).and_then(|node| node.parent),
+            |&id| self.nodes.get(id).and_then(|node| node.parent),
+        )
+    }
+
+    // The nearest `dir="ltr"`/`dir="rtl"` on the node or an ancestor. Other values (including
+    // `auto`) are skipped, so those elements inherit like ones without the attribute.
+    pub fn text_direction(&self, node_id: NodeId) -> TextDirection {
+        std::iter::once(node_id)
+            .chain(self.ancestors(node_id))
+            .find_map(|id| match &self.nodes.get(id)?.kind {
+                NodeKind::Element(el) => TextDirection::parse(el.attr("dir")?),
+                NodeKind::Text(_) => None,
+            })
+            .unwrap_or_default()
+    }
+
+    // The nearest valid inline `text-align` on the node or an ancestor.
+    pub fn text_align(&self, node_id: NodeId) -> TextAlign {
+        std::iter::once(node_id)
+            .chain(self.ancestors(node_id))
+            .find_map(|id| match &self.nodes.get(id)?.kind {
+                NodeKind::Element(el) => TextAlign::parse(el.style("text-align")?),
+                NodeKind::Text(_) => None,
+            })
+            .unwrap_or_default()
+    }
+
+    // Unlinks `node_id` from its parent. The node stays in the arena until `compact`.
+    pub fn detach(&mut self, node_id: NodeId) {
+        let Some(parent) = self
+            .nodes
+            .get_mut(node_id)
+            .and_then(|node| node.parent.take())
+        else {
+            return;
+        };
+        self.nodes[parent]
+            .children
+            .retain(|&child| child != node_id);
+    }
+
+    // Rebuilds the arena with only nodes reachable from the root, keeping their relative
+    // order. The second value maps each old id to its new id (`None` if dropped).
+    pub fn compact(&self) -> (Document, Vec<Option<NodeId>>) {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root];
+        while let Some(node_id) = stack.pop() {
+            if node_id >= self.nodes.len() || reachable[node_id] {
+                continue;
+            }
+            reachable[node_id] = true;
+            stack.extend(self.nodes[node_id].children.iter().copied());
+        }
+
+        let mut mapping = vec![None; self.nodes.len()];
+        let kept = (0..self.nodes.len()).filter(|&old| reachable[old]);
+        for (new, old) in kept.enumerate() {
+            mapping[old] = Some(new);
+        }
+
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(old, _)| reachable[*old])
+            .map(|(_, node)| Node {
+                parent: node.parent.and_then(|parent| mapping[parent]),
+                children: node
+                    .children
+                    .iter()
+                    .filter_map(|&child| mapping.get(child).copied().flatten())
+                    .collect(),
+                kind: node.kind.clone(),
+            })
+            .collect();
+
+        let document = Document {
+            root: mapping.get(self.root).copied().flatten().unwrap_or(0),
+            nodes,
+        };
+        (document, mapping)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LayoutBox {
     pub node_id: NodeId,
@@ -45,6 +232,66 @@ pub struct LayoutTree {
     pub boxes: Vec<LayoutBox>,
 }
 
+impl LayoutTree {
+    // One `node_id x y width height` line per box, in paint order (boxes are stored in the
+    // order the display list draws them).
+    pub fn to_snapshot(&self) -> String {
+        let mut out = String::new();
+        for layout_box in &self.boxes {
+            out.push_str(&format!(
+                "{} {} {} {} {}\n",
+                layout_box.node_id, layout_box.x, layout_box.y, layout_box.width, layout_box.height
+            ));
+        }
+        out
+    }
+}
+
+pub const DEFAULT_MAX_LAYOUT_DEPTH: u32 = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutOptions {
+    pub max_depth: u32,
+    pub grid_step: u32,
+    // Block heights by lowercase tag name, replacing the built-in default for that tag.
+    pub tag_heights: HashMap<String, u32>,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_LAYOUT_DEPTH,
+            grid_step: 1,
+            tag_heights: HashMap::new(),
+        }
+    }
+}
+
+impl LayoutOptions {
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn snap_to_grid(mut self, step: u32) -> Self {
+        self.grid_step = step.max(1);
+        self
+    }
+
+    pub fn with_tag_height(mut self, tag_name: &str, height: u32) -> Self {
+        self.tag_heights
+            .insert(tag_name.to_ascii_lowercase(), height);
+        self
+    }
+
+    fn element_height(&self, tag_name: &str) -> u32 {
+        self.tag_heights
+            .get(tag_name)
+            .copied()
+            .unwrap_or_else(|| element_height(tag_name))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DisplayCommand {
     FillRect {
@@ -53,15 +300,42 @@ pub enum DisplayCommand {
         width: u32,
         height: u32,
         color: [u8; 4],
+        node_id: Option<NodeId>,
+    },
+    StrokeRect {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        thickness: u32,
+        color: [u8; 4],
+        node_id: Option<NodeId>,
     },
+    // With `TextAlign::Start`, `x` is where the run starts, or for `TextDirection::Rtl` the right
+    // edge it ends at. Other alignments place the line within `box_width` pixels from `x`.
     DrawText {
         x: u32,
         y: u32,
         text: String,
         color: [u8; 4],
+        underline: bool,
+        direction: TextDirection,
+        align: TextAlign,
+        box_width: u32,
+        node_id: Option<NodeId>,
     },
 }
 
+impl DisplayCommand {
+    pub fn node_id(&self) -> Option<NodeId> {
+        match *self {
+            Self::FillRect { node_id, .. }
+            | Self::StrokeRect { node_id, .. }
+            | Self::DrawText { node_id, .. } => node_id,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DisplayList {
     pub viewport_width: u32,
@@ -69,10 +343,60 @@ pub struct DisplayList {
     pub commands: Vec<DisplayCommand>,
 }
 
+impl DisplayList {
+    // Painter's order: untagged commands (the viewport background) first, then each node's
+    // commands as one run, parents before children and siblings in document order.
+    pub fn is_well_ordered(&self, document: &Document) -> bool {
+        let mut position = vec![None; document.nodes.len()];
+        let mut stack = vec![document.root];
+        let mut next = 0;
+        while let Some(node_id) = stack.pop() {
+            let Some(slot) = position.get_mut(node_id) else {
+                continue;
+            };
+            if slot.is_some() {
+                continue;
+            }
+            *slot = Some(next);
+            next += 1;
+            stack.extend(document.nodes[node_id].children.iter().rev());
+        }
+
+        let mut last = None;
+        for command in &self.commands {
+            let current = match command.node_id() {
+                None if last.is_none() => continue,
+                None => return false,
+                Some(node_id) => position.get(node_id).copied().flatten(),
+            };
+            let Some(current) = current else {
+                return false;
+            };
+            if last.is_some_and(|last| current < last) {
+                return false;
+            }
+            last = Some(current);
+        }
+        true
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ScriptSnippet {
     pub node_id: NodeId,
     pub code: String,
+    pub leading_offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScriptOptions {
+    pub trim: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DisplayListOptions {
+    // Adds a non-empty `id` to element labels, e.g. `<div#main>`.
+    pub element_ids: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -100,25 +424,148 @@ pub fn render_document(input: &str, viewport_width: u32, viewport_height: u32) -
     }
 }
 
+// Re-runs layout and display list construction for an already parsed document, e.g. on resize.
+pub fn relayout(
+    document: &Document,
+    viewport_width: u32,
+    viewport_height: u32,
+) -> (LayoutTree, DisplayList) {
+    let layout = layout_document(document, viewport_width, viewport_height);
+    let display_list = build_display_list(document, &layout, viewport_width, viewport_height);
+    (layout, display_list)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenizeStats {
+    // Start and end tag tokens, including the end tags synthesized for void elements.
+    pub tag_count: usize,
+    pub text_count: usize,
+    // Comments are skipped rather than tokenized, so they only show up here.
+    pub comment_count: usize,
+    pub bytes_scanned: usize,
+}
+
 pub fn tokenize(input: &str) -> Vec<Token> {
+    tokenize_with_stats(input).0
+}
+
+pub fn tokenize_with_stats(input: &str) -> (Vec<Token>, TokenizeStats) {
     let mut tokens = Vec::new();
+    let mut stats = TokenizeStats::default();
+    scan_tokens(
+        &normalize_newlines(input),
+        true,
+        &mut tokens,
+        &mut stats.comment_count,
+    );
+    stats.bytes_scanned = input.len();
+    for token in &tokens {
+        match token {
+            Token::StartTag { .. } | Token::EndTag { .. } => stats.tag_count += 1,
+            Token::Text(_) => stats.text_count += 1,
+        }
+    }
+    (tokens, stats)
+}
+
+// One token per line, e.g. `START a href="/x"`, `TEXT "Hello"`, `END a`.
+pub fn debug_tokens(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        match token {
+            Token::StartTag { name, attributes } => {
+                out.push_str("START ");
+                out.push_str(name);
+                for (key, value) in attributes {
+                    out.push_str(&format!(" {key}={value:?}"));
+                }
+            }
+            Token::EndTag { name } => {
+                out.push_str("END ");
+                out.push_str(name);
+            }
+            Token::Text(text) => out.push_str(&format!("TEXT {text:?}")),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Tokenizer {
+    buffer: String,
+    tokens: Vec<Token>,
+    // The last chunk ended in `\r`, already buffered as `\n`; a leading `\n` in the next
+    // chunk belongs to the same line break.
+    pending_cr: bool,
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        let chunk = match chunk.strip_prefix('\n') {
+            Some(rest) if self.pending_cr => {
+                self.pending_cr = false;
+                rest
+            }
+            _ => chunk,
+        };
+        if !chunk.is_empty() {
+            self.pending_cr = chunk.ends_with('\r');
+        }
+        self.buffer.push_str(&normalize_newlines(chunk));
+        let consumed = scan_tokens(&self.buffer, false, &mut self.tokens, &mut 0);
+        self.buffer.drain(..consumed);
+    }
+
+    pub fn drain(&mut self) -> Vec<Token> {
+        std::mem::take(&mut self.tokens)
+    }
+
+    pub fn finish(mut self) -> Vec<Token> {
+        scan_tokens(&self.buffer, true, &mut self.tokens, &mut 0);
+        self.tokens
+    }
+}
+
+// Input stream preprocessing: `\r\n` and lone `\r` both become `\n` before tokenizing.
+fn normalize_newlines(input: &str) -> Cow<'_, str> {
+    if input.contains('\r') {
+        Cow::Owned(input.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+// Emits tokens from `input`, adds skipped comments to `comments` and returns how many bytes
+// were consumed. Unless `eof` is set, a trailing comment, tag, script body or text run that may
+// continue in the next chunk is left unconsumed.
+fn scan_tokens(input: &str, eof: bool, tokens: &mut Vec<Token>, comments: &mut usize) -> usize {
     let mut i = 0;
 
     while i < input.len() {
         let rest = &input[i..];
         if rest.starts_with("<!--") {
             if let Some(end) = rest.find("-->") {
+                *comments += 1;
                 i += end + 3;
+            } else if eof {
+                *comments += 1;
+                return input.len();
             } else {
-                break;
+                return i;
             }
             continue;
         }
 
         if rest.starts_with('<') {
             let Some(close) = rest.find('>') else {
-                break;
+                return if eof { input.len() } else { i };
             };
+            let tag_start = i;
             let inside = rest[1..close].trim();
             i += close + 1;
 
@@ -140,23 +587,38 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 continue;
             }
 
-            tokens.push(Token::StartTag { name: name.clone() });
-
             if name == "script" {
                 let script_rest = &input[i..];
-                if let Some(script_end) = find_case_insensitive(script_rest, "</script>") {
-                    let code = &script_rest[..script_end];
-                    if !code.trim().is_empty() {
-                        tokens.push(Token::Text(code.to_string()));
+                let Some(script_end) = find_case_insensitive(script_rest, "</script>") else {
+                    if !eof {
+                        return tag_start;
                     }
-                    tokens.push(Token::EndTag {
-                        name: "script".to_string(),
+                    tokens.push(Token::StartTag {
+                        name,
+                        attributes: parse_attributes(inside),
                     });
-                    i += script_end + "</script>".len();
+                    continue;
+                };
+                tokens.push(Token::StartTag {
+                    name,
+                    attributes: parse_attributes(inside),
+                });
+                let code = &script_rest[..script_end];
+                if !code.trim().is_empty() {
+                    tokens.push(Token::Text(code.to_string()));
                 }
+                tokens.push(Token::EndTag {
+                    name: "script".to_string(),
+                });
+                i += script_end + "</script>".len();
                 continue;
             }
 
+            tokens.push(Token::StartTag {
+                name: name.clone(),
+                attributes: parse_attributes(inside),
+            });
+
             if self_closing || is_void_element(&name) {
                 tokens.push(Token::EndTag { name });
             }
@@ -172,6 +634,9 @@ pub fn tokenize(input: &str) -> Vec<Token> {
             }
             i += next_tag;
         } else {
+            if !eof {
+                return i;
+            }
             let trimmed = rest.trim();
             if !trimmed.is_empty() {
                 tokens.push(Token::Text(trimmed.to_string()));
@@ -180,16 +645,14 @@ pub fn tokenize(input: &str) -> Vec<Token> {
         }
     }
 
-    tokens
+    input.len()
 }
 
 pub fn parse_document(tokens: &[Token]) -> Document {
     let mut nodes = vec![Node {
         parent: None,
         children: Vec::new(),
-        kind: NodeKind::Element(ElementData {
-            tag_name: "document".to_string(),
-        }),
+        kind: NodeKind::Element(ElementData::new("document")),
     }];
 
     let root = 0;
@@ -197,7 +660,7 @@ pub fn parse_document(tokens: &[Token]) -> Document {
 
     for token in tokens {
         match token {
-            Token::StartTag { name } => {
+            Token::StartTag { name, attributes } => {
                 let parent = *stack.last().unwrap_or(&root);
                 let node_id = nodes.len();
                 nodes.push(Node {
@@ -205,6 +668,7 @@ pub fn parse_document(tokens: &[Token]) -> Document {
                     children: Vec::new(),
                     kind: NodeKind::Element(ElementData {
                         tag_name: name.clone(),
+                        attributes: attributes.clone(),
                     }),
                 });
                 nodes[parent].children.push(node_id);
@@ -212,6 +676,8 @@ pub fn parse_document(tokens: &[Token]) -> Document {
                     stack.push(node_id);
                 }
             }
+            // Void elements are never pushed, so their synthesized end tags must not pop parents.
+            Token::EndTag { name } if is_void_element(name) => {}
             Token::EndTag { name } => {
                 while stack.len() > 1 {
                     let node_id = *stack.last().unwrap_or(&root);
@@ -245,69 +711,354 @@ pub fn layout_document(
     document: &Document,
     viewport_width: u32,
     viewport_height: u32,
+) -> LayoutTree {
+    layout_document_with_options(
+        document,
+        viewport_width,
+        viewport_height,
+        &LayoutOptions::default(),
+    )
+}
+
+pub fn layout_document_with_options(
+    document: &Document,
+    viewport_width: u32,
+    viewport_height: u32,
+    options: &LayoutOptions,
 ) -> LayoutTree {
     let mut boxes = Vec::new();
-    let mut cursor_y = 8;
+    if viewport_width == 0 || viewport_height == 0 {
+        return LayoutTree { boxes };
+    }
+    let viewport = ContainingBlock {
+        x: 8,
+        right: viewport_width.saturating_sub(8),
+        rows: 0,
+    };
+    layout_flow(
+        document,
+        document.root,
+        viewport,
+        0,
+        8,
+        viewport_height,
+        options,
+        &mut boxes,
+    );
+
+    if options.grid_step > 1 {
+        for layout_box in &mut boxes {
+            layout_box.y = snap_to_step(layout_box.y, options.grid_step);
+            layout_box.height =
+                snap_to_step(layout_box.height, options.grid_step).max(options.grid_step);
+        }
+    }
 
-    for &child in &document.nodes[document.root].children {
-        cursor_y = layout_node(
-            document,
-            child,
-            0,
-            cursor_y,
-            viewport_width,
-            viewport_height,
-            &mut boxes,
-        );
+    // Offsets saturate at u32::MAX, so trim extents to keep every right/bottom edge in range.
+    for layout_box in &mut boxes {
+        layout_box.width = layout_box.width.min(u32::MAX - layout_box.x);
+        layout_box.height = layout_box.height.min(u32::MAX - layout_box.y);
     }
 
     LayoutTree { boxes }
 }
 
+// Rounds to the nearest multiple of `step`; exact halves round up.
+fn snap_to_step(value: u32, step: u32) -> u32 {
+    if step <= 1 {
+        return value;
+    }
+    let rounded = (u64::from(value) + u64::from(step / 2)) / u64::from(step) * u64::from(step);
+    rounded.min(u64::from(u32::MAX - u32::MAX % step)) as u32
+}
+
 pub fn build_display_list(
     document: &Document,
     layout: &LayoutTree,
     viewport_width: u32,
     viewport_height: u32,
 ) -> DisplayList {
-    let mut commands = Vec::new();
-
-    commands.push(DisplayCommand::FillRect {
-        x: 0,
-        y: 0,
-        width: viewport_width,
-        height: viewport_height,
-        color: [245, 245, 248, 255],
-    });
+    build_display_list_with_options(
+        document,
+        layout,
+        viewport_width,
+        viewport_height,
+        &DisplayListOptions::default(),
+    )
+}
+
+// Commands follow `layout.boxes`, which layout emits in document order, so the list is in
+// painter's order (see `DisplayList::is_well_ordered`).
+pub fn build_display_list_with_options(
+    document: &Document,
+    layout: &LayoutTree,
+    viewport_width: u32,
+    viewport_height: u32,
+    options: &DisplayListOptions,
+) -> DisplayList {
+    DisplayList {
+        viewport_width,
+        viewport_height,
+        commands: DisplayListIter::new(document, layout, viewport_width, viewport_height, options)
+            .collect(),
+    }
+}
+
+// Yields the same commands as `build_display_list_with_options`, a layout box at a time, so a
+// consumer can paint large pages without holding the whole list.
+#[derive(Debug, Clone)]
+pub struct DisplayListIter<'a> {
+    document: &'a Document,
+    layout: &'a LayoutTree,
+    options: DisplayListOptions,
+    next_box: usize,
+    pending: VecDeque<DisplayCommand>,
+}
+
+impl<'a> DisplayListIter<'a> {
+    pub fn new(
+        document: &'a Document,
+        layout: &'a LayoutTree,
+        viewport_width: u32,
+        viewport_height: u32,
+        options: &DisplayListOptions,
+    ) -> Self {
+        let mut iter = Self {
+            document,
+            layout,
+            options: *options,
+            next_box: 0,
+            pending: VecDeque::new(),
+        };
+        if viewport_width == 0 || viewport_height == 0 {
+            iter.next_box = layout.boxes.len();
+        } else {
+            iter.pending.push_back(DisplayCommand::FillRect {
+                x: 0,
+                y: 0,
+                width: viewport_width,
+                height: viewport_height,
+                color: [245, 245, 248, 255],
+                node_id: None,
+            });
+        }
+        iter
+    }
 
-    for layout_box in &layout.boxes {
-        let color = color_for_node(document, layout_box.node_id);
-        commands.push(DisplayCommand::FillRect {
+    fn queue_box(&mut self, index: usize) {
+        let document = self.document;
+        let layout_box = &self.layout.boxes[index];
+        let opacity = node_opacity(document, layout_box.node_id);
+        let color = with_opacity(color_for_node(document, layout_box.node_id), opacity);
+        self.pending.push_back(DisplayCommand::FillRect {
             x: layout_box.x,
             y: layout_box.y,
             width: layout_box.width,
             height: layout_box.height,
             color,
+            node_id: Some(layout_box.node_id),
         });
 
-        if let Some(label) = label_for_node(document, layout_box.node_id) {
-            commands.push(DisplayCommand::DrawText {
-                x: layout_box.x.saturating_add(4),
-                y: layout_box.y.saturating_add(4),
-                text: label,
-                color: [18, 24, 45, 255],
+        if element_tag(document, layout_box.node_id) == Some("blockquote") {
+            self.pending
+                .push_back(quote_bar(document, self.layout, index, opacity));
+        }
+
+        if let Some(border_color) = border_for_node(document, layout_box.node_id) {
+            self.pending.push_back(DisplayCommand::StrokeRect {
+                x: layout_box.x,
+                y: layout_box.y,
+                width: layout_box.width,
+                height: layout_box.height,
+                thickness: 1,
+                color: with_opacity(border_color, opacity),
+                node_id: Some(layout_box.node_id),
             });
         }
+
+        if let Some(label) = label_for_node(document, layout_box.node_id, &self.options) {
+            let is_link = is_link_text(document, layout_box.node_id);
+            let is_placeholder = element_tag(document, layout_box.node_id) == Some("input");
+            let direction = document.text_direction(layout_box.node_id);
+            let align = document.text_align(layout_box.node_id);
+            let x = match (align, direction) {
+                (TextAlign::Start, TextDirection::Rtl) => {
+                    (layout_box.x.saturating_add(layout_box.width)).saturating_sub(4)
+                }
+                _ => layout_box.x.saturating_add(4),
+            };
+            // Text runs wrap the same way layout sized their box; tag labels stay on one line.
+            let lines = match &document.nodes[layout_box.node_id].kind {
+                NodeKind::Text(_) => text_lines(&label, layout_box.width),
+                NodeKind::Element(_) => vec![label],
+            };
+            for (row, line) in (0_u32..).zip(lines) {
+                self.pending.push_back(DisplayCommand::DrawText {
+                    x,
+                    y: layout_box
+                        .y
+                        .saturating_add(4)
+                        .saturating_add(row.saturating_mul(TEXT_LINE_ADVANCE)),
+                    text: line,
+                    color: with_opacity(
+                        if is_link {
+                            LINK_TEXT_COLOR
+                        } else if is_placeholder {
+                            PLACEHOLDER_TEXT_COLOR
+                        } else {
+                            [18, 24, 45, 255]
+                        },
+                        opacity,
+                    ),
+                    underline: is_link,
+                    direction,
+                    align,
+                    box_width: layout_box.width.saturating_sub(8),
+                    node_id: Some(layout_box.node_id),
+                });
+            }
+        }
     }
+}
 
-    DisplayList {
-        viewport_width,
-        viewport_height,
-        commands,
+impl Iterator for DisplayListIter<'_> {
+    type Item = DisplayCommand;
+
+    fn next(&mut self) -> Option<DisplayCommand> {
+        while self.pending.is_empty() && self.next_box < self.layout.boxes.len() {
+            self.queue_box(self.next_box);
+            self.next_box += 1;
+        }
+        self.pending.pop_front()
+    }
+}
+
+// Visible text in document order, whitespace collapsed and runs joined by single spaces.
+// Script/style content and hidden subtrees are skipped, as layout skips them.
+pub fn document_text(document: &Document) -> String {
+    let mut words: Vec<&str> = Vec::new();
+    let mut stack = vec![document.root];
+    while let Some(node_id) = stack.pop() {
+        match &document.nodes[node_id].kind {
+            NodeKind::Element(el) => {
+                if matches!(el.tag_name.as_str(), "script" | "style") || el.is_hidden() {
+                    continue;
+                }
+                stack.extend(document.nodes[node_id].children.iter().rev());
+            }
+            NodeKind::Text(text) => words.extend(text.split_whitespace()),
+        }
+    }
+    words.join(" ")
+}
+
+// Text of the first `<title>`, whitespace collapsed; `None` when missing or blank.
+pub fn document_title(document: &Document) -> Option<String> {
+    let title = document
+        .nodes
+        .iter()
+        .position(|node| matches!(&node.kind, NodeKind::Element(el) if el.tag_name == "title"))?;
+    let mut words: Vec<&str> = Vec::new();
+    let mut stack = vec![title];
+    while let Some(node_id) = stack.pop() {
+        match &document.nodes[node_id].kind {
+            NodeKind::Element(_) => stack.extend(document.nodes[node_id].children.iter().rev()),
+            NodeKind::Text(text) => words.extend(text.split_whitespace()),
+        }
+    }
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+// One region per laid-out `<a href>`; text inside the anchor shares the anchor's box.
+pub fn clickable_regions(document: &Document, layout: &LayoutTree) -> Vec<(LayoutBox, String)> {
+    layout
+        .boxes
+        .iter()
+        .filter_map(
+            |layout_box| match &document.nodes[layout_box.node_id].kind {
+                NodeKind::Element(el) if el.tag_name == "a" => {
+                    el.attr("href").map(|href| (*layout_box, href.to_string()))
+                }
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+// Hex colors plus `rgb(r, g, b)` / `rgba(r, g, b, a)` with integer channels and a 0-1 alpha.
+// Out-of-range components are clamped; a wrong component count is rejected.
+pub fn parse_css_color(value: &str) -> Option<[u8; 4]> {
+    let value = value.trim();
+    if value.starts_with('#') {
+        return parse_hex_color(value);
+    }
+
+    let (name, rest) = value.split_once('(')?;
+    let args = rest.strip_suffix(')')?;
+    let components: Vec<&str> = args.split(',').map(str::trim).collect();
+    let expected = match name.trim().to_ascii_lowercase().as_str() {
+        "rgb" => 3,
+        "rgba" => 4,
+        _ => return None,
+    };
+    if components.len() != expected {
+        return None;
+    }
+
+    let channel = |component: &str| {
+        component
+            .parse::<i64>()
+            .ok()
+            .map(|value| value.clamp(0, 255) as u8)
+    };
+    let alpha = match components.get(3) {
+        Some(component) => {
+            let alpha = component.parse::<f32>().ok().filter(|a| a.is_finite())?;
+            (alpha.clamp(0.0, 1.0) * 255.0).round() as u8
+        }
+        None => 255,
+    };
+    Some([
+        channel(components[0])?,
+        channel(components[1])?,
+        channel(components[2])?,
+        alpha,
+    ])
+}
+
+// Accepts `#rgb` and `#rrggbb`; the result is always opaque.
+pub fn parse_hex_color(value: &str) -> Option<[u8; 4]> {
+    let hex = value.trim().strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgb = [0_u8; 3];
+            for (i, out) in rgb.iter_mut().enumerate() {
+                *out = channel(&hex[i..i + 1])? * 17;
+            }
+            Some([rgb[0], rgb[1], rgb[2], 255])
+        }
+        6 => Some([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+            255,
+        ]),
+        _ => None,
     }
 }
 
 fn collect_scripts(document: &Document) -> Vec<ScriptSnippet> {
+    collect_scripts_with_options(document, &ScriptOptions::default())
+}
+
+pub fn collect_scripts_with_options(
+    document: &Document,
+    options: &ScriptOptions,
+) -> Vec<ScriptSnippet> {
     let mut snippets = Vec::new();
     for (node_id, node) in document.nodes.iter().enumerate() {
         let NodeKind::Element(el) = &node.kind else {
@@ -317,6 +1068,12 @@ fn collect_scripts(document: &Document) -> Vec<ScriptSnippet> {
         if el.tag_name != "script" {
             continue;
         }
+        if document
+            .ancestors(node_id)
+            .any(|ancestor| element_tag(document, ancestor) == Some("template"))
+        {
+            continue;
+        }
 
         let mut combined = String::new();
         for &child in &node.children {
@@ -325,10 +1082,23 @@ fn collect_scripts(document: &Document) -> Vec<ScriptSnippet> {
             }
         }
 
-        if !combined.trim().is_empty() {
+        if combined.trim().is_empty() {
+            continue;
+        }
+
+        if options.trim {
+            // Keep the stripped prefix length so diagnostics can map back to the source text.
+            let leading_offset = combined.len() - combined.trim_start().len();
+            snippets.push(ScriptSnippet {
+                node_id,
+                code: combined.trim().to_string(),
+                leading_offset,
+            });
+        } else {
             snippets.push(ScriptSnippet {
                 node_id,
                 code: combined,
+                leading_offset: 0,
             });
         }
     }
@@ -336,29 +1106,105 @@ fn collect_scripts(document: &Document) -> Vec<ScriptSnippet> {
     snippets
 }
 
-fn layout_node(
+// The horizontal band a node is laid out in: block boxes start at `x` (a blockquote adds its own
+// indent) and stop at `right`. `rows` counts the enclosing table rows and flex containers.
+#[derive(Debug, Clone, Copy)]
+struct ContainingBlock {
+    x: u32,
+    right: u32,
+    rows: u32,
+}
+
+impl ContainingBlock {
+    fn inset(x: u32, width: u32, rows: u32) -> Self {
+        Self {
+            x: x.saturating_add(4),
+            right: x.saturating_add(width.saturating_sub(4)),
+            rows,
+        }
+    }
+}
+
+// Rows and flex containers lay out their items by recursing into `layout_flow`, so past this
+// many levels they fall back to plain blocks rather than growing the call stack.
+const MAX_NESTED_ROWS: u32 = 32;
+
+// Lays `parent`'s children out in normal flow from `cursor_y` and returns the cursor below them,
+// stopping once it reaches `bottom`.
+#[allow(clippy::too_many_arguments)]
+fn layout_flow(
     document: &Document,
-    node_id: NodeId,
+    parent: NodeId,
+    block: ContainingBlock,
     depth: u32,
     mut cursor_y: u32,
-    viewport_width: u32,
-    viewport_height: u32,
+    bottom: u32,
+    options: &LayoutOptions,
     boxes: &mut Vec<LayoutBox>,
 ) -> u32 {
-    if cursor_y >= viewport_height {
-        return cursor_y;
-    }
-
-    let node = &document.nodes[node_id];
+    // Explicit stack instead of recursion so hostile nesting cannot overflow the call stack.
+    let mut stack: Vec<(NodeId, u32, ContainingBlock)> = document.nodes[parent]
+        .children
+        .iter()
+        .rev()
+        .map(|&child| (child, depth, block))
+        .collect();
+
+    while let Some((node_id, depth, block)) = stack.pop() {
+        if cursor_y >= bottom {
+            break;
+        }
+        cursor_y = layout_node(
+            document, node_id, depth, cursor_y, block, options, boxes, &mut stack,
+        );
+    }
+
+    cursor_y
+}
+
+#[allow(clippy::too_many_arguments)]
+fn layout_node(
+    document: &Document,
+    node_id: NodeId,
+    depth: u32,
+    mut cursor_y: u32,
+    block: ContainingBlock,
+    options: &LayoutOptions,
+    boxes: &mut Vec<LayoutBox>,
+    stack: &mut Vec<(NodeId, u32, ContainingBlock)>,
+) -> u32 {
+    let node = &document.nodes[node_id];
     match &node.kind {
         NodeKind::Element(el) => {
-            if el.tag_name == "script" {
+            // Hidden subtrees take no space; their children are never queued.
+            if el.tag_name == "script" || el.is_hidden() {
                 return cursor_y;
             }
+            if el.tag_name == "br" {
+                return layout_line_break(document, node_id, cursor_y);
+            }
 
-            let x = 8 + depth.saturating_mul(12);
-            let width = viewport_width.saturating_sub(x.saturating_add(8)).max(8);
-            let height = element_height(el.tag_name.as_str());
+            let x = if el.tag_name == "blockquote" {
+                block.x.saturating_add(QUOTE_INDENT)
+            } else {
+                block.x
+            };
+            let available = block.right.saturating_sub(x).max(8);
+            let width = style_width(el, available).unwrap_or_else(|| block_width(el, available));
+            let rows = block.rows.saturating_add(1);
+            if depth < options.max_depth && rows <= MAX_NESTED_ROWS {
+                if el.is_flex_row() {
+                    return layout_flex_row(
+                        document, node_id, x, width, cursor_y, depth, rows, options, boxes,
+                    );
+                }
+                if el.tag_name == "tr" {
+                    return layout_table_row(
+                        document, node_id, x, width, cursor_y, depth, rows, options, boxes,
+                    );
+                }
+            }
+            let height = options.element_height(el.tag_name.as_str());
 
             boxes.push(LayoutBox {
                 node_id,
@@ -369,30 +1215,31 @@ fn layout_node(
             });
 
             cursor_y = cursor_y.saturating_add(height).saturating_add(6);
-            for &child in &node.children {
-                cursor_y = layout_node(
-                    document,
-                    child,
-                    depth.saturating_add(1),
-                    cursor_y,
-                    viewport_width,
-                    viewport_height,
-                    boxes,
-                );
+            if depth < options.max_depth {
+                // Children, text included, wrap within this box rather than the whole viewport.
+                let inner = ContainingBlock {
+                    x: x.saturating_add(12),
+                    right: x.saturating_add(width),
+                    ..block
+                };
+                for &child in node.children.iter().rev() {
+                    stack.push((child, depth + 1, inner));
+                }
             }
         }
         NodeKind::Text(text) => {
             if !text.trim().is_empty() {
-                let x = 12 + depth.saturating_mul(12);
-                let width = viewport_width.saturating_sub(x.saturating_add(8)).max(8);
+                let x = block.x.saturating_add(4);
+                let width = block.right.saturating_sub(x).max(8);
+                let lines = text_lines(text, width).len() as u32;
                 boxes.push(LayoutBox {
                     node_id,
                     x,
                     y: cursor_y,
                     width,
-                    height: 18,
+                    height: text_block_height(lines),
                 });
-                cursor_y = cursor_y.saturating_add(24);
+                cursor_y = cursor_y.saturating_add(TEXT_LINE_ADVANCE.saturating_mul(lines));
             }
         }
     }
@@ -400,6 +1247,383 @@ fn layout_node(
     cursor_y
 }
 
+// Text runs already end their own line, so a `br` right after one only starts the next run on a
+// fresh line at the left margin; any other `br` leaves a blank line.
+fn layout_line_break(document: &Document, node_id: NodeId, cursor_y: u32) -> u32 {
+    let follows_text = document.nodes[node_id]
+        .parent
+        .and_then(|parent| {
+            let siblings = &document.nodes[parent].children;
+            let index = siblings.iter().position(|&child| child == node_id)?;
+            index.checked_sub(1).map(|previous| siblings[previous])
+        })
+        .is_some_and(|previous| {
+            matches!(&document.nodes[previous].kind, NodeKind::Text(text) if !text.trim().is_empty())
+        });
+    if follows_text {
+        cursor_y
+    } else {
+        cursor_y.saturating_add(TEXT_LINE_ADVANCE)
+    }
+}
+
+// Lays the row's `td`/`th` cells side by side in equal columns sized by the widest row of the
+// enclosing table. Cell contents stack inside their column; nested tables are not special-cased.
+#[allow(clippy::too_many_arguments)]
+fn layout_table_row(
+    document: &Document,
+    row_id: NodeId,
+    x: u32,
+    width: u32,
+    cursor_y: u32,
+    depth: u32,
+    rows: u32,
+    options: &LayoutOptions,
+    boxes: &mut Vec<LayoutBox>,
+) -> u32 {
+    let row_index = boxes.len();
+    boxes.push(LayoutBox {
+        node_id: row_id,
+        x,
+        y: cursor_y,
+        width,
+        height: 0,
+    });
+
+    let cells = table_cells(document, row_id);
+    let columns = document
+        .ancestors(row_id)
+        .find(|&id| element_tag(document, id) == Some("table"))
+        .map(|table| {
+            table_rows(document, table)
+                .iter()
+                .map(|&row| table_cells(document, row).len())
+                .max()
+                .unwrap_or(0)
+        })
+        .unwrap_or(cells.len())
+        .max(1) as u32;
+    let column_width = (width / columns).max(8);
+
+    let mut cell_indices = Vec::with_capacity(cells.len());
+    let mut row_height = options.element_height("tr");
+    for (column, &cell) in cells.iter().enumerate() {
+        let cell_x = x.saturating_add((column as u32).saturating_mul(column_width));
+        cell_indices.push(boxes.len());
+        boxes.push(LayoutBox {
+            node_id: cell,
+            x: cell_x,
+            y: cursor_y,
+            width: column_width,
+            height: 0,
+        });
+        let bottom = layout_flow(
+            document,
+            cell,
+            ContainingBlock::inset(cell_x, column_width, rows),
+            depth.saturating_add(2),
+            cursor_y.saturating_add(4),
+            u32::MAX,
+            options,
+            boxes,
+        );
+        row_height = row_height.max(bottom.saturating_sub(cursor_y));
+    }
+
+    for index in cell_indices {
+        boxes[index].height = row_height;
+    }
+    boxes[row_index].height = row_height;
+    cursor_y.saturating_add(row_height).saturating_add(6)
+}
+
+// Direct children sit side by side from the container's top-left, inset by 4px. Items with an
+// inline `width` keep it and the rest split what is left; an item that would overflow the
+// line starts a new one below the tallest item so far.
+#[allow(clippy::too_many_arguments)]
+fn layout_flex_row(
+    document: &Document,
+    container_id: NodeId,
+    x: u32,
+    width: u32,
+    cursor_y: u32,
+    depth: u32,
+    rows: u32,
+    options: &LayoutOptions,
+    boxes: &mut Vec<LayoutBox>,
+) -> u32 {
+    let container_index = boxes.len();
+    boxes.push(LayoutBox {
+        node_id: container_id,
+        x,
+        y: cursor_y,
+        width,
+        height: 0,
+    });
+
+    let items = flex_items(document, container_id);
+    let inner_x = x.saturating_add(4);
+    let inner_width = width.saturating_sub(8).max(8);
+    let fixed_widths: Vec<Option<u32>> = items
+        .iter()
+        .map(|&item| match &document.nodes[item].kind {
+            NodeKind::Element(el) => style_width(el, inner_width),
+            NodeKind::Text(_) => None,
+        })
+        .collect();
+    let fixed_total = fixed_widths
+        .iter()
+        .flatten()
+        .fold(0_u32, |total, width| total.saturating_add(*width));
+    let flexible = fixed_widths.iter().filter(|width| width.is_none()).count() as u32;
+    let shared_width = (inner_width.saturating_sub(fixed_total) / flexible.max(1)).max(8);
+
+    let mut item_x = inner_x;
+    let mut line_top = cursor_y.saturating_add(4);
+    let mut bottom = line_top;
+    for (&item, fixed_width) in items.iter().zip(fixed_widths) {
+        let item_width = fixed_width.unwrap_or(shared_width);
+        if item_x > inner_x
+            && item_x.saturating_add(item_width) > inner_x.saturating_add(inner_width)
+        {
+            item_x = inner_x;
+            line_top = bottom.saturating_add(6);
+        }
+
+        let item_index = boxes.len();
+        let height = match &document.nodes[item].kind {
+            NodeKind::Text(text) => text_block_height(text_lines(text, item_width).len() as u32),
+            NodeKind::Element(_) => 18,
+        };
+        boxes.push(LayoutBox {
+            node_id: item,
+            x: item_x,
+            y: line_top,
+            width: item_width,
+            height,
+        });
+        if let NodeKind::Element(el) = &document.nodes[item].kind {
+            let contents_bottom = layout_flow(
+                document,
+                item,
+                ContainingBlock::inset(item_x, item_width, rows),
+                depth.saturating_add(2),
+                line_top.saturating_add(4),
+                u32::MAX,
+                options,
+                boxes,
+            );
+            boxes[item_index].height = options
+                .element_height(&el.tag_name)
+                .max(contents_bottom.saturating_sub(line_top));
+        }
+        bottom = bottom.max(line_top.saturating_add(boxes[item_index].height));
+        item_x = item_x.saturating_add(item_width);
+    }
+
+    let tag = element_tag(document, container_id).unwrap_or_default();
+    let height = options
+        .element_height(tag)
+        .max(bottom.saturating_add(4).saturating_sub(cursor_y));
+    boxes[container_index].height = height;
+    cursor_y.saturating_add(height).saturating_add(6)
+}
+
+fn flex_items(document: &Document, container: NodeId) -> Vec<NodeId> {
+    document.nodes[container]
+        .children
+        .iter()
+        .copied()
+        .filter(|&child| match &document.nodes[child].kind {
+            NodeKind::Element(el) => el.tag_name != "script" && !el.is_hidden(),
+            NodeKind::Text(text) => !text.trim().is_empty(),
+        })
+        .collect()
+}
+
+fn table_rows(document: &Document, table: NodeId) -> Vec<NodeId> {
+    let mut rows = Vec::new();
+    for &child in &document.nodes[table].children {
+        match element_tag(document, child) {
+            Some("tr") => rows.push(child),
+            Some("thead" | "tbody" | "tfoot") => rows.extend(
+                document.nodes[child]
+                    .children
+                    .iter()
+                    .copied()
+                    .filter(|&row| element_tag(document, row) == Some("tr")),
+            ),
+            _ => {}
+        }
+    }
+    rows
+}
+
+fn table_cells(document: &Document, row: NodeId) -> Vec<NodeId> {
+    document.nodes[row]
+        .children
+        .iter()
+        .copied()
+        .filter(|&cell| match &document.nodes[cell].kind {
+            NodeKind::Element(el) => matches!(el.tag_name.as_str(), "td" | "th") && !el.is_hidden(),
+            NodeKind::Text(_) => false,
+        })
+        .collect()
+}
+
+fn element_tag(document: &Document, node_id: NodeId) -> Option<&str> {
+    match &document.nodes[node_id].kind {
+        NodeKind::Element(el) => Some(el.tag_name.as_str()),
+        NodeKind::Text(_) => None,
+    }
+}
+
+const TEXT_LINE_ADVANCE: u32 = 24;
+// Advance of one character of document text as the renderer draws it (bitmap font at scale 2).
+const TEXT_CHAR_WIDTH: u32 = 12;
+
+// A run's lines inside a text box of `box_width`, leaving the 4px label inset on both sides.
+fn text_lines(text: &str, box_width: u32) -> Vec<String> {
+    wrap_text(text, box_width.saturating_sub(8), TEXT_CHAR_WIDTH)
+}
+
+fn text_block_height(lines: u32) -> u32 {
+    18_u32.saturating_add(lines.saturating_sub(1).saturating_mul(TEXT_LINE_ADVANCE))
+}
+
+// Greedy word wrap on whitespace: a word moves to the next line when it would overflow. Only a
+// word wider than a whole line is hard-broken, with a trailing hyphen on each broken piece.
+fn wrap_text(text: &str, max_width: u32, char_width: u32) -> Vec<String> {
+    let max_chars = (max_width / char_width.max(1)).max(1) as usize;
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_chars = 0;
+    for mut word in text.split_whitespace() {
+        let mut word_chars = word.chars().count();
+        if line_chars > 0 && line_chars + 1 + word_chars <= max_chars {
+            line.push(' ');
+            line.push_str(word);
+            line_chars += 1 + word_chars;
+            continue;
+        }
+        if line_chars > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word_chars > max_chars {
+            let keep = max_chars.saturating_sub(1).max(1);
+            let split = word.char_indices().nth(keep).map_or(word.len(), |(i, _)| i);
+            let (head, tail) = word.split_at(split);
+            lines.push(if max_chars > 1 {
+                format!("{head}-")
+            } else {
+                head.to_string()
+            });
+            word = tail;
+            word_chars -= keep;
+        }
+        line.push_str(word);
+        line_chars = word_chars;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+// How far a `blockquote` steps in from its containing block; nested quotes add it per level.
+const QUOTE_INDENT: u32 = 16;
+const QUOTE_BAR_WIDTH: u32 = 3;
+const QUOTE_BAR_COLOR: [u8; 4] = [122, 146, 204, 255];
+
+// Thin bar down the quote's left edge, from its own box to the bottom of the last box laid out
+// inside it. Descendants directly follow the quote in `layout.boxes`.
+fn quote_bar(
+    document: &Document,
+    layout: &LayoutTree,
+    index: usize,
+    opacity: f32,
+) -> DisplayCommand {
+    let quote = &layout.boxes[index];
+    let bottom = layout.boxes[index + 1..]
+        .iter()
+        .take_while(|inner| {
+            document
+                .ancestors(inner.node_id)
+                .any(|id| id == quote.node_id)
+        })
+        .map(|inner| inner.y.saturating_add(inner.height))
+        .fold(quote.y.saturating_add(quote.height), u32::max);
+    DisplayCommand::FillRect {
+        x: quote.x,
+        y: quote.y,
+        width: QUOTE_BAR_WIDTH.min(quote.width),
+        height: bottom - quote.y,
+        color: with_opacity(QUOTE_BAR_COLOR, opacity),
+        node_id: Some(quote.node_id),
+    }
+}
+
+const HR_HEIGHT: u32 = 2;
+const HR_COLOR: [u8; 4] = [150, 162, 188, 255];
+
+const INPUT_WIDTH: u32 = 200;
+const INPUT_HEIGHT: u32 = 28;
+const PLACEHOLDER_TEXT_COLOR: [u8; 4] = [128, 136, 156, 255];
+
+const LINK_TEXT_COLOR: [u8; 4] = [26, 82, 214, 255];
+
+fn is_link_text(document: &Document, node_id: NodeId) -> bool {
+    matches!(document.nodes[node_id].kind, NodeKind::Text(_))
+        && document
+            .ancestors(node_id)
+            .any(|ancestor| element_tag(document, ancestor) == Some("a"))
+}
+
+// Inline `width` as `N%` of `available` or `Npx`, never wider than `available`. Other units and
+// negative values are ignored.
+fn style_width(el: &ElementData, available: u32) -> Option<u32> {
+    let value = el.style("width")?.to_ascii_lowercase();
+    let pixels = match value.strip_suffix('%') {
+        Some(percent) => f64::from(available) * percent.trim().parse::<f64>().ok()? / 100.0,
+        None => match value.strip_suffix("px") {
+            Some(px) => px.trim().parse::<f64>().ok()?,
+            None => value.parse::<f64>().ok().filter(|&zero| zero == 0.0)?,
+        },
+    };
+    if !pixels.is_finite() || pixels < 0.0 {
+        return None;
+    }
+    Some((pixels.round() as u32).min(available))
+}
+
+// Blocks fill the available width, except form fields: `size` characters of text plus padding,
+// or INPUT_WIDTH without it.
+fn block_width(el: &ElementData, available: u32) -> u32 {
+    if el.tag_name != "input" {
+        return available;
+    }
+    el.attr("size")
+        .and_then(|size| size.trim().parse::<u32>().ok())
+        .filter(|&size| size > 0)
+        .map_or(INPUT_WIDTH, |size| {
+            size.saturating_mul(TEXT_CHAR_WIDTH).saturating_add(8)
+        })
+        .min(available)
+}
+
+// Placeholder shown in an empty text-like `<input>`; other input types draw a bare box.
+fn input_placeholder(el: &ElementData) -> Option<&str> {
+    let text_like = el.attr("type").is_none_or(|kind| {
+        matches!(
+            kind.trim().to_ascii_lowercase().as_str(),
+            "" | "text" | "search" | "email" | "url" | "tel" | "password" | "number"
+        )
+    });
+    el.attr("placeholder")
+        .map(str::trim)
+        .filter(|placeholder| text_like && !placeholder.is_empty())
+}
+
 fn element_height(tag_name: &str) -> u32 {
     match tag_name {
         "html" => 26,
@@ -409,55 +1633,108 @@ fn element_height(tag_name: &str) -> u32 {
         "p" => 26,
         "div" => 30,
         "section" => 34,
+        "hr" => HR_HEIGHT,
+        "input" => INPUT_HEIGHT,
         _ => 24,
     }
 }
 
+// Inline `opacity` on the node and every ancestor, multiplied together. Accepts `0.5` or `50%`;
+// unparseable values count as fully opaque.
+fn node_opacity(document: &Document, node_id: NodeId) -> f32 {
+    std::iter::once(node_id)
+        .chain(document.ancestors(node_id))
+        .filter_map(|id| match &document.nodes[id].kind {
+            NodeKind::Element(el) => el.style("opacity").and_then(parse_opacity),
+            NodeKind::Text(_) => None,
+        })
+        .product()
+}
+
+fn parse_opacity(value: &str) -> Option<f32> {
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+        None => value.parse::<f32>().ok()?,
+    };
+    opacity.is_finite().then(|| opacity.clamp(0.0, 1.0))
+}
+
+fn with_opacity(color: [u8; 4], opacity: f32) -> [u8; 4] {
+    if opacity >= 1.0 {
+        return color;
+    }
+    let alpha = (color[3] as f32 * opacity).round() as u8;
+    [color[0], color[1], color[2], alpha]
+}
+
+// Inline `background-color` (or a plain-color `background`) wins over the per-tag palette.
 fn color_for_node(document: &Document, node_id: NodeId) -> [u8; 4] {
+    match &document.nodes[node_id].kind {
+        NodeKind::Element(el) => {
+            let background = el
+                .style("background-color")
+                .or_else(|| el.style("background"))
+                .and_then(parse_css_color);
+            background.unwrap_or(match el.tag_name.as_str() {
+                "html" => [233, 237, 248, 255],
+                "body" => [236, 241, 251, 255],
+                "header" | "footer" => [195, 212, 250, 255],
+                "main" | "article" | "section" | "aside" => [206, 221, 250, 255],
+                "nav" => [187, 206, 249, 255],
+                "h1" => [169, 192, 248, 255],
+                "h2" | "h3" => [179, 201, 248, 255],
+                "p" | "li" | "td" | "th" => [217, 228, 251, 255],
+                "hr" => HR_COLOR,
+                "input" => [255, 255, 255, 255],
+                _ => [210, 224, 250, 255],
+            })
+        }
+        NodeKind::Text(_) => [244, 246, 252, 255],
+    }
+}
+
+fn border_for_node(document: &Document, node_id: NodeId) -> Option<[u8; 4]> {
     match &document.nodes[node_id].kind {
         NodeKind::Element(el) => match el.tag_name.as_str() {
-            "html" => [233, 237, 248, 255],
-            "body" => [236, 241, 251, 255],
-            "header" | "footer" => [195, 212, 250, 255],
-            "main" | "article" | "section" | "aside" => [206, 221, 250, 255],
-            "nav" => [187, 206, 249, 255],
-            "h1" => [169, 192, 248, 255],
-            "h2" | "h3" => [179, 201, 248, 255],
-            "p" | "li" | "td" | "th" => [217, 228, 251, 255],
-            _ => [210, 224, 250, 255],
+            "table" | "td" | "th" | "pre" | "fieldset" | "input" => Some([120, 140, 182, 255]),
+            _ => None,
         },
-        NodeKind::Text(_) => [244, 246, 252, 255],
+        NodeKind::Text(_) => None,
     }
 }
 
-fn label_for_node(document: &Document, node_id: NodeId) -> Option<String> {
+fn label_for_node(
+    document: &Document,
+    node_id: NodeId,
+    options: &DisplayListOptions,
+) -> Option<String> {
     match &document.nodes[node_id].kind {
-        NodeKind::Element(el) => Some(format!("<{}>", el.tag_name)),
+        // A rule is only a couple of pixels tall; a tag label would spill over its neighbours.
+        NodeKind::Element(el) if el.tag_name == "hr" => None,
+        NodeKind::Element(el) if el.tag_name == "input" => {
+            input_placeholder(el).map(str::to_string)
+        }
+        NodeKind::Element(el) => {
+            let id = el
+                .attr("id")
+                .map(str::trim)
+                .filter(|id| options.element_ids && !id.is_empty());
+            Some(match id {
+                Some(id) => format!("<{}#{id}>", el.tag_name),
+                None => format!("<{}>", el.tag_name),
+            })
+        }
         NodeKind::Text(text) => {
             let condensed = text.split_whitespace().collect::<Vec<_>>().join(" ");
             if condensed.is_empty() {
                 None
             } else {
-                Some(truncate_text(&condensed, 64))
+                Some(condensed)
             }
         }
     }
 }
 
-fn truncate_text(text: &str, max_chars: usize) -> String {
-    if text.chars().count() <= max_chars {
-        return text.to_string();
-    }
-    let suffix = "...";
-    let keep = max_chars.saturating_sub(suffix.len());
-    let mut out = String::with_capacity(max_chars + suffix.len());
-    for ch in text.chars().take(keep) {
-        out.push(ch);
-    }
-    out.push_str(suffix);
-    out
-}
-
 fn normalize_tag_name(raw: &str) -> String {
     raw.trim_matches('/')
         .split_whitespace()
@@ -466,6 +1743,48 @@ fn normalize_tag_name(raw: &str) -> String {
         .to_ascii_lowercase()
 }
 
+fn parse_attributes(inside: &str) -> Vec<(String, String)> {
+    let inside = inside.trim_end_matches('/');
+    let Some(name_end) = inside.find(char::is_whitespace) else {
+        return Vec::new();
+    };
+
+    let mut attributes = Vec::new();
+    let mut rest = inside[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|ch: char| ch == '=' || ch.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let quote = after_eq
+                .chars()
+                .next()
+                .filter(|ch| *ch == '"' || *ch == '\'');
+            if let Some(quote) = quote {
+                let body = &after_eq[1..];
+                let end = body.find(quote).unwrap_or(body.len());
+                value = body[..end].to_string();
+                rest = body.get(end + 1..).unwrap_or("");
+            } else {
+                let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                value = after_eq[..end].to_string();
+                rest = &after_eq[end..];
+            }
+            rest = rest.trim_start();
+        }
+
+        if !key.is_empty() {
+            attributes.push((key, value));
+        }
+    }
+    attributes
+}
+
 fn is_void_element(name: &str) -> bool {
     matches!(name, "br" | "img" | "meta" | "link" | "hr" | "input")
 }
@@ -480,21 +1799,953 @@ fn find_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parses_functional_css_colors() {
+        assert_eq!(parse_css_color("rgb(255, 0, 0)"), Some([255, 0, 0, 255]));
+        assert_eq!(parse_css_color("rgba(0,0,0,0.5)"), Some([0, 0, 0, 128]));
+        assert_eq!(
+            parse_css_color(" RGB(300, -4, 16) "),
+            Some([255, 0, 16, 255])
+        );
+        assert_eq!(parse_css_color("rgba(1, 2, 3, 7)"), Some([1, 2, 3, 255]));
+        assert_eq!(parse_css_color("#fff"), Some([255, 255, 255, 255]));
+        assert_eq!(parse_css_color("rgb(300)"), None);
+        assert_eq!(parse_css_color("rgb(1, 2, 3, 0.5)"), None);
+        assert_eq!(parse_css_color("rgba(1, 2, 3)"), None);
+        assert_eq!(parse_css_color("rgb(1, 2, x)"), None);
+        assert_eq!(parse_css_color("hsl(0, 0, 0)"), None);
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_hex_color("#1e2a3b"), Some([0x1e, 0x2a, 0x3b, 255]));
+        assert_eq!(parse_hex_color("#FfF"), Some([255, 255, 255, 255]));
+        assert_eq!(parse_hex_color("1e2a3b"), None);
+        assert_eq!(parse_hex_color("#1e2a3"), None);
+        assert_eq!(parse_hex_color("#gg0000"), None);
+    }
+
     #[test]
     fn tokenizes_html() {
         let input = "<html><body><h1>Hello</h1><p>world</p></body></html>";
         let tokens = tokenize(input);
 
         assert!(tokens.contains(&Token::StartTag {
-            name: "html".to_string()
+            name: "html".to_string(),
+            attributes: Vec::new(),
         }));
         assert!(tokens.contains(&Token::StartTag {
-            name: "h1".to_string()
+            name: "h1".to_string(),
+            attributes: Vec::new(),
         }));
         assert!(tokens.contains(&Token::Text("Hello".to_string())));
         assert!(tokens.contains(&Token::Text("world".to_string())));
     }
 
+    #[test]
+    fn tokenizes_multibyte_text() {
+        let input = "<p>héllo — wörld</p><!-- ümlaut --><div>日本語</div>";
+        let tokens = tokenize(input);
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::StartTag {
+                    name: "p".to_string(),
+                    attributes: Vec::new(),
+                },
+                Token::Text("héllo — wörld".to_string()),
+                Token::EndTag {
+                    name: "p".to_string()
+                },
+                Token::StartTag {
+                    name: "div".to_string(),
+                    attributes: Vec::new(),
+                },
+                Token::Text("日本語".to_string()),
+                Token::EndTag {
+                    name: "div".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_multibyte_script_and_unterminated_input() {
+        let input = "<script>let s = \"ß→✓\";</SCRIPT><p>ünterminated <b";
+        let tokens = tokenize(input);
+
+        assert!(tokens.contains(&Token::Text("let s = \"ß→✓\";".to_string())));
+        assert!(tokens.contains(&Token::Text("ünterminated".to_string())));
+    }
+
+    #[test]
+    fn tokenizes_attributes() {
+        let tokens = tokenize("<a HREF='/x' data-id=7 hidden style=\"color: red\">link</a>");
+
+        assert_eq!(
+            tokens[0],
+            Token::StartTag {
+                name: "a".to_string(),
+                attributes: vec![
+                    ("href".to_string(), "/x".to_string()),
+                    ("data-id".to_string(), "7".to_string()),
+                    ("hidden".to_string(), String::new()),
+                    ("style".to_string(), "color: red".to_string()),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn display_none_elements_are_skipped_by_layout() {
+        let input = "<body><p style=\"display: none\">Hidden <b>child</b></p><h1>Shown</h1></body>";
+        let doc = parse_document(&tokenize(input));
+        let layout = layout_document(&doc, 640, 360);
+
+        let tag_of = |node_id: NodeId| match &doc.nodes[node_id].kind {
+            NodeKind::Element(el) => el.tag_name.clone(),
+            NodeKind::Text(text) => text.clone(),
+        };
+        let laid_out: Vec<String> = layout.boxes.iter().map(|b| tag_of(b.node_id)).collect();
+        assert_eq!(laid_out, vec!["body", "h1", "Shown"]);
+
+        let body = layout.boxes[0];
+        let h1 = layout.boxes[1];
+        assert_eq!(h1.y, body.y + body.height + 6);
+    }
+
+    #[test]
+    fn hidden_attribute_and_template_content_are_not_laid_out() {
+        let input = "<body><div hidden>Secret</div><template><p>Inert</p><script>run()</script></template><h1>Shown</h1></body>";
+        let output = render_document(input, 640, 360);
+
+        let laid_out: Vec<String> = output
+            .layout
+            .boxes
+            .iter()
+            .map(|b| match &output.document.nodes[b.node_id].kind {
+                NodeKind::Element(el) => el.tag_name.clone(),
+                NodeKind::Text(text) => text.clone(),
+            })
+            .collect();
+        assert_eq!(laid_out, vec!["body", "h1", "Shown"]);
+
+        // Template content stays in the DOM but never runs.
+        assert!(output
+            .document
+            .nodes
+            .iter()
+            .any(|node| matches!(&node.kind, NodeKind::Text(text) if text == "Inert")));
+        assert!(output.scripts.is_empty());
+    }
+
+    #[test]
+    fn compact_renumbers_after_detach() {
+        let mut doc = parse_document(&tokenize("<div><p>a</p><span>b</span></div>"));
+        // 0 document, 1 div, 2 p, 3 "a", 4 span, 5 "b"
+        doc.detach(2);
+        let (compacted, mapping) = doc.compact();
+
+        assert_eq!(
+            mapping,
+            vec![Some(0), Some(1), None, None, Some(2), Some(3)]
+        );
+        assert_eq!(compacted.nodes.len(), 4);
+        assert_eq!(compacted.nodes[1].children, vec![2]);
+        assert_eq!(compacted.nodes[2].parent, Some(1));
+        assert_eq!(compacted.nodes[3].parent, Some(2));
+        assert_eq!(compacted.nodes[3].kind, NodeKind::Text("b".to_string()));
+    }
+
+    #[test]
+    fn streaming_tokenizer_matches_one_shot() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.feed("<p>He");
+        let mut tokens = tokenizer.drain();
+        // The partial text run stays buffered until the next chunk arrives.
+        assert_eq!(tokens.len(), 1);
+
+        tokenizer.feed("llo</p>");
+        tokens.extend(tokenizer.drain());
+        tokens.extend(tokenizer.finish());
+
+        assert_eq!(tokens, tokenize("<p>Hello</p>"));
+    }
+
+    #[test]
+    fn streaming_tokenizer_buffers_split_comments_and_scripts() {
+        let input = "<div id=x>a<!-- c > d --><script>if (a < b) {}</script>tail";
+        let mut tokenizer = Tokenizer::new();
+        let mut tokens = Vec::new();
+        for chunk in input.as_bytes().chunks(3) {
+            tokenizer.feed(std::str::from_utf8(chunk).unwrap());
+            tokens.extend(tokenizer.drain());
+        }
+        tokens.extend(tokenizer.finish());
+
+        assert_eq!(tokens, tokenize(input));
+    }
+
+    #[test]
+    fn crlf_and_cr_line_endings_tokenize_like_lf() {
+        let lf = "<pre title=\"a\nb\">one\ntwo\n\nthree</pre>\n<p>end</p>\n";
+        let crlf = lf.replace('\n', "\r\n");
+        let cr = lf.replace('\n', "\r");
+        let expected = tokenize(lf);
+
+        assert_eq!(tokenize(&crlf), expected);
+        assert_eq!(tokenize(&cr), expected);
+        let (_, stats) = tokenize_with_stats(&crlf);
+        assert_eq!(stats.bytes_scanned, crlf.len());
+        assert!(expected
+            .iter()
+            .any(|token| matches!(token, Token::Text(text) if text.contains('\n'))));
+
+        let document = parse_document(&tokenize(&crlf));
+        assert!(document.nodes.iter().all(|node| match &node.kind {
+            NodeKind::Text(text) => !text.contains('\r'),
+            NodeKind::Element(el) => el.attributes.iter().all(|(_, value)| !value.contains('\r')),
+        }));
+
+        // A `\r\n` split across chunks is still one line break.
+        let mut tokenizer = Tokenizer::new();
+        let mut tokens = Vec::new();
+        for chunk in crlf.split_inclusive('\r') {
+            tokenizer.feed(chunk);
+            tokens.extend(tokenizer.drain());
+        }
+        tokens.extend(tokenizer.finish());
+        assert_eq!(tokens, expected);
+
+        // Only the `\n` directly after the `\r` is swallowed, even when it is a chunk of its own.
+        let mut tokenizer = Tokenizer::new();
+        for chunk in ["<pre>a\r", "\n", "\n", "b</pre>"] {
+            tokenizer.feed(chunk);
+        }
+        assert_eq!(tokenizer.finish(), tokenize("<pre>a\n\nb</pre>"));
+    }
+
+    #[test]
+    fn anchors_become_clickable_regions() {
+        let input = "<body><p>intro</p><a href=\"/next\">Next <b>page</b></a></body>";
+        let doc = parse_document(&tokenize(input));
+        let layout = layout_document(&doc, 640, 360);
+        let regions = clickable_regions(&doc, &layout);
+
+        let anchor_box = layout
+            .boxes
+            .iter()
+            .find(|b| {
+                matches!(&doc.nodes[b.node_id].kind, NodeKind::Element(el) if el.tag_name == "a")
+            })
+            .copied()
+            .unwrap();
+        assert_eq!(regions, vec![(anchor_box, "/next".to_string())]);
+    }
+
+    #[test]
+    fn flex_row_children_share_one_row() {
+        let input = "<div style=\"display:flex\"><p>A</p><p>B</p><p>C</p></div><p>After</p>";
+        let doc = parse_document(&tokenize(input));
+        let layout = layout_document(&doc, 640, 360);
+        let container = layout.boxes[0];
+        let items: Vec<LayoutBox> = layout
+            .boxes
+            .iter()
+            .copied()
+            .filter(|b| element_tag(&doc, b.node_id) == Some("p"))
+            .collect();
+        let [a, b, c, after] = items[..] else {
+            panic!("expected four paragraphs, got {items:?}");
+        };
+
+        assert!(a.x < b.x && b.x < c.x);
+        assert!(a.y == b.y && b.y == c.y);
+        assert_eq!((a.width, b.width), (c.width, c.width));
+        assert_eq!(b.x, a.x + a.width);
+        assert!(c.x + c.width <= container.x + container.width);
+        assert!(a.y + a.height <= container.y + container.height);
+        assert!(after.y >= container.y + container.height);
+        assert_eq!(after.x, container.x);
+
+        // Item contents stay inside their item.
+        let text_b = layout
+            .boxes
+            .iter()
+            .find(|text| doc.nodes[text.node_id].kind == NodeKind::Text("B".to_string()))
+            .unwrap();
+        assert!(text_b.x >= b.x && text_b.x + text_b.width <= b.x + b.width);
+        assert!(text_b.y > b.y);
+    }
+
+    #[test]
+    fn flex_row_wraps_items_past_the_container_width() {
+        let item = "<div style=\"width:300px\"></div>";
+        let input = format!(
+            "<section style=\"display: flex\">{}</section>",
+            item.repeat(3)
+        );
+        let doc = parse_document(&tokenize(&input));
+        let layout = layout_document(&doc, 640, 360);
+        let items: Vec<LayoutBox> = layout
+            .boxes
+            .iter()
+            .copied()
+            .filter(|b| element_tag(&doc, b.node_id) == Some("div"))
+            .collect();
+
+        // The section is 624px wide with 616px inside its inset: two 300px items fit.
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].y, items[1].y);
+        assert_eq!(items[1].x, items[0].x + 300);
+        assert_eq!(items[2].x, items[0].x);
+        assert!(items[2].y >= items[0].y + items[0].height);
+        assert_eq!(items.iter().map(|b| b.width).collect::<Vec<_>>(), [300; 3]);
+
+        let column = "<div style=\"display:flex; flex-direction: column\"><p>A</p><p>B</p></div>";
+        let doc = parse_document(&tokenize(column));
+        let layout = layout_document(&doc, 640, 360);
+        let ps: Vec<LayoutBox> = layout
+            .boxes
+            .iter()
+            .copied()
+            .filter(|b| element_tag(&doc, b.node_id) == Some("p"))
+            .collect();
+        assert!(ps[1].y > ps[0].y);
+    }
+
+    #[test]
+    fn table_rows_lay_cells_out_in_columns() {
+        let input = "<table><tr><td>a</td><td>b</td></tr><tr><td>c</td><td>d</td></tr></table>";
+        let doc = parse_document(&tokenize(input));
+        let layout = layout_document(&doc, 640, 360);
+
+        let cells: Vec<LayoutBox> = layout
+            .boxes
+            .iter()
+            .copied()
+            .filter(|b| element_tag(&doc, b.node_id) == Some("td"))
+            .collect();
+        assert_eq!(cells.len(), 4);
+
+        let mut xs: Vec<u32> = cells.iter().map(|b| b.x).collect();
+        let mut ys: Vec<u32> = cells.iter().map(|b| b.y).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+        assert_eq!(xs.len(), 2);
+        assert_eq!(ys.len(), 2);
+        assert_eq!(cells[0].width, cells[1].width);
+        assert_eq!(cells[1].x, cells[0].x + cells[0].width);
+
+        // Cell text stays inside its column.
+        let text_c = layout
+            .boxes
+            .iter()
+            .find(|b| doc.nodes[b.node_id].kind == NodeKind::Text("c".to_string()))
+            .unwrap();
+        assert!(text_c.x >= cells[2].x && text_c.x < cells[3].x);
+    }
+
+    #[test]
+    fn cell_contents_get_the_same_block_layout_as_the_page() {
+        let input = "<table><tr><td><blockquote><p>q</p></blockquote>\
+                     <div style=\"width: 40px\">w</div>\
+                     <div style=\"display: flex\"><span>x</span><span>y</span></div></td>\
+                     </tr></table>";
+        let doc = parse_document(&tokenize(input));
+        let layout = layout_document(&doc, 640, 360);
+        let tag_box = |tag: &str| {
+            *layout
+                .boxes
+                .iter()
+                .find(|b| element_tag(&doc, b.node_id) == Some(tag))
+                .unwrap()
+        };
+
+        let cell = tag_box("td");
+        assert_eq!(tag_box("blockquote").x, cell.x + 4 + QUOTE_INDENT);
+        assert_eq!(tag_box("div").width, 40);
+        let spans: Vec<LayoutBox> = layout
+            .boxes
+            .iter()
+            .copied()
+            .filter(|b| element_tag(&doc, b.node_id) == Some("span"))
+            .collect();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].y, spans[1].y);
+        assert!(spans[1].x > spans[0].x);
+        for layout_box in &layout.boxes[1..] {
+            assert!(
+                layout_box.x + layout_box.width <= cell.x + cell.width,
+                "{layout_box:?} leaves {cell:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn relayout_matches_full_render_at_new_size() {
+        let html =
+            "<html><body><h1>Title</h1><p>Body text</p><div><span>x</span></div></body></html>";
+        let cached = render_document(html, 640, 360);
+
+        let (layout, display_list) = relayout(&cached.document, 320, 200);
+        let full = render_document(html, 320, 200);
+        assert_eq!(layout, full.layout);
+        assert_eq!(display_list, full.display_list);
+    }
+
+    #[test]
+    fn zero_sized_viewport_lays_out_nothing() {
+        let html =
+            "<h1>Title</h1><p>Body <a href=\"/x\">link</a></p><table><tr><td>a</td></tr></table>";
+        for (width, height) in [(0, 360), (640, 0), (0, 0)] {
+            let output = render_document(html, width, height);
+            assert!(output.layout.boxes.is_empty(), "{width}x{height}");
+            assert!(output.display_list.commands.is_empty(), "{width}x{height}");
+            assert_eq!(output.display_list.viewport_width, width);
+            assert_eq!(output.display_list.viewport_height, height);
+        }
+    }
+
+    #[test]
+    fn layout_snapshot_lists_boxes_in_paint_order() {
+        let output = render_document("<h1>Title</h1><p>Body <b>bold</b></p>", 320, 200);
+        // h1, its text, p, its text, b, its text: parents paint before their children.
+        let expected = "\
+1 8 8 304 44
+2 24 58 288 18
+3 8 82 304 26
+4 24 114 288 18
+5 20 138 292 24
+6 36 168 276 18
+";
+        assert_eq!(output.layout.to_snapshot(), expected);
+        assert_eq!(LayoutTree { boxes: Vec::new() }.to_snapshot(), "");
+    }
+
+    #[test]
+    fn text_align_is_inherited_by_text_commands() {
+        let html = "<p style=\"text-align: center\">Centered <b>Bold</b></p>\
+                    <div style=\"text-align:right\"><p style=\"text-align: left\">Left</p></div>\
+                    <p style=\"text-align: sideways\">Plain</p>";
+        let output = render_document(html, 640, 360);
+        let text_command = |label: &str| {
+            output
+                .display_list
+                .commands
+                .iter()
+                .find_map(|cmd| match cmd {
+                    DisplayCommand::DrawText {
+                        x,
+                        text,
+                        align,
+                        box_width,
+                        node_id,
+                        ..
+                    } if text == label => Some((*x, *align, *box_width, node_id.unwrap())),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let (x, align, box_width, node_id) = text_command("Centered");
+        assert_eq!(align, TextAlign::Center);
+        let layout_box = output
+            .layout
+            .boxes
+            .iter()
+            .find(|b| b.node_id == node_id)
+            .unwrap();
+        assert_eq!((x, box_width), (layout_box.x + 4, layout_box.width - 8));
+
+        assert_eq!(text_command("Bold").1, TextAlign::Center);
+        assert_eq!(text_command("Left").1, TextAlign::Left);
+        assert_eq!(text_command("Plain").1, TextAlign::Start);
+    }
+
+    #[test]
+    fn dir_attribute_sets_inherited_text_direction() {
+        let output = render_document(
+            "<div dir=\"RTL\"><p>Right</p><p dir=\"ltr\">Forced</p><p dir=\"auto\">Auto</p></div><p>Left</p>",
+            320,
+            480,
+        );
+        let text_command = |label: &str| {
+            output
+                .display_list
+                .commands
+                .iter()
+                .find_map(|cmd| match cmd {
+                    DisplayCommand::DrawText {
+                        x, text, direction, ..
+                    } if text == label => Some((*x, *direction)),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let (right_x, right_direction) = text_command("Right");
+        assert_eq!(right_direction, TextDirection::Rtl);
+        assert!(
+            right_x > 280,
+            "rtl text anchors at the right edge, got {right_x}"
+        );
+        assert_eq!(text_command("Auto").1, TextDirection::Rtl);
+
+        let (forced_x, forced_direction) = text_command("Forced");
+        assert_eq!(forced_direction, TextDirection::Ltr);
+        assert!(forced_x < 60, "got {forced_x}");
+
+        let (left_x, left_direction) = text_command("Left");
+        assert_eq!(left_direction, TextDirection::Ltr);
+        assert!(left_x < 40, "got {left_x}");
+    }
+
+    #[test]
+    fn percentage_width_takes_share_of_available_width() {
+        let document = parse_document(&tokenize(
+            "<div style=\"width:50%\">Half</div><div style=\"width: 25.5 %\"></div>",
+        ));
+        let layout = layout_document(&document, 640, 360);
+        let widths: Vec<u32> = layout
+            .boxes
+            .iter()
+            .filter(|layout_box| element_tag(&document, layout_box.node_id) == Some("div"))
+            .map(|layout_box| layout_box.width)
+            .collect();
+
+        // x = 8 at depth 0, leaving 640 - 8 - 8 = 624 pixels.
+        assert_eq!(widths, [312, 159]);
+    }
+
+    #[test]
+    fn children_of_a_sized_box_stay_within_its_width() {
+        let words = "word ".repeat(40);
+        let document = parse_document(&tokenize(&format!(
+            "<div style=\"width:50%\"><p>{words}</p>{words}<div style=\"width:50%\"></div></div>"
+        )));
+        let layout = layout_document(&document, 640, 2000);
+        let parent = layout.boxes[0];
+        assert_eq!((parent.x, parent.width), (8, 312));
+
+        let children = &layout.boxes[1..];
+        assert!(children.len() >= 4, "{children:?}");
+        for child in children {
+            assert!(
+                child.x + child.width <= parent.x + parent.width,
+                "{child:?} overflows {parent:?}"
+            );
+        }
+        // Text wraps against the parent's width, and percentages resolve against it.
+        let text = children
+            .iter()
+            .find(|b| matches!(document.nodes[b.node_id].kind, NodeKind::Text(_)))
+            .unwrap();
+        assert!(text.height > text_block_height(1), "{text:?}");
+        assert_eq!(children.last().unwrap().width, (312 - 12) / 2);
+    }
+
+    #[test]
+    fn pixel_width_is_clamped_to_available_width() {
+        let document = parse_document(&tokenize(
+            "<div style=\"width:200px\"></div><div style=\"width:2000px\"></div>\
+             <div style=\"width:-5px\"></div><div style=\"width:3em\"></div>",
+        ));
+        let layout = layout_document(&document, 640, 360);
+        let widths: Vec<u32> = layout
+            .boxes
+            .iter()
+            .map(|layout_box| layout_box.width)
+            .collect();
+
+        assert_eq!(widths, [200, 624, 624, 624]);
+    }
+
+    #[test]
+    fn display_list_iter_streams_the_pre_iterator_commands() {
+        let html = "<h1>Title</h1><p>Body <a href=\"/x\">link</a></p><hr>";
+        let document = parse_document(&tokenize(html));
+        let layout = layout_document(&document, 200, 240);
+        let options = DisplayListOptions::default();
+
+        let fill = |x, y, width, height, color, node_id| DisplayCommand::FillRect {
+            x,
+            y,
+            width,
+            height,
+            color,
+            node_id,
+        };
+        let text = |x, y, text: &str, box_width, node_id| DisplayCommand::DrawText {
+            x,
+            y,
+            text: text.to_string(),
+            color: [18, 24, 45, 255],
+            underline: false,
+            direction: TextDirection::Ltr,
+            align: TextAlign::Start,
+            box_width,
+            node_id: Some(node_id),
+        };
+        let text_fill = [244, 246, 252, 255];
+        // The eager builder's output for this page from before it was rebuilt on the iterator.
+        let expected = [
+            fill(0, 0, 200, 240, [245, 245, 248, 255], None),
+            fill(8, 8, 184, 44, [169, 192, 248, 255], Some(1)),
+            text(12, 12, "<h1>", 176, 1),
+            fill(24, 58, 168, 18, text_fill, Some(2)),
+            text(28, 62, "Title", 160, 2),
+            fill(8, 82, 184, 26, [217, 228, 251, 255], Some(3)),
+            text(12, 86, "<p>", 176, 3),
+            fill(24, 114, 168, 18, text_fill, Some(4)),
+            text(28, 118, "Body", 160, 4),
+            fill(20, 138, 172, 24, [210, 224, 250, 255], Some(5)),
+            text(24, 142, "<a>", 164, 5),
+            fill(36, 168, 156, 18, text_fill, Some(6)),
+            DisplayCommand::DrawText {
+                x: 40,
+                y: 172,
+                text: "link".to_string(),
+                color: LINK_TEXT_COLOR,
+                underline: true,
+                direction: TextDirection::Ltr,
+                align: TextAlign::Start,
+                box_width: 148,
+                node_id: Some(6),
+            },
+            fill(8, 192, 184, 2, HR_COLOR, Some(7)),
+        ];
+        let streamed: Vec<_> =
+            DisplayListIter::new(&document, &layout, 200, 240, &options).collect();
+        assert_eq!(streamed, expected);
+        assert_eq!(
+            build_display_list_with_options(&document, &layout, 200, 240, &options).commands,
+            expected
+        );
+
+        assert_eq!(
+            DisplayListIter::new(&document, &layout, 0, 240, &options).next(),
+            None
+        );
+    }
+
+    #[test]
+    fn nested_opacity_multiplies_into_fill_and_text_alpha() {
+        let output = render_document(
+            "<div style=\"opacity: 0.5; background-color: #ff0000\"><p style=\"opacity:50%\">Faded</p></div>",
+            320,
+            240,
+        );
+        let fill_alpha = |tag: &str| {
+            output
+                .display_list
+                .commands
+                .iter()
+                .find_map(|cmd| match cmd {
+                    DisplayCommand::FillRect {
+                        color,
+                        node_id: Some(id),
+                        ..
+                    } if element_tag(&output.document, *id) == Some(tag) => Some(*color),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(fill_alpha("div"), [255, 0, 0, 128]);
+        assert_eq!(fill_alpha("p")[3], 64);
+
+        let text_alpha = output
+            .display_list
+            .commands
+            .iter()
+            .find_map(|cmd| match cmd {
+                DisplayCommand::DrawText { text, color, .. } if text == "Faded" => Some(color[3]),
+                _ => None,
+            });
+        assert_eq!(text_alpha, Some(64));
+    }
+
+    #[test]
+    fn blockquote_is_indented_with_a_left_bar() {
+        let output = render_document(
+            "<p>Said</p><blockquote><p>Quoted</p><p>More</p></blockquote>",
+            320,
+            240,
+        );
+        let tag_box = |tag: &str| {
+            output
+                .layout
+                .boxes
+                .iter()
+                .find(|b| element_tag(&output.document, b.node_id) == Some(tag))
+                .unwrap()
+        };
+        let paragraph = tag_box("p");
+        let quote = tag_box("blockquote");
+        assert!(quote.x > paragraph.x, "{quote:?} vs {paragraph:?}");
+
+        let bars: Vec<_> = output
+            .display_list
+            .commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DisplayCommand::FillRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                    node_id,
+                } if *node_id == Some(quote.node_id) && *color == QUOTE_BAR_COLOR => {
+                    Some((*x, *y, *width, *height))
+                }
+                _ => None,
+            })
+            .collect();
+        let last = output.layout.boxes.last().unwrap();
+        assert_eq!(
+            bars,
+            [(
+                quote.x,
+                quote.y,
+                QUOTE_BAR_WIDTH,
+                last.y + last.height - quote.y
+            )]
+        );
+        assert!(output.display_list.is_well_ordered(&output.document));
+    }
+
+    #[test]
+    fn input_renders_a_bordered_box_with_a_muted_placeholder() {
+        let output = render_document(
+            "<input placeholder=\"name\"><input size=\"5\"><input type=\"hidden\">",
+            640,
+            240,
+        );
+        let inputs: Vec<_> = output
+            .layout
+            .boxes
+            .iter()
+            .filter(|b| element_tag(&output.document, b.node_id) == Some("input"))
+            .collect();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(
+            (inputs[0].width, inputs[0].height),
+            (INPUT_WIDTH, INPUT_HEIGHT)
+        );
+        assert_eq!(inputs[1].width, 5 * TEXT_CHAR_WIDTH + 8);
+
+        let commands = &output.display_list.commands;
+        assert!(commands.iter().any(|cmd| matches!(
+            cmd,
+            DisplayCommand::StrokeRect { node_id, width, .. }
+                if *node_id == Some(inputs[0].node_id) && *width == INPUT_WIDTH
+        )));
+        let texts: Vec<_> = commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DisplayCommand::DrawText {
+                    text,
+                    color,
+                    node_id,
+                    ..
+                } if node_id.is_some_and(|id| inputs.iter().any(|input| input.node_id == id)) => {
+                    Some((text.as_str(), *color))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, [("name", PLACEHOLDER_TEXT_COLOR)]);
+    }
+
+    #[test]
+    fn text_wraps_before_the_word_that_would_overflow() {
+        assert_eq!(
+            wrap_text("one two three four", 10 * 12, 12),
+            ["one two", "three four"]
+        );
+        assert_eq!(
+            wrap_text("one two three four", 13 * 12, 12),
+            ["one two three", "four"]
+        );
+
+        let output = render_document("<p>one two three four</p><p>next</p>", 180, 240);
+        let texts: Vec<(u32, &str)> = output
+            .display_list
+            .commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DisplayCommand::DrawText { y, text, .. } if !text.starts_with('<') => {
+                    Some((*y, text.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+        // 148px text box: 11 characters fit, so "three" starts the second line.
+        assert_eq!(texts, [(44, "one two"), (68, "three four"), (124, "next")]);
+        assert_eq!(output.layout.boxes[1].height, 18 + TEXT_LINE_ADVANCE);
+    }
+
+    #[test]
+    fn over_wide_word_is_hard_broken_with_hyphens() {
+        assert_eq!(
+            wrap_text("a supercalifragilistic b", 6 * 12, 12),
+            ["a", "super-", "calif-", "ragil-", "istic", "b"]
+        );
+        assert_eq!(wrap_text("abc", 12, 12), ["a", "b", "c"]);
+        assert!(wrap_text("   ", 120, 12).is_empty());
+    }
+
+    #[test]
+    fn hr_renders_as_thin_rule() {
+        let output = render_document("<p>Above</p><hr><p>Below</p>", 320, 240);
+        let hr = output
+            .document
+            .nodes
+            .iter()
+            .position(|node| matches!(&node.kind, NodeKind::Element(el) if el.tag_name == "hr"))
+            .unwrap();
+
+        let rules: Vec<_> = output
+            .display_list
+            .commands
+            .iter()
+            .filter(|cmd| match cmd {
+                DisplayCommand::FillRect { node_id, .. } => *node_id == Some(hr),
+                DisplayCommand::StrokeRect { node_id, .. }
+                | DisplayCommand::DrawText { node_id, .. } => *node_id == Some(hr),
+            })
+            .collect();
+        let [DisplayCommand::FillRect {
+            width,
+            height,
+            color,
+            ..
+        }] = rules.as_slice()
+        else {
+            panic!("expected a single rule rect, got {rules:?}");
+        };
+        assert_eq!(*height, HR_HEIGHT);
+        assert!(*width > 280, "rule spans the content width, got {width}");
+        assert_eq!(*color, HR_COLOR);
+
+        // Every other box is far taller, and the next paragraph starts just below the rule.
+        let boxes = &output.layout.boxes;
+        let index = boxes.iter().position(|b| b.node_id == hr).unwrap();
+        assert!(boxes
+            .iter()
+            .filter(|b| b.node_id != hr)
+            .all(|b| b.height >= 18));
+        assert_eq!(boxes[index + 1].y, boxes[index].y + HR_HEIGHT + 6);
+    }
+
+    #[test]
+    fn document_title_reads_the_first_title() {
+        let doc = parse_document(&tokenize(
+            "<html><head><title>  Hello\n  world </title><title>Second</title></head></html>",
+        ));
+        assert_eq!(document_title(&doc).as_deref(), Some("Hello world"));
+
+        let doc = parse_document(&tokenize("<title> </title><p>Body</p>"));
+        assert_eq!(document_title(&doc), None);
+    }
+
+    #[test]
+    fn document_text_joins_visible_runs() {
+        let doc = parse_document(&tokenize(
+            "<html><head><style>p { color: red }</style></head><body><h1>Heading</h1>\n  <script>var x = 1;</script><p>Paragraph</p></body></html>",
+        ));
+        assert_eq!(document_text(&doc), "Heading Paragraph");
+
+        let doc = parse_document(&tokenize(
+            "<p>two   words</p><div hidden>gone</div><p>and\nmore</p>",
+        ));
+        assert_eq!(document_text(&doc), "two words and more");
+    }
+
+    #[test]
+    fn debug_tokens_prints_one_token_per_line() {
+        let tokens = vec![
+            Token::StartTag {
+                name: "a".to_string(),
+                attributes: vec![("href".to_string(), "/x".to_string())],
+            },
+            Token::Text("Hello \"you\"".to_string()),
+            Token::EndTag {
+                name: "a".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            debug_tokens(&tokens),
+            "START a href=\"/x\"\nTEXT \"Hello \\\"you\\\"\"\nEND a\n"
+        );
+        assert_eq!(debug_tokens(&[]), "");
+    }
+
+    #[test]
+    fn br_starts_next_text_run_on_a_new_line() {
+        let output = render_document("<p>first<br>second</p>", 640, 360);
+        let text_box = |label: &str| {
+            output
+                .layout
+                .boxes
+                .iter()
+                .find(|b| matches!(&output.document.nodes[b.node_id].kind, NodeKind::Text(t) if t == label))
+                .copied()
+                .unwrap()
+        };
+
+        let first = text_box("first");
+        let second = text_box("second");
+        assert_eq!(second.x, first.x);
+        assert_eq!(second.y, first.y + 24);
+        assert!(!output.layout.boxes.iter().any(|b| {
+            matches!(&output.document.nodes[b.node_id].kind, NodeKind::Element(el) if el.tag_name == "br")
+        }));
+
+        let blank = render_document("<p>first<br><br>second</p>", 640, 360);
+        let second_after_blank = blank
+            .layout
+            .boxes
+            .iter()
+            .find(|b| matches!(&blank.document.nodes[b.node_id].kind, NodeKind::Text(t) if t == "second"))
+            .unwrap();
+        assert_eq!(second_after_blank.y, first.y + 48);
+    }
+
+    #[test]
+    fn link_text_is_blue_and_underlined() {
+        let output = render_document("<p>plain</p><a href=\"/x\"><b>link</b></a>", 640, 360);
+        let text_command = |label: &str| {
+            output
+                .display_list
+                .commands
+                .iter()
+                .find_map(|cmd| match cmd {
+                    DisplayCommand::DrawText {
+                        text,
+                        color,
+                        underline,
+                        ..
+                    } if text == label => Some((*color, *underline)),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let (link_color, link_underline) = text_command("link");
+        assert!(link_underline);
+        assert!(link_color[2] > link_color[0] && link_color[2] > link_color[1]);
+
+        let (plain_color, plain_underline) = text_command("plain");
+        assert!(!plain_underline);
+        assert_eq!(plain_color, [18, 24, 45, 255]);
+    }
+
     #[test]
     fn builds_dom_shape() {
         let input = "<html><body><h1>Hello</h1><p>Body</p></body></html>";
@@ -535,6 +2786,276 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deep_nesting_respects_max_depth() {
+        let depth = 10_000;
+        let input = format!("{}{}", "<div>".repeat(depth), "</div>".repeat(depth));
+        let document = parse_document(&tokenize(&input));
+        let options = LayoutOptions::default().with_max_depth(64);
+        let layout = layout_document_with_options(&document, 640, u32::MAX, &options);
+
+        assert_eq!(layout.boxes.len(), 65);
+        assert_eq!(layout.boxes.last().map(|b| b.x), Some(8 + 64 * 12));
+
+        let output = render_document(&input, 640, 360);
+        assert!(!output.layout.boxes.is_empty());
+
+        // Rows recurse into their cells, so deeply nested tables must still stay off the stack.
+        let cell = "<table><tr><td>";
+        let input = format!(
+            "{}x{}",
+            cell.repeat(depth),
+            "</td></tr></table>".repeat(depth)
+        );
+        let document = parse_document(&tokenize(&input));
+        let options = LayoutOptions::default().with_max_depth(u32::MAX);
+        let layout = layout_document_with_options(&document, 640, u32::MAX, &options);
+        assert!(layout.boxes.len() > depth);
+    }
+
+    #[test]
+    fn extreme_viewport_width_keeps_box_edges_in_range() {
+        let nested = format!("{}text{}", "<div>".repeat(2_000), "</div>".repeat(2_000));
+        let input = format!(
+            "<div style=\"display: flex\"><p style=\"width: 100%\">a</p>\
+             <p style=\"width: 100%\">b</p><p>c</p></div>\
+             <table><tr><td>1</td><td>2</td></tr></table>{nested}"
+        );
+        let document = parse_document(&tokenize(&input));
+        let options = LayoutOptions::default().with_max_depth(u32::MAX);
+        let width = u32::MAX - 3;
+        let layout = layout_document_with_options(&document, width, u32::MAX, &options);
+
+        assert!(layout.boxes.len() > 2_000);
+        for layout_box in &layout.boxes {
+            assert!(layout_box.width <= width, "{layout_box:?}");
+            assert!(
+                layout_box.x.checked_add(layout_box.width).is_some(),
+                "{layout_box:?}"
+            );
+            assert!(
+                layout_box.y.checked_add(layout_box.height).is_some(),
+                "{layout_box:?}"
+            );
+        }
+        let deepest = layout.boxes.last().unwrap();
+        assert_eq!(deepest.x, 12 + 2_000 * 12);
+        assert_eq!(deepest.width, width - deepest.x - 8);
+
+        let display_list = build_display_list(&document, &layout, width, u32::MAX);
+        assert!(display_list.commands.len() > layout.boxes.len());
+    }
+
+    #[test]
+    fn snap_to_grid_rounds_boxes() {
+        assert_eq!(snap_to_step(18, 4), 20);
+        assert_eq!(snap_to_step(17, 4), 16);
+        assert_eq!(snap_to_step(18, 1), 18);
+
+        let input = "<html><body><h1>Title</h1><p>Copy</p></body></html>";
+        let document = parse_document(&tokenize(input));
+        let plain = layout_document(&document, 640, 360);
+        let unit = layout_document_with_options(
+            &document,
+            640,
+            360,
+            &LayoutOptions::default().snap_to_grid(1),
+        );
+        assert_eq!(plain, unit);
+
+        let snapped = layout_document_with_options(
+            &document,
+            640,
+            360,
+            &LayoutOptions::default().snap_to_grid(4),
+        );
+        assert!(snapped
+            .boxes
+            .iter()
+            .all(|b| b.y % 4 == 0 && b.height % 4 == 0 && b.height > 0));
+    }
+
+    #[test]
+    fn tag_height_overrides_replace_the_default() {
+        let document = parse_document(&tokenize("<h1>Title</h1><p>Copy</p><div>Box</div>"));
+        let options = LayoutOptions::default().with_tag_height("P", 40);
+        let layout = layout_document_with_options(&document, 640, 360, &options);
+        let height = |tag: &str| {
+            layout
+                .boxes
+                .iter()
+                .find(|b| element_tag(&document, b.node_id) == Some(tag))
+                .map(|b| b.height)
+        };
+        assert_eq!(height("p"), Some(40));
+        assert_eq!(height("h1"), Some(element_height("h1")));
+        assert_eq!(height("div"), Some(element_height("div")));
+
+        // Everything below the paragraph shifts down by the extra height.
+        let plain = layout_document(&document, 640, 360);
+        let div = |layout: &LayoutTree| {
+            layout
+                .boxes
+                .iter()
+                .find(|b| element_tag(&document, b.node_id) == Some("div"))
+                .unwrap()
+                .y
+        };
+        assert_eq!(div(&layout), div(&plain) + 40 - element_height("p"));
+    }
+
+    #[test]
+    fn text_commands_carry_their_text_node_id() {
+        let output = render_document("<p>Copy</p>", 640, 360);
+
+        let node_id = output
+            .display_list
+            .commands
+            .iter()
+            .find_map(|cmd| match cmd {
+                DisplayCommand::DrawText { text, node_id, .. } if text == "Copy" => *node_id,
+                _ => None,
+            })
+            .expect("text command with node id");
+        assert!(matches!(
+            &output.document.nodes[node_id].kind,
+            NodeKind::Text(text) if text == "Copy"
+        ));
+    }
+
+    #[test]
+    fn element_backgrounds_are_tagged_with_node_ids() {
+        let input = "<body><p>Copy</p><table><tr><td>Cell</td></tr></table></body>";
+        let output = render_document(input, 640, 360);
+
+        assert!(matches!(
+            output.display_list.commands.first(),
+            Some(DisplayCommand::FillRect { node_id: None, .. })
+        ));
+
+        for layout_box in &output.layout.boxes {
+            let NodeKind::Element(el) = &output.document.nodes[layout_box.node_id].kind else {
+                continue;
+            };
+            let tagged = Some(layout_box.node_id);
+            let mut backgrounds = 0;
+            let mut borders = 0;
+            for cmd in &output.display_list.commands {
+                match cmd {
+                    DisplayCommand::FillRect { node_id, .. } if *node_id == tagged => {
+                        backgrounds += 1
+                    }
+                    DisplayCommand::StrokeRect { node_id, .. } if *node_id == tagged => {
+                        borders += 1
+                    }
+                    _ => {}
+                }
+            }
+            assert_eq!(backgrounds, 1, "missing background for <{}>", el.tag_name);
+            let bordered = el.tag_name == "table" || el.tag_name == "td";
+            assert_eq!(borders, usize::from(bordered));
+        }
+    }
+
+    #[test]
+    fn display_list_paints_parents_before_children() {
+        let input = "<div><section><p>Inner</p></section><p>Sibling</p></div>";
+        let output = render_document(input, 640, 360);
+        let list = &output.display_list;
+        assert!(list.is_well_ordered(&output.document));
+
+        let fill_index = |tag: &str, nth: usize| {
+            list.commands
+                .iter()
+                .enumerate()
+                .filter(|(_, command)| matches!(command, DisplayCommand::FillRect { .. }))
+                .filter(|(_, command)| {
+                    command
+                        .node_id()
+                        .is_some_and(|id| match &output.document.nodes[id].kind {
+                            NodeKind::Element(el) => el.tag_name == tag,
+                            NodeKind::Text(_) => false,
+                        })
+                })
+                .map(|(index, _)| index)
+                .nth(nth)
+                .unwrap_or_else(|| panic!("no FillRect #{nth} for <{tag}>"))
+        };
+        let div = fill_index("div", 0);
+        let section = fill_index("section", 0);
+        let inner = fill_index("p", 0);
+        let sibling = fill_index("p", 1);
+        assert!(div < section && section < inner && inner < sibling);
+
+        let mut child_first = list.clone();
+        child_first.commands.swap(div, section);
+        assert!(!child_first.is_well_ordered(&output.document));
+
+        let mut late_background = list.clone();
+        let background = late_background.commands.remove(0);
+        late_background.commands.push(background);
+        assert!(!late_background.is_well_ordered(&output.document));
+    }
+
+    #[test]
+    fn element_ids_appear_in_labels_when_enabled() {
+        let document = parse_document(&tokenize(
+            "<section id=\"hero\"><p>Intro</p></section><div id=\" \"></div>",
+        ));
+        let layout = layout_document(&document, 640, 360);
+        let labels = |options: &DisplayListOptions| -> Vec<String> {
+            build_display_list_with_options(&document, &layout, 640, 360, options)
+                .commands
+                .into_iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::DrawText { text, .. } => Some(text),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let with_ids = labels(&DisplayListOptions { element_ids: true });
+        assert_eq!(with_ids, ["<section#hero>", "<p>", "Intro", "<div>"]);
+        let plain = labels(&DisplayListOptions::default());
+        assert!(plain.iter().all(|label| !label.contains('#')), "{plain:?}");
+        assert_eq!(
+            build_display_list(&document, &layout, 640, 360).commands,
+            build_display_list_with_options(
+                &document,
+                &layout,
+                640,
+                360,
+                &DisplayListOptions::default()
+            )
+            .commands
+        );
+    }
+
+    #[test]
+    fn tokenize_stats_match_produced_tokens() {
+        let input = "<!DOCTYPE html><!-- header --><p>Hi<br>there</p><!-- footer -->\
+                     <script>run()</script><!-- unterminated";
+        let (tokens, stats) = tokenize_with_stats(input);
+        assert_eq!(tokens, tokenize(input));
+
+        let tags = tokens
+            .iter()
+            .filter(|token| !matches!(token, Token::Text(_)))
+            .count();
+        assert_eq!(stats.tag_count, tags);
+        assert_eq!(stats.text_count, tokens.len() - tags);
+        assert_eq!(
+            stats,
+            TokenizeStats {
+                tag_count: 6,
+                text_count: 3,
+                comment_count: 3,
+                bytes_scanned: input.len(),
+            }
+        );
+        assert_eq!(tokenize_with_stats("").1, TokenizeStats::default());
+    }
+
     #[test]
     fn display_list_includes_text_commands() {
         let input = "<html><body><h1>Hello</h1><p>Visible text</p></body></html>";
@@ -557,4 +3078,19 @@ mod tests {
         assert_eq!(output.scripts.len(), 1);
         assert_eq!(output.scripts[0].code, "window.answer = 42;");
     }
+
+    #[test]
+    fn trimmed_scripts_record_leading_offset() {
+        let input = "<body><script>\n      let x = 1;\n    </script></body>";
+        let document = parse_document(&tokenize(input));
+
+        let scripts = collect_scripts_with_options(&document, &ScriptOptions { trim: true });
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].code, "let x = 1;");
+        assert_eq!(scripts[0].leading_offset, "\n      ".len());
+
+        let untrimmed = collect_scripts_with_options(&document, &ScriptOptions::default());
+        assert_eq!(untrimmed[0].code, "\n      let x = 1;\n    ");
+        assert_eq!(untrimmed[0].leading_offset, 0);
+    }
 }
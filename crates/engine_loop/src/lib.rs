@@ -1,22 +1,28 @@
 use std::time::Duration;
 
+const MAX_TIME_SCALE: f32 = 16.0;
+
 #[derive(Debug, Clone, Copy)]
 pub struct FrameTiming {
     pub frame_index: u64,
     pub dt_seconds: f32,
     pub fps: f32,
     pub fixed_updates: u32,
+    pub total_seconds: f32,
 }
 
 #[derive(Debug)]
 pub struct Scheduler {
     fixed_step: Duration,
     max_updates_per_frame: u32,
+    variable: bool,
+    time_scale: f32,
     accumulator: Duration,
     frame_index: u64,
     second_accumulator: Duration,
     frames_this_second: u32,
     fps: f32,
+    total_elapsed: Duration,
 }
 
 impl Scheduler {
@@ -25,14 +31,41 @@ impl Scheduler {
         Self {
             fixed_step: Duration::from_secs_f64(1.0 / f64::from(tick_hz)),
             max_updates_per_frame: 8,
+            variable: false,
+            time_scale: 1.0,
             accumulator: Duration::ZERO,
             frame_index: 0,
             second_accumulator: Duration::ZERO,
             frames_this_second: 0,
             fps: 0.0,
+            total_elapsed: Duration::ZERO,
+        }
+    }
+
+    // Reports every frame as a single update of the raw dt instead of fixed steps.
+    pub fn variable() -> Self {
+        Self {
+            variable: true,
+            ..Self::new(60)
         }
     }
 
+    pub fn is_variable(&self) -> bool {
+        self.variable
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = if time_scale.is_finite() {
+            time_scale.clamp(0.0, MAX_TIME_SCALE)
+        } else {
+            1.0
+        };
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
     pub fn with_max_updates_per_frame(mut self, max_updates_per_frame: u32) -> Self {
         self.max_updates_per_frame = max_updates_per_frame.max(1);
         self
@@ -42,6 +75,15 @@ impl Scheduler {
         self.fixed_step
     }
 
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.frame_index = 0;
+        self.second_accumulator = Duration::ZERO;
+        self.frames_this_second = 0;
+        self.fps = 0.0;
+        self.total_elapsed = Duration::ZERO;
+    }
+
     pub fn advance_with_fixed_updates<F>(
         &mut self,
         dt: Duration,
@@ -50,18 +92,29 @@ impl Scheduler {
     where
         F: FnMut(Duration),
     {
-        self.accumulator = self.accumulator.saturating_add(dt);
+        // Simulation time is scaled; FPS below keeps counting real frames and wall time.
+        // Same rounding as Duration::mul_f32, but a huge dt saturates instead of panicking.
+        let scaled_dt = Duration::try_from_secs_f32(dt.as_secs_f32() * self.time_scale)
+            .unwrap_or(Duration::MAX);
 
         let mut updates = 0;
-        while self.accumulator >= self.fixed_step && updates < self.max_updates_per_frame {
-            self.accumulator -= self.fixed_step;
-            fixed_update(self.fixed_step);
-            updates += 1;
+        if self.variable {
+            fixed_update(scaled_dt);
+            updates = 1;
+        } else {
+            self.accumulator = self.accumulator.saturating_add(scaled_dt);
+            while self.accumulator >= self.fixed_step && updates < self.max_updates_per_frame {
+                self.accumulator -= self.fixed_step;
+                fixed_update(self.fixed_step);
+                updates += 1;
+            }
         }
 
         self.frame_index = self.frame_index.wrapping_add(1);
         self.frames_this_second = self.frames_this_second.saturating_add(1);
         self.second_accumulator = self.second_accumulator.saturating_add(dt);
+        // Accumulated as a Duration so long sessions do not drift from f32 rounding.
+        self.total_elapsed = self.total_elapsed.saturating_add(dt);
 
         if self.second_accumulator >= Duration::from_secs(1) {
             let secs = self.second_accumulator.as_secs_f32();
@@ -74,9 +127,10 @@ impl Scheduler {
 
         FrameTiming {
             frame_index: self.frame_index,
-            dt_seconds: dt.as_secs_f32(),
+            dt_seconds: scaled_dt.as_secs_f32(),
             fps: self.fps,
             fixed_updates: updates,
+            total_seconds: self.total_elapsed.as_secs_f32(),
         }
     }
 
@@ -107,6 +161,7 @@ mod tests {
             dt_seconds: 0.0,
             fps: 0.0,
             fixed_updates: 0,
+            total_seconds: 0.0,
         };
 
         for _ in 0..65 {
@@ -125,4 +180,86 @@ mod tests {
         assert_eq!(timing.fixed_updates, callbacks);
         assert_eq!(callbacks, 2);
     }
+
+    #[test]
+    fn total_seconds_sums_real_durations() {
+        let mut scheduler = Scheduler::new(60);
+        scheduler.set_time_scale(0.5);
+        let steps = [16_u64, 17, 250, 1_000, 33];
+        let mut timing = scheduler.advance(Duration::ZERO);
+        for millis in steps {
+            timing = scheduler.advance(Duration::from_millis(millis));
+        }
+
+        let expected = steps.iter().sum::<u64>() as f32 / 1_000.0;
+        assert!((timing.total_seconds - expected).abs() < 1e-6);
+
+        scheduler.reset();
+        assert_eq!(scheduler.advance(Duration::ZERO).total_seconds, 0.0);
+    }
+
+    #[test]
+    fn reset_restarts_timing_but_keeps_configuration() {
+        let mut scheduler = Scheduler::new(60).with_max_updates_per_frame(3);
+        for _ in 0..70 {
+            scheduler.advance(Duration::from_millis(23));
+        }
+        assert!(scheduler.fps > 0.0);
+
+        scheduler.reset();
+        assert_eq!(scheduler.frame_index, 0);
+        assert_eq!(scheduler.accumulator, Duration::ZERO);
+        assert_eq!(scheduler.fps, 0.0);
+        assert_eq!(scheduler.max_updates_per_frame, 3);
+        assert_eq!(scheduler.fixed_step(), Scheduler::new(60).fixed_step());
+
+        let timing = scheduler.advance(Duration::from_millis(1));
+        assert_eq!(timing.frame_index, 1);
+    }
+
+    #[test]
+    fn variable_mode_reports_raw_dt() {
+        let mut scheduler = Scheduler::variable();
+        let mut seen = Vec::new();
+        let timing =
+            scheduler.advance_with_fixed_updates(Duration::from_millis(5), |dt| seen.push(dt));
+
+        assert_eq!(timing.fixed_updates, 1);
+        assert_eq!(timing.dt_seconds, 0.005);
+        assert_eq!(seen, vec![Duration::from_millis(5)]);
+        assert_eq!(scheduler.accumulator, Duration::ZERO);
+    }
+
+    #[test]
+    fn time_scale_slows_fixed_updates() {
+        let mut normal = Scheduler::new(50);
+        let mut slow = Scheduler::new(50);
+        slow.set_time_scale(0.5);
+
+        let mut normal_updates = 0;
+        let mut slow_updates = 0;
+        for _ in 0..60 {
+            normal_updates += normal.advance(Duration::from_millis(20)).fixed_updates;
+            slow_updates += slow.advance(Duration::from_millis(20)).fixed_updates;
+        }
+
+        assert_eq!(normal_updates, 60);
+        assert_eq!(slow_updates, 30);
+        assert_eq!(slow.fps, normal.fps);
+
+        slow.set_time_scale(-1.0);
+        assert_eq!(slow.time_scale(), 0.0);
+    }
+
+    #[test]
+    fn huge_time_scales_are_clamped_and_never_overflow() {
+        let mut scheduler = Scheduler::variable();
+        scheduler.set_time_scale(1e30);
+        assert_eq!(scheduler.time_scale(), MAX_TIME_SCALE);
+
+        let timing = scheduler.advance(Duration::from_millis(10));
+        assert_eq!(timing.dt_seconds, 0.16);
+        let timing = scheduler.advance(Duration::MAX);
+        assert_eq!(timing.dt_seconds, Duration::MAX.as_secs_f32());
+    }
 }
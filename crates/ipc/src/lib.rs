@@ -1,6 +1,16 @@
 use std::collections::VecDeque;
 
-pub const IPC_SCHEMA_VERSION: u32 = 1;
+pub const IPC_SCHEMA_VERSION: u32 = 2;
+
+// Per-variant versions, written after the tag. A newer version of a variant may only append
+// fields, so decoders read the fields they know and ignore the rest.
+const LOAD_DOCUMENT_VERSION: u8 = 1;
+const TICK_VERSION: u8 = 1;
+const SHUTDOWN_VERSION: u8 = 1;
+const DOCUMENT_READY_VERSION: u8 = 1;
+const LOG_VERSION: u8 = 1;
+const ACK_SHUTDOWN_VERSION: u8 = 1;
+const ERROR_VERSION: u8 = 1;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Viewport {
@@ -22,18 +32,88 @@ pub enum BrowserToContent {
     Shutdown,
 }
 
+impl BrowserToContent {
+    pub fn schema_version(&self) -> u8 {
+        match self {
+            Self::LoadDocument { .. } => LOAD_DOCUMENT_VERSION,
+            Self::Tick { .. } => TICK_VERSION,
+            Self::Shutdown => SHUTDOWN_VERSION,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ContentToBrowser {
-    DocumentReady { request_id: u64, command_count: u32 },
-    Log { level: u8, message: String },
+    DocumentReady {
+        request_id: u64,
+        command_count: u32,
+    },
+    Log {
+        level: u8,
+        message: String,
+    },
     AckShutdown,
+    // A request that failed (parse error, fetch failure); `code` is defined by the sender.
+    Error {
+        request_id: u64,
+        code: u32,
+        message: String,
+    },
+}
+
+impl ContentToBrowser {
+    pub fn schema_version(&self) -> u8 {
+        match self {
+            Self::DocumentReady { .. } => DOCUMENT_READY_VERSION,
+            Self::Log { .. } => LOG_VERSION,
+            Self::AckShutdown => ACK_SHUTDOWN_VERSION,
+            Self::Error { .. } => ERROR_VERSION,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CodecError {
-    UnexpectedEof,
-    InvalidTag(u8),
-    InvalidUtf8,
+    UnexpectedEof { offset: usize },
+    InvalidTag { tag: u8, offset: usize },
+    InvalidUtf8 { offset: usize },
+    UnsupportedVersion { tag: u8, version: u8, offset: usize },
+}
+
+impl CodecError {
+    // Byte offset into the decoded payload where the problem was found.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Self::UnexpectedEof { offset }
+            | Self::InvalidTag { offset, .. }
+            | Self::InvalidUtf8 { offset }
+            | Self::UnsupportedVersion { offset, .. } => offset,
+        }
+    }
+
+    fn shifted(self, base: usize) -> Self {
+        match self {
+            Self::UnexpectedEof { offset } => Self::UnexpectedEof {
+                offset: base + offset,
+            },
+            Self::InvalidTag { tag, offset } => Self::InvalidTag {
+                tag,
+                offset: base + offset,
+            },
+            Self::InvalidUtf8 { offset } => Self::InvalidUtf8 {
+                offset: base + offset,
+            },
+            Self::UnsupportedVersion {
+                tag,
+                version,
+                offset,
+            } => Self::UnsupportedVersion {
+                tag,
+                version,
+                offset: base + offset,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -78,6 +158,7 @@ pub fn encode_browser_to_content(message: &BrowserToContent) -> Vec<u8> {
             viewport,
         } => {
             write_u8(&mut out, 1);
+            write_u8(&mut out, LOAD_DOCUMENT_VERSION);
             write_u64(&mut out, *request_id);
             write_string(&mut out, url);
             write_string(&mut out, html);
@@ -86,10 +167,12 @@ pub fn encode_browser_to_content(message: &BrowserToContent) -> Vec<u8> {
         }
         BrowserToContent::Tick { frame_index } => {
             write_u8(&mut out, 2);
+            write_u8(&mut out, TICK_VERSION);
             write_u64(&mut out, *frame_index);
         }
         BrowserToContent::Shutdown => {
             write_u8(&mut out, 3);
+            write_u8(&mut out, SHUTDOWN_VERSION);
         }
     }
 
@@ -100,6 +183,9 @@ pub fn decode_browser_to_content(bytes: &[u8]) -> Result<BrowserToContent, Codec
     let mut cursor = Cursor::new(bytes);
     let _version = cursor.read_u32()?;
     let tag = cursor.read_u8()?;
+    // Every variant is still at its first version, so the version is only validated; fields added
+    // later get defaults when it is older than the one that introduced them.
+    let _version = cursor.read_variant_version(tag)?;
 
     match tag {
         1 => {
@@ -120,10 +206,40 @@ pub fn decode_browser_to_content(bytes: &[u8]) -> Result<BrowserToContent, Codec
             Ok(BrowserToContent::Tick { frame_index })
         }
         3 => Ok(BrowserToContent::Shutdown),
-        _ => Err(CodecError::InvalidTag(tag)),
+        _ => Err(CodecError::InvalidTag { tag, offset: 4 }),
     }
 }
 
+// Layout: u32 count, then `count` entries of u32 length + one encoded message.
+pub fn encode_batch(messages: &[BrowserToContent]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_u32(&mut out, messages.len() as u32);
+    for message in messages {
+        let encoded = encode_browser_to_content(message);
+        write_u32(&mut out, encoded.len() as u32);
+        out.extend_from_slice(&encoded);
+    }
+    out
+}
+
+pub fn decode_batch(bytes: &[u8]) -> Result<Vec<BrowserToContent>, CodecError> {
+    let mut cursor = Cursor::new(bytes);
+    let count = cursor.read_u32()?;
+    let mut messages = Vec::new();
+    for _ in 0..count {
+        let len = cursor.read_u32()? as usize;
+        let base = cursor.offset;
+        let message =
+            decode_browser_to_content(cursor.read_exact(len)?).map_err(|err| err.shifted(base))?;
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+pub fn peek_browser_to_content_tag(bytes: &[u8]) -> Result<u8, CodecError> {
+    peek_tag(bytes)
+}
+
 pub fn encode_content_to_browser(message: &ContentToBrowser) -> Vec<u8> {
     let mut out = Vec::new();
     write_u32(&mut out, IPC_SCHEMA_VERSION);
@@ -134,16 +250,30 @@ pub fn encode_content_to_browser(message: &ContentToBrowser) -> Vec<u8> {
             command_count,
         } => {
             write_u8(&mut out, 1);
+            write_u8(&mut out, DOCUMENT_READY_VERSION);
             write_u64(&mut out, *request_id);
             write_u32(&mut out, *command_count);
         }
         ContentToBrowser::Log { level, message } => {
             write_u8(&mut out, 2);
+            write_u8(&mut out, LOG_VERSION);
             write_u8(&mut out, *level);
             write_string(&mut out, message);
         }
         ContentToBrowser::AckShutdown => {
             write_u8(&mut out, 3);
+            write_u8(&mut out, ACK_SHUTDOWN_VERSION);
+        }
+        ContentToBrowser::Error {
+            request_id,
+            code,
+            message,
+        } => {
+            write_u8(&mut out, 4);
+            write_u8(&mut out, ERROR_VERSION);
+            write_u64(&mut out, *request_id);
+            write_u32(&mut out, *code);
+            write_string(&mut out, message);
         }
     }
 
@@ -154,6 +284,7 @@ pub fn decode_content_to_browser(bytes: &[u8]) -> Result<ContentToBrowser, Codec
     let mut cursor = Cursor::new(bytes);
     let _version = cursor.read_u32()?;
     let tag = cursor.read_u8()?;
+    let _version = cursor.read_variant_version(tag)?;
 
     match tag {
         1 => {
@@ -170,10 +301,31 @@ pub fn decode_content_to_browser(bytes: &[u8]) -> Result<ContentToBrowser, Codec
             Ok(ContentToBrowser::Log { level, message })
         }
         3 => Ok(ContentToBrowser::AckShutdown),
-        _ => Err(CodecError::InvalidTag(tag)),
+        4 => {
+            let request_id = cursor.read_u64()?;
+            let code = cursor.read_u32()?;
+            let message = cursor.read_string()?;
+            Ok(ContentToBrowser::Error {
+                request_id,
+                code,
+                message,
+            })
+        }
+        _ => Err(CodecError::InvalidTag { tag, offset: 4 }),
     }
 }
 
+pub fn peek_content_to_browser_tag(bytes: &[u8]) -> Result<u8, CodecError> {
+    peek_tag(bytes)
+}
+
+// Reads only the schema header and variant tag, leaving the payload untouched.
+fn peek_tag(bytes: &[u8]) -> Result<u8, CodecError> {
+    let mut cursor = Cursor::new(bytes);
+    let _version = cursor.read_u32()?;
+    cursor.read_u8()
+}
+
 struct Cursor<'a> {
     bytes: &'a [u8],
     offset: usize,
@@ -185,12 +337,16 @@ impl<'a> Cursor<'a> {
     }
 
     fn read_exact(&mut self, len: usize) -> Result<&'a [u8], CodecError> {
-        if self.offset + len > self.bytes.len() {
-            return Err(CodecError::UnexpectedEof);
-        }
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(CodecError::UnexpectedEof {
+                offset: self.offset,
+            })?;
         let start = self.offset;
-        self.offset += len;
-        Ok(&self.bytes[start..self.offset])
+        self.offset = end;
+        Ok(&self.bytes[start..end])
     }
 
     fn read_u8(&mut self) -> Result<u8, CodecError> {
@@ -209,10 +365,24 @@ impl<'a> Cursor<'a> {
         Ok(u64::from_le_bytes(buf))
     }
 
+    // Version 0 was never written; anything newer decodes the fields this build knows.
+    fn read_variant_version(&mut self, tag: u8) -> Result<u8, CodecError> {
+        let offset = self.offset;
+        match self.read_u8()? {
+            0 => Err(CodecError::UnsupportedVersion {
+                tag,
+                version: 0,
+                offset,
+            }),
+            version => Ok(version),
+        }
+    }
+
     fn read_string(&mut self) -> Result<String, CodecError> {
         let len = self.read_u32()? as usize;
+        let offset = self.offset;
         let bytes = self.read_exact(len)?;
-        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidUtf8)
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidUtf8 { offset })
     }
 }
 
@@ -266,6 +436,102 @@ mod tests {
         assert_eq!(decoded, message);
     }
 
+    #[test]
+    fn error_roundtrip() {
+        let message = ContentToBrowser::Error {
+            request_id: 17,
+            code: 404,
+            message: "fetch failed: not found".to_string(),
+        };
+
+        let encoded = encode_content_to_browser(&message);
+        assert_eq!(peek_content_to_browser_tag(&encoded), Ok(4));
+        assert_eq!(encoded[5], message.schema_version());
+        assert_eq!(decode_content_to_browser(&encoded), Ok(message));
+    }
+
+    #[test]
+    fn error_with_empty_message_roundtrips() {
+        let message = ContentToBrowser::Error {
+            request_id: 3,
+            code: 0,
+            message: String::new(),
+        };
+
+        let encoded = encode_content_to_browser(&message);
+        // schema(4) + tag(1) + version(1) + request_id(8) + code(4) + message len(4)
+        assert_eq!(encoded.len(), 22);
+        assert_eq!(decode_content_to_browser(&encoded), Ok(message));
+        assert_eq!(
+            decode_content_to_browser(&encoded[..21]),
+            Err(CodecError::UnexpectedEof { offset: 18 })
+        );
+    }
+
+    #[test]
+    fn oversized_string_length_is_eof() {
+        let mut bytes = Vec::new();
+        write_u32(&mut bytes, IPC_SCHEMA_VERSION);
+        write_u8(&mut bytes, 1);
+        write_u8(&mut bytes, LOAD_DOCUMENT_VERSION);
+        write_u64(&mut bytes, 7);
+        write_u32(&mut bytes, u32::MAX);
+        bytes.extend_from_slice(b"short");
+
+        assert_eq!(
+            decode_browser_to_content(&bytes),
+            Err(CodecError::UnexpectedEof { offset: 18 })
+        );
+    }
+
+    #[test]
+    fn decoders_never_panic_on_random_input() {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let seeds = [
+            encode_browser_to_content(&BrowserToContent::LoadDocument {
+                request_id: 1,
+                url: "file:///a.html".to_string(),
+                html: "<p>x</p>".to_string(),
+                viewport: Viewport {
+                    width: 10,
+                    height: 10,
+                },
+            }),
+            encode_content_to_browser(&ContentToBrowser::Log {
+                level: 1,
+                message: "hello".to_string(),
+            }),
+        ];
+
+        for round in 0..20_000 {
+            let mut bytes = if round % 2 == 0 {
+                let len = (next() % 48) as usize;
+                (0..len).map(|_| next() as u8).collect::<Vec<_>>()
+            } else {
+                seeds[round % 4 / 2].clone()
+            };
+            if !bytes.is_empty() {
+                let flips = 1 + next() % 4;
+                for _ in 0..flips {
+                    let index = (next() % bytes.len() as u64) as usize;
+                    bytes[index] = next() as u8;
+                }
+                bytes.truncate((next() % (bytes.len() as u64 + 1)) as usize);
+            }
+
+            let _ = decode_browser_to_content(&bytes);
+            let _ = decode_content_to_browser(&bytes);
+            let _ = decode_batch(&bytes);
+        }
+    }
+
     #[test]
     fn in_process_transport_smoke() {
         let mut transport = InProcessTransport::default();
@@ -277,4 +543,180 @@ mod tests {
 
         assert_eq!(message.unwrap(), BrowserToContent::Tick { frame_index: 3 });
     }
+
+    #[test]
+    fn peeks_tags_without_decoding() {
+        let load = encode_browser_to_content(&BrowserToContent::LoadDocument {
+            request_id: 9,
+            url: "file:///x.html".to_string(),
+            html: "<p>x</p>".to_string(),
+            viewport: Viewport {
+                width: 1,
+                height: 1,
+            },
+        });
+        assert_eq!(peek_browser_to_content_tag(&load), Ok(1));
+        assert_eq!(
+            peek_browser_to_content_tag(&load[..4]),
+            Err(CodecError::UnexpectedEof { offset: 4 })
+        );
+
+        let ack = encode_content_to_browser(&ContentToBrowser::AckShutdown);
+        assert_eq!(peek_content_to_browser_tag(&ack), Ok(3));
+        assert_eq!(
+            peek_content_to_browser_tag(&[]),
+            Err(CodecError::UnexpectedEof { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn batch_roundtrip() {
+        let messages = vec![
+            BrowserToContent::LoadDocument {
+                request_id: 3,
+                url: String::new(),
+                html: "<p>batched</p>".to_string(),
+                viewport: Viewport {
+                    width: 320,
+                    height: 240,
+                },
+            },
+            BrowserToContent::Tick { frame_index: 12 },
+            BrowserToContent::Shutdown,
+        ];
+
+        let mut encoded = encode_batch(&messages);
+        // Bytes past the declared count are ignored.
+        encoded.extend_from_slice(&[0xff, 0xff]);
+        assert_eq!(decode_batch(&encoded), Ok(messages));
+
+        let mut short = encode_batch(&[BrowserToContent::Shutdown]);
+        short[0] = 2;
+        assert_eq!(
+            decode_batch(&short),
+            Err(CodecError::UnexpectedEof { offset: 14 })
+        );
+    }
+
+    #[test]
+    fn errors_report_failing_offset() {
+        let encoded = encode_browser_to_content(&BrowserToContent::LoadDocument {
+            request_id: 5,
+            url: "file:///a.html".to_string(),
+            html: "<p>a</p>".to_string(),
+            viewport: Viewport {
+                width: 2,
+                height: 2,
+            },
+        });
+        // schema(4) + tag(1) + version(1) + request_id(8) + url len(4) + url(14) + html len(4)
+        // + html(8)
+        let width_offset = 4 + 1 + 1 + 8 + 4 + 14 + 4 + 8;
+        let truncated = &encoded[..width_offset + 2];
+        let err = decode_browser_to_content(truncated).unwrap_err();
+        assert_eq!(
+            err,
+            CodecError::UnexpectedEof {
+                offset: width_offset
+            }
+        );
+
+        let mut bad_tag = encoded.clone();
+        bad_tag[4] = 9;
+        assert_eq!(decode_browser_to_content(&bad_tag).unwrap_err().offset(), 4);
+
+        // One past the last variant is rejected rather than falling through to the last arm.
+        let mut past_shutdown = encode_browser_to_content(&BrowserToContent::Shutdown);
+        past_shutdown[4] = 4;
+        assert_eq!(
+            decode_browser_to_content(&past_shutdown),
+            Err(CodecError::InvalidTag { tag: 4, offset: 4 })
+        );
+        let mut past_error = encode_content_to_browser(&ContentToBrowser::AckShutdown);
+        past_error[4] = 5;
+        assert_eq!(
+            decode_content_to_browser(&past_error),
+            Err(CodecError::InvalidTag { tag: 5, offset: 4 })
+        );
+
+        let mut bad_utf8 = encoded;
+        bad_utf8[4 + 1 + 1 + 8 + 4] = 0xff;
+        assert_eq!(
+            decode_browser_to_content(&bad_utf8),
+            Err(CodecError::InvalidUtf8 { offset: 18 })
+        );
+    }
+
+    #[test]
+    fn variant_versions_follow_the_tag() {
+        let messages = [
+            BrowserToContent::LoadDocument {
+                request_id: 1,
+                url: String::new(),
+                html: String::new(),
+                viewport: Viewport {
+                    width: 1,
+                    height: 1,
+                },
+            },
+            BrowserToContent::Tick { frame_index: 2 },
+            BrowserToContent::Shutdown,
+        ];
+        for message in messages {
+            let encoded = encode_browser_to_content(&message);
+            assert_eq!(encoded[5], message.schema_version());
+            assert_eq!(decode_browser_to_content(&encoded), Ok(message));
+        }
+
+        let ready = ContentToBrowser::DocumentReady {
+            request_id: 4,
+            command_count: 12,
+        };
+        let encoded = encode_content_to_browser(&ready);
+        assert_eq!(encoded[5], ready.schema_version());
+        assert_eq!(decode_content_to_browser(&encoded), Ok(ready));
+
+        let mut unversioned = encode_content_to_browser(&ContentToBrowser::AckShutdown);
+        unversioned[5] = 0;
+        assert_eq!(
+            decode_content_to_browser(&unversioned),
+            Err(CodecError::UnsupportedVersion {
+                tag: 3,
+                version: 0,
+                offset: 5
+            })
+        );
+    }
+
+    #[test]
+    fn newer_variant_version_still_decodes_known_fields() {
+        let message = BrowserToContent::LoadDocument {
+            request_id: 8,
+            url: "file:///v2.html".to_string(),
+            html: "<p>v2</p>".to_string(),
+            viewport: Viewport {
+                width: 640,
+                height: 480,
+            },
+        };
+        // A future LoadDocument that appends a device scale factor.
+        let mut newer = encode_browser_to_content(&message);
+        newer[5] = LOAD_DOCUMENT_VERSION + 1;
+        write_u32(&mut newer, 2);
+
+        assert_eq!(decode_browser_to_content(&newer), Ok(message.clone()));
+
+        // Framing keeps the extra field from bleeding into the next batched message.
+        let mut batch = Vec::new();
+        write_u32(&mut batch, 2);
+        write_u32(&mut batch, newer.len() as u32);
+        batch.extend_from_slice(&newer);
+        let tick = encode_browser_to_content(&BrowserToContent::Tick { frame_index: 9 });
+        write_u32(&mut batch, tick.len() as u32);
+        batch.extend_from_slice(&tick);
+        assert_eq!(
+            decode_batch(&batch),
+            Ok(vec![message, BrowserToContent::Tick { frame_index: 9 }])
+        );
+    }
 }
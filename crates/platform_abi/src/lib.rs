@@ -1,6 +1,6 @@
 #![forbid(unsafe_op_in_unsafe_fn)]
 
-pub const PLATFORM_ABI_VERSION: u32 = 2;
+pub const PLATFORM_ABI_VERSION: u32 = 6;
 
 pub const PLATFORM_FALSE: u8 = 0;
 pub const PLATFORM_TRUE: u8 = 1;
@@ -10,17 +10,26 @@ pub const PLATFORM_EVENT_QUIT: u32 = 1;
 pub const PLATFORM_EVENT_KEY_DOWN: u32 = 2;
 pub const PLATFORM_EVENT_KEY_UP: u32 = 3;
 pub const PLATFORM_EVENT_RESIZE: u32 = 4;
+pub const PLATFORM_EVENT_MOUSE_MOVE: u32 = 5;
 
 pub const PLATFORM_KEY_UNKNOWN: u32 = 0;
+pub const PLATFORM_KEY_TAB: u32 = 9;
 pub const PLATFORM_KEY_ESCAPE: u32 = 27;
 pub const PLATFORM_KEY_ENTER: u32 = 13;
 pub const PLATFORM_KEY_SPACE: u32 = 32;
+pub const PLATFORM_KEY_1: u32 = 49;
+pub const PLATFORM_KEY_2: u32 = 50;
+pub const PLATFORM_KEY_3: u32 = 51;
 pub const PLATFORM_KEY_F: u32 = 70;
 pub const PLATFORM_KEY_H: u32 = 72;
+pub const PLATFORM_KEY_I: u32 = 73;
 pub const PLATFORM_KEY_J: u32 = 74;
 pub const PLATFORM_KEY_K: u32 = 75;
 pub const PLATFORM_KEY_S: u32 = 83;
 
+pub const PLATFORM_MODIFIER_NONE: u32 = 0;
+pub const PLATFORM_MODIFIER_SHIFT: u32 = 1;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PlatformConfig {
@@ -49,6 +58,20 @@ pub struct PlatformEvent {
     pub key_code: u32,
     pub width: u32,
     pub height: u32,
+    pub modifiers: u32,
+    // Pointer position for mouse events, in window pixels from the top-left corner.
+    pub x: i32,
+    pub y: i32,
+}
+
+// A region of a presented frame, in frame pixels, that changed since the previous present.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlatformDirtyRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
 }
 
 impl Default for PlatformConfig {
@@ -75,6 +98,23 @@ impl Default for PlatformFrame {
     }
 }
 
+impl PlatformFrame {
+    // Checks the header is self-consistent without dereferencing `pixels_rgba8`.
+    pub fn validate(&self) -> bool {
+        let min_stride = u64::from(self.width) * 4;
+        self.struct_size as usize == core::mem::size_of::<Self>()
+            && !self.pixels_rgba8.is_null()
+            && self.width > 0
+            && self.height > 0
+            && u64::from(self.stride_bytes) >= min_stride
+    }
+
+    // Bytes the platform side may read: `stride_bytes * height`.
+    pub fn byte_len(&self) -> u64 {
+        u64::from(self.stride_bytes) * u64::from(self.height)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -101,9 +141,30 @@ mod tests {
         unsafe { (core::ptr::addr_of!((*base).height) as usize) - (base as usize) }
     }
 
+    fn offset_of_event_modifiers() -> usize {
+        let uninit = MaybeUninit::<PlatformEvent>::uninit();
+        let base = uninit.as_ptr();
+        // SAFETY: We compute field offsets from a dangling base pointer only.
+        unsafe { (core::ptr::addr_of!((*base).modifiers) as usize) - (base as usize) }
+    }
+
+    fn offset_of_event_y() -> usize {
+        let uninit = MaybeUninit::<PlatformEvent>::uninit();
+        let base = uninit.as_ptr();
+        // SAFETY: We compute field offsets from a dangling base pointer only.
+        unsafe { (core::ptr::addr_of!((*base).y) as usize) - (base as usize) }
+    }
+
+    fn offset_of_dirty_rect_height() -> usize {
+        let uninit = MaybeUninit::<PlatformDirtyRect>::uninit();
+        let base = uninit.as_ptr();
+        // SAFETY: We compute field offsets from a dangling base pointer only.
+        unsafe { (core::ptr::addr_of!((*base).height) as usize) - (base as usize) }
+    }
+
     #[test]
     fn abi_constants_match_contract() {
-        assert_eq!(PLATFORM_ABI_VERSION, 2);
+        assert_eq!(PLATFORM_ABI_VERSION, 6);
         assert_eq!(PLATFORM_FALSE, 0);
         assert_eq!(PLATFORM_TRUE, 1);
     }
@@ -130,10 +191,57 @@ mod tests {
         assert_eq!(offset_of_frame_pixels(), 16);
     }
 
+    #[test]
+    fn frame_validate_checks_stride_and_size() {
+        let pixels = [0_u8; 16];
+        let frame = PlatformFrame {
+            width: 2,
+            height: 2,
+            stride_bytes: 8,
+            pixels_rgba8: pixels.as_ptr(),
+            ..PlatformFrame::default()
+        };
+        assert!(frame.validate());
+        assert_eq!(frame.byte_len(), 16);
+
+        let padded = PlatformFrame {
+            stride_bytes: 12,
+            ..frame
+        };
+        assert!(padded.validate());
+        assert_eq!(padded.byte_len(), 24);
+
+        assert!(!PlatformFrame {
+            stride_bytes: 4,
+            ..frame
+        }
+        .validate());
+        assert!(!PlatformFrame { height: 0, ..frame }.validate());
+        assert!(!PlatformFrame {
+            pixels_rgba8: core::ptr::null(),
+            ..frame
+        }
+        .validate());
+        assert!(!PlatformFrame {
+            struct_size: 0,
+            ..frame
+        }
+        .validate());
+    }
+
     #[test]
     fn platform_event_layout_matches_c_abi() {
-        assert_eq!(size_of::<PlatformEvent>(), 20);
+        assert_eq!(size_of::<PlatformEvent>(), 32);
         assert_eq!(align_of::<PlatformEvent>(), 4);
         assert_eq!(offset_of_event_height(), 16);
+        assert_eq!(offset_of_event_modifiers(), 20);
+        assert_eq!(offset_of_event_y(), 28);
+    }
+
+    #[test]
+    fn platform_dirty_rect_layout_matches_c_abi() {
+        assert_eq!(size_of::<PlatformDirtyRect>(), 16);
+        assert_eq!(align_of::<PlatformDirtyRect>(), 4);
+        assert_eq!(offset_of_dirty_rect_height(), 12);
     }
 }
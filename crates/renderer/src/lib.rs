@@ -1,10 +1,12 @@
 use fontdue::{
-    layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle},
+    layout::{CoordinateSystem, GlyphPosition, Layout, LayoutSettings, TextStyle},
     Font, FontSettings,
 };
 use std::{
+    borrow::Cow,
     collections::{HashMap, HashSet},
     env, fs,
+    ops::Range,
     path::{Path, PathBuf},
 };
 
@@ -16,22 +18,35 @@ pub enum Pattern {
 }
 
 impl Pattern {
-    pub fn parse(input: &str) -> Option<Self> {
-        match input {
-            "gradient" => Some(Self::Gradient),
-            "solid" => Some(Self::Solid),
-            "rects" => Some(Self::Rects),
-            _ => None,
-        }
+    // Picker order; `from_index` and `next` follow it.
+    pub fn all() -> &'static [Pattern] {
+        &[Self::Gradient, Self::Solid, Self::Rects]
     }
 
-    pub fn next(self) -> Self {
+    pub fn label(&self) -> &'static str {
         match self {
-            Self::Gradient => Self::Solid,
-            Self::Solid => Self::Rects,
-            Self::Rects => Self::Gradient,
+            Self::Gradient => "gradient",
+            Self::Solid => "solid",
+            Self::Rects => "rects",
         }
     }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|pattern| pattern.label() == input)
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::all().get(usize::from(index)).copied()
+    }
+
+    pub fn next(self) -> Self {
+        let all = Self::all();
+        let index = all.iter().position(|&pattern| pattern == self).unwrap_or(0);
+        all[(index + 1) % all.len()]
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -43,6 +58,35 @@ pub struct DrawRect {
     pub color: [u8; 4],
 }
 
+impl DrawRect {
+    pub fn outline(&self, thickness: i32) -> [DrawRect; 4] {
+        let thickness = thickness.max(1).min(self.width).min(self.height);
+        let edge = |x, y, width, height| DrawRect {
+            x,
+            y,
+            width,
+            height,
+            color: self.color,
+        };
+        [
+            edge(self.x, self.y, self.width, thickness),
+            edge(
+                self.x,
+                self.y.saturating_add(self.height - thickness),
+                self.width,
+                thickness,
+            ),
+            edge(self.x, self.y, thickness, self.height),
+            edge(
+                self.x.saturating_add(self.width - thickness),
+                self.y,
+                thickness,
+                self.height,
+            ),
+        ]
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DrawText {
     pub x: i32,
@@ -50,12 +94,147 @@ pub struct DrawText {
     pub text: String,
     pub color: [u8; 4],
     pub scale: u32,
+    pub underline: bool,
+    // Labels wider than this many pixels are cut short and end in "...".
+    pub max_width: Option<u32>,
+    // Right-to-left runs end at `x` instead of starting there.
+    pub rtl: bool,
+    // Places the label within this many pixels starting at `x`; overrides `rtl` when set.
+    pub align: Option<(TextAlign, u32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+// Pixel distances for one line of text; `descent` is measured downward from the baseline.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_height: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlayCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct OverlayInfo {
-    pub frame_index: u64,
-    pub fps: f32,
+    pub frame_index: Option<u64>,
+    pub fps: Option<f32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub corner: OverlayCorner,
+}
+
+impl OverlayInfo {
+    pub fn builder() -> OverlayInfoBuilder {
+        OverlayInfoBuilder::default()
+    }
+
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(frame_index) = self.frame_index {
+            parts.push(format!("F{frame_index}"));
+        }
+        if let Some(fps) = self.fps {
+            parts.push(format!("P{fps:.1}"));
+        }
+        if let Some(width) = self.width {
+            parts.push(format!("W{width}"));
+        }
+        if let Some(height) = self.height {
+            parts.push(format!("H{height}"));
+        }
+        parts.join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OverlayInfoBuilder {
+    info: OverlayInfo,
+}
+
+impl OverlayInfoBuilder {
+    pub fn frame_index(mut self, frame_index: u64) -> Self {
+        self.info.frame_index = Some(frame_index);
+        self
+    }
+
+    pub fn fps(mut self, fps: f32) -> Self {
+        self.info.fps = Some(fps);
+        self
+    }
+
+    pub fn width(mut self, width: u32) -> Self {
+        self.info.width = Some(width);
+        self
+    }
+
+    pub fn height(mut self, height: u32) -> Self {
+        self.info.height = Some(height);
+        self
+    }
+
+    pub fn corner(mut self, corner: OverlayCorner) -> Self {
+        self.info.corner = corner;
+        self
+    }
+
+    pub fn build(self) -> OverlayInfo {
+        self.info
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    pub fn from_degrees(degrees: u32) -> Option<Self> {
+        match degrees {
+            0 => Some(Self::Deg0),
+            90 => Some(Self::Deg90),
+            180 => Some(Self::Deg180),
+            270 => Some(Self::Deg270),
+            _ => None,
+        }
+    }
+
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Self::Deg90 | Self::Deg270)
+    }
+}
+
+// Auto draws with the selected font; Bitmap always uses the builtin 5x7 glyphs and
+// AntiAliased prefers a loaded system font even when the bitmap font is selected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextMode {
+    #[default]
+    Auto,
+    Bitmap,
+    AntiAliased,
+}
+
+// A region of the presented frame, in presented pixels, that changed since the frame before it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: u32,
+    pub y: u32,
     pub width: u32,
     pub height: u32,
 }
@@ -64,10 +243,24 @@ pub struct Renderer {
     width: u32,
     height: u32,
     pixels: Vec<u8>,
+    rotation: Rotation,
+    rotated: Vec<u8>,
+    previous_frame: Vec<u8>,
+    dirty_rects: Vec<DirtyRect>,
+    invert: bool,
+    grayscale: bool,
+    clear_color: Option<[u8; 4]>,
+    gradient_stops: Vec<(f32, [u8; 4])>,
     pattern: Pattern,
     fonts: Vec<FontChoice>,
     font_index: usize,
     loaded_fonts: HashMap<usize, Font>,
+    text_mode: TextMode,
+    text_font: Option<usize>,
+    text_scale: f32,
+    glyph_atlas: GlyphAtlas,
+    missing_glyph: Option<char>,
+    spotlight: Option<Spotlight>,
 }
 
 #[derive(Debug, Clone)]
@@ -76,23 +269,117 @@ struct FontChoice {
     path: Option<PathBuf>,
 }
 
-impl Renderer {
-    pub fn new(width: u32, height: u32) -> Self {
-        let fonts = discover_fonts();
+// Options applied before the font catalog is scanned and the default font is loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RendererBuilder {
+    width: u32,
+    height: u32,
+    pattern: Pattern,
+    clear_color: Option<[u8; 4]>,
+    rotation: Rotation,
+    grayscale: bool,
+    text_mode: TextMode,
+    font_roots: Option<Vec<PathBuf>>,
+}
+
+impl Default for RendererBuilder {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            pattern: Pattern::Gradient,
+            clear_color: None,
+            rotation: Rotation::Deg0,
+            grayscale: false,
+            text_mode: TextMode::Auto,
+            font_roots: None,
+        }
+    }
+}
+
+impl RendererBuilder {
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    pub fn clear_color(mut self, color: Option<[u8; 4]>) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn grayscale(mut self, grayscale: bool) -> Self {
+        self.grayscale = grayscale;
+        self
+    }
+
+    pub fn text_mode(mut self, mode: TextMode) -> Self {
+        self.text_mode = mode;
+        self
+    }
+
+    // Directories scanned for font files instead of the platform's system font folders.
+    pub fn font_roots<P: Into<PathBuf>>(mut self, roots: impl IntoIterator<Item = P>) -> Self {
+        self.font_roots = Some(roots.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn build(self) -> Renderer {
+        let roots = self.font_roots.unwrap_or_else(default_font_roots);
+        let fonts = discover_fonts(&roots);
         let font_index = default_font_index(&fonts);
-        let mut renderer = Self {
+        let mut renderer = Renderer {
             width: 0,
             height: 0,
             pixels: Vec::new(),
-            pattern: Pattern::Gradient,
+            rotation: self.rotation,
+            rotated: Vec::new(),
+            previous_frame: Vec::new(),
+            dirty_rects: Vec::new(),
+            invert: false,
+            spotlight: None,
+            grayscale: self.grayscale,
+            clear_color: self.clear_color,
+            gradient_stops: Vec::new(),
+            pattern: self.pattern,
             fonts,
             font_index,
             loaded_fonts: HashMap::new(),
+            text_mode: self.text_mode,
+            text_font: None,
+            text_scale: 1.0,
+            glyph_atlas: GlyphAtlas::default(),
+            missing_glyph: None,
         };
-        renderer.ensure_font_loaded(renderer.font_index);
-        renderer.resize(width, height);
+        renderer.resolve_text_font();
+        renderer.resize(self.width, self.height);
         renderer
     }
+}
+
+impl Renderer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::builder().width(width).height(height).build()
+    }
+
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder::default()
+    }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         if self.width == width && self.height == height {
@@ -104,16 +391,192 @@ impl Renderer {
         if self.pixels.len() != new_len {
             self.pixels.resize(new_len, 0);
         }
+        self.rotated.clear();
+    }
+
+    // The last presented frame, after post-processing and rotation. With a rotation set it is
+    // empty between a resize or rotation change and the next render, never the stale frame.
+    pub fn framebuffer(&self) -> &[u8] {
+        if self.rotation == Rotation::Deg0 {
+            &self.pixels
+        } else {
+            &self.rotated
+        }
+    }
+
+    // Full-width bands of the last frame that differ from the frame rendered before it; the
+    // whole frame after a resize, and empty when nothing changed.
+    pub fn last_dirty_rects(&self) -> &[DirtyRect] {
+        &self.dirty_rects
+    }
+
+    // Copies the last rendered frame, at `presented_size`, into a caller-owned RGBA buffer.
+    pub fn present_to(&self, buffer: &mut [u8]) -> Result<(), String> {
+        let (width, height) = self.presented_size();
+        let expected = pixel_len(width, height);
+        if buffer.len() != expected {
+            return Err(format!(
+                "present_to needs {expected} bytes for a {width}x{height} frame, got {}",
+                buffer.len()
+            ));
+        }
+        let frame = self.framebuffer();
+        if frame.len() == expected {
+            buffer.copy_from_slice(frame);
+        } else {
+            // Nothing rendered since the last resize yet.
+            buffer.fill(0);
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self, color: [u8; 4]) {
+        let [r, g, b, a] = color;
+        clear_rgba(&mut self.pixels, r, g, b, a);
+        if self.rotation != Rotation::Deg0 {
+            self.rotated.resize(self.pixels.len(), 0);
+            clear_rgba(&mut self.rotated, r, g, b, a);
+        }
+    }
+
+    // Draws a text caret onto the last rendered frame, in presented coordinates: a vertical bar
+    // `height` pixels tall, 2px wide from 16px up and 1px below that. Pair with `caret_visible`
+    // to blink it.
+    pub fn draw_caret(&mut self, x: i32, y: i32, height: u32, color: [u8; 4]) {
+        let (width, frame_height) = self.presented_size();
+        let bar_width = if height >= 16 { 2 } else { 1 };
+        let bar_height = i32::try_from(height).unwrap_or(i32::MAX);
+        let frame = if self.rotation == Rotation::Deg0 {
+            &mut self.pixels
+        } else {
+            &mut self.rotated
+        };
+        if frame.len() != pixel_len(width, frame_height) {
+            return;
+        }
+        fill_rect(
+            frame,
+            width,
+            frame_height,
+            x,
+            y,
+            bar_width,
+            bar_height,
+            color,
+        );
+
+        // Keep partial presents in step: the caret rows are dirty now, and the next frame is
+        // compared against the frame with the caret on it.
+        if self.previous_frame.len() == frame.len() {
+            fill_rect(
+                &mut self.previous_frame,
+                width,
+                frame_height,
+                x,
+                y,
+                bar_width,
+                bar_height,
+                color,
+            );
+        }
+        let top = y.clamp(0, frame_height as i32) as u32;
+        let bottom = y.saturating_add(bar_height).clamp(0, frame_height as i32) as u32;
+        if top < bottom && x < width as i32 && x.saturating_add(bar_width) > 0 {
+            self.dirty_rects.push(DirtyRect {
+                x: 0,
+                y: top,
+                width,
+                height: bottom - top,
+            });
+        }
     }
 
     pub fn set_pattern(&mut self, pattern: Pattern) {
         self.pattern = pattern;
     }
 
+    // Stops are (position in 0..=1, color) pairs; an empty list restores the default ramp.
+    pub fn set_gradient_stops(&mut self, mut stops: Vec<(f32, [u8; 4])>) {
+        for stop in &mut stops {
+            stop.0 = if stop.0.is_finite() {
+                stop.0.clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.gradient_stops = stops;
+    }
+
     pub fn pattern(&self) -> Pattern {
         self.pattern
     }
 
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        if rotation != self.rotation {
+            self.rotated.clear();
+        }
+        self.rotation = rotation;
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    pub fn set_invert(&mut self, invert: bool) {
+        self.invert = invert;
+    }
+
+    pub fn is_inverted(&self) -> bool {
+        self.invert
+    }
+
+    // Dims everything outside `spotlight` as the last pass of each frame, in presented pixels.
+    pub fn set_spotlight(&mut self, spotlight: Option<Spotlight>) {
+        self.spotlight = spotlight;
+    }
+
+    pub fn spotlight(&self) -> Option<Spotlight> {
+        self.spotlight
+    }
+
+    pub fn set_grayscale(&mut self, grayscale: bool) {
+        self.grayscale = grayscale;
+    }
+
+    pub fn is_grayscale(&self) -> bool {
+        self.grayscale
+    }
+
+    pub fn set_clear_color(&mut self, color: Option<[u8; 4]>) {
+        self.clear_color = color;
+    }
+
+    pub fn clear_color(&self) -> Option<[u8; 4]> {
+        self.clear_color
+    }
+
+    // Only characters the bitmap font can draw are accepted as placeholders.
+    pub fn set_missing_glyph(&mut self, placeholder: char) -> bool {
+        if !has_builtin_glyph(placeholder) {
+            return false;
+        }
+        self.missing_glyph = Some(placeholder);
+        true
+    }
+
+    pub fn missing_glyph(&self) -> Option<char> {
+        self.missing_glyph
+    }
+
+    pub fn presented_size(&self) -> (u32, u32) {
+        if self.rotation.swaps_axes() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
     pub fn render(&mut self, frame_index: u64, time_seconds: f32) -> &[u8] {
         self.render_pattern(frame_index, time_seconds, None)
     }
@@ -125,6 +588,13 @@ impl Renderer {
         overlay: Option<OverlayInfo>,
     ) -> &[u8] {
         match self.pattern {
+            Pattern::Gradient if !self.gradient_stops.is_empty() => render_stop_gradient(
+                &mut self.pixels,
+                self.width,
+                self.height,
+                frame_index,
+                &self.gradient_stops,
+            ),
             Pattern::Gradient => {
                 render_gradient(&mut self.pixels, self.width, self.height, frame_index)
             }
@@ -139,7 +609,7 @@ impl Renderer {
             draw_overlay(&mut self.pixels, self.width, self.height, overlay);
         }
 
-        &self.pixels
+        self.finish_frame()
     }
 
     pub fn render_display_list(
@@ -150,17 +620,21 @@ impl Renderer {
         texts: &[DrawText],
         overlay: Option<OverlayInfo>,
     ) -> &[u8] {
-        let bg_pulse = pulse_u8(frame_index, time_seconds) >> 4;
-        clear_rgba(
-            &mut self.pixels,
-            20_u8.saturating_add(bg_pulse),
-            20_u8.saturating_add(bg_pulse),
-            24_u8.saturating_add(bg_pulse),
-            255,
-        );
+        if let Some([r, g, b, a]) = self.clear_color {
+            clear_rgba(&mut self.pixels, r, g, b, a);
+        } else {
+            let bg_pulse = pulse_u8(frame_index, time_seconds) >> 4;
+            clear_rgba(
+                &mut self.pixels,
+                20_u8.saturating_add(bg_pulse),
+                20_u8.saturating_add(bg_pulse),
+                24_u8.saturating_add(bg_pulse),
+                255,
+            );
+        }
 
         for rect in rects {
-            fill_rect(
+            blend_rect(
                 &mut self.pixels,
                 self.width,
                 self.height,
@@ -172,44 +646,86 @@ impl Renderer {
             );
         }
 
-        let use_system_font = self.ensure_font_loaded(self.font_index);
         for text in texts {
-            if use_system_font {
-                if let Some(font) = self.loaded_fonts.get(&self.font_index) {
-                    let px = text_px(text.scale);
-                    draw_text_fontdue(
-                        &mut self.pixels,
-                        self.width,
-                        self.height,
-                        text.x,
-                        text.y,
-                        &text.text,
-                        text.color,
-                        font,
-                        px,
-                    );
-                } else {
-                    draw_text_scaled(
-                        &mut self.pixels,
-                        self.width,
-                        self.height,
-                        text.x,
-                        text.y,
-                        &text.text,
-                        text.color,
-                        text.scale.max(1),
-                    );
-                }
+            let text_label = match text.max_width {
+                Some(max_width) => elide_to_width(&text.text, max_width, |candidate| {
+                    self.text_advance(candidate, text.scale)
+                }),
+                None => Cow::Borrowed(text.text.as_str()),
+            };
+            let advance = || self.text_advance(&text_label, text.scale).round() as i32;
+            // Lines wider than the alignment box start at its left edge rather than overhang it.
+            let slack = |width: u32| (i32::try_from(width).unwrap_or(i32::MAX) - advance()).max(0);
+            let x = match text.align {
+                Some((TextAlign::Left, _)) => text.x,
+                Some((TextAlign::Center, width)) => text.x.saturating_add(slack(width) / 2),
+                Some((TextAlign::Right, width)) => text.x.saturating_add(slack(width)),
+                None if text.rtl => text.x.saturating_sub(advance()),
+                None => text.x,
+            };
+            let font = self
+                .text_font
+                .and_then(|index| Some((index, self.loaded_fonts.get(&index)?)));
+            if let Some((font_index, font)) = font {
+                let px = self.text_font_px(font, text.scale);
+                let label = substitute_missing(&text_label, self.missing_glyph, |ch| {
+                    font.lookup_glyph_index(ch) != 0
+                });
+                draw_text_fontdue(
+                    &mut self.pixels,
+                    self.width,
+                    self.height,
+                    x,
+                    text.y,
+                    &label,
+                    text.color,
+                    (font_index, font),
+                    px,
+                    &mut self.glyph_atlas,
+                );
             } else {
+                let label = substitute_missing(&text_label, self.missing_glyph, has_builtin_glyph);
+                let scale = self.bitmap_scale(text.scale);
                 draw_text_scaled(
                     &mut self.pixels,
                     self.width,
                     self.height,
-                    text.x,
+                    x,
                     text.y,
-                    &text.text,
+                    &label,
+                    text.color,
+                    scale,
+                );
+            }
+
+            if text.underline {
+                let font = self
+                    .text_font
+                    .and_then(|index| self.loaded_fonts.get(&index));
+                let (offset, thickness) = match font {
+                    Some(font) => {
+                        let px = self.text_font_px(font, text.scale);
+                        let ascent = line_ascent(font, px);
+                        (
+                            ascent.round() as i32 + 1,
+                            (px / 14.0).round().max(1.0) as i32,
+                        )
+                    }
+                    None => {
+                        let scale = self.bitmap_scale(text.scale) as i32;
+                        (8 * scale, scale)
+                    }
+                };
+                let underline_width = self.text_advance(&text_label, text.scale).round() as i32;
+                blend_rect(
+                    &mut self.pixels,
+                    self.width,
+                    self.height,
+                    x,
+                    text.y.saturating_add(offset),
+                    underline_width,
+                    thickness,
                     text.color,
-                    text.scale.max(1),
                 );
             }
         }
@@ -218,7 +734,57 @@ impl Renderer {
             draw_overlay(&mut self.pixels, self.width, self.height, overlay);
         }
 
-        &self.pixels
+        self.finish_frame()
+    }
+
+    // A ring of dots whose bright head steps around every few frames, with `message`
+    // centered underneath. Shown while a document is still being fetched.
+    pub fn render_loading(&mut self, frame_index: u64, message: &str) -> &[u8] {
+        let [r, g, b, a] = self.clear_color.unwrap_or([20, 24, 34, 255]);
+        clear_rgba(&mut self.pixels, r, g, b, a);
+
+        let scale = self.bitmap_scale(2);
+        let radius = (self.width.min(self.height) / 10).clamp(6, 24) as i32;
+        let dot = (radius / 3).max(2);
+        let text_height = 7 * scale as i32;
+        let block_height = 2 * radius + dot + LOADING_TEXT_GAP + text_height;
+        let center_x = self.width as i32 / 2;
+        let center_y = (self.height as i32 - block_height) / 2 + radius + dot / 2;
+
+        let head = frame_index / LOADING_FRAMES_PER_STEP % LOADING_DOTS;
+        for index in 0..LOADING_DOTS {
+            let angle = index as f32 / LOADING_DOTS as f32 * std::f32::consts::TAU;
+            let trail = (head + LOADING_DOTS - index) % LOADING_DOTS;
+            let level = (255 - trail * 160 / LOADING_DOTS) as u8;
+            fill_rect(
+                &mut self.pixels,
+                self.width,
+                self.height,
+                center_x + (radius as f32 * angle.sin()).round() as i32 - dot / 2,
+                center_y - (radius as f32 * angle.cos()).round() as i32 - dot / 2,
+                dot,
+                dot,
+                [level, level, level, 255],
+            );
+        }
+
+        let max_text_width = self.width.saturating_sub(16);
+        let label = elide_to_width(message, max_text_width, |candidate| {
+            measure_text(candidate, scale) as f32
+        });
+        let text_x = (self.width as i32 - measure_text(&label, scale) as i32) / 2;
+        draw_text_scaled(
+            &mut self.pixels,
+            self.width,
+            self.height,
+            text_x.max(0),
+            center_y + radius + dot / 2 + LOADING_TEXT_GAP,
+            &label,
+            LOADING_TEXT_COLOR,
+            scale,
+        );
+
+        self.finish_frame()
     }
 
     pub fn width(&self) -> u32 {
@@ -241,6 +807,7 @@ impl Renderer {
                 break;
             }
         }
+        self.resolve_text_font();
         self.current_font_name().to_string()
     }
 
@@ -263,6 +830,68 @@ impl Renderer {
         self.fonts.len()
     }
 
+    // Clamped to TEXT_SCALE_RANGE. Bitmap text rounds the scaled size to whole pixels.
+    pub fn set_text_scale(&mut self, scale: f32) {
+        self.text_scale = if scale.is_finite() {
+            scale.clamp(*TEXT_SCALE_RANGE.start(), *TEXT_SCALE_RANGE.end())
+        } else {
+            1.0
+        };
+    }
+
+    pub fn text_scale(&self) -> f32 {
+        self.text_scale
+    }
+
+    fn bitmap_scale(&self, scale: u32) -> u32 {
+        (scale.max(1) as f32 * self.text_scale).round().max(1.0) as u32
+    }
+
+    fn text_font_px(&self, font: &Font, scale: u32) -> f32 {
+        fitted_text_px(font, scale) * self.text_scale
+    }
+
+    pub fn glyph_atlas_stats(&self) -> GlyphAtlasStats {
+        self.glyph_atlas.stats()
+    }
+
+    pub fn font_metrics(&self, scale: u32) -> FontMetrics {
+        let bitmap_scale = self.bitmap_scale(scale);
+        let bitmap = FontMetrics {
+            ascent: (7 * bitmap_scale) as f32,
+            descent: 0.0,
+            line_height: (8 * bitmap_scale) as f32,
+        };
+        let Some(font) = self.text_font() else {
+            return bitmap;
+        };
+        let px = self.text_font_px(font, scale);
+        font.horizontal_line_metrics(px)
+            .map_or(bitmap, |metrics| FontMetrics {
+                ascent: metrics.ascent,
+                descent: -metrics.descent,
+                line_height: metrics.new_line_size,
+            })
+    }
+
+    pub fn text_advance(&self, text: &str, scale: u32) -> f32 {
+        let Some(font) = self.text_font() else {
+            return measure_text(text, self.bitmap_scale(scale)) as f32;
+        };
+
+        let px = self.text_font_px(font, scale);
+        let mut advance = 0.0;
+        let mut previous = None;
+        for ch in text.chars() {
+            if let Some(left) = previous {
+                advance += font.horizontal_kern(left, ch, px).unwrap_or(0.0);
+            }
+            advance += font.metrics(ch, px).advance_width;
+            previous = Some(ch);
+        }
+        advance
+    }
+
     pub fn set_font_index(&mut self, index: usize) -> bool {
         if index >= self.fonts.len() {
             return false;
@@ -271,31 +900,122 @@ impl Renderer {
             return false;
         }
         self.font_index = index;
+        self.resolve_text_font();
         true
     }
 
-    fn font_is_ready(&mut self, index: usize) -> bool {
-        match self.fonts.get(index) {
-            Some(FontChoice { path: None, .. }) => true,
-            Some(FontChoice { path: Some(_), .. }) => self.ensure_font_loaded(index),
-            None => false,
+    // Adds a specific font file to the catalog, independent of the system font scan.
+    pub fn load_font_file(&mut self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let path = path.as_ref();
+        if let Some(index) = self
+            .fonts
+            .iter()
+            .position(|choice| choice.path.as_deref() == Some(path))
+        {
+            if self.ensure_font_loaded(index) {
+                return Ok(index);
+            }
         }
+
+        let bytes = fs::read(path)
+            .map_err(|err| format!("failed to read font {}: {err}", path.display()))?;
+        let font = Font::from_bytes(bytes, FontSettings::default())
+            .map_err(|err| format!("failed to parse font {}: {err}", path.display()))?;
+        let index = self.fonts.len();
+        self.fonts.push(FontChoice {
+            name: font_display_name(path),
+            path: Some(path.to_path_buf()),
+        });
+        self.loaded_fonts.insert(index, font);
+        Ok(index)
     }
 
-    fn ensure_font_loaded(&mut self, index: usize) -> bool {
-        if self.loaded_fonts.contains_key(&index) {
-            return true;
-        }
-        let Some(choice) = self.fonts.get(index) else {
-            return false;
-        };
-        let Some(path) = &choice.path else {
-            return false;
-        };
+    pub fn set_text_mode(&mut self, mode: TextMode) {
+        self.text_mode = mode;
+        self.resolve_text_font();
+    }
 
-        let Ok(bytes) = fs::read(path) else {
-            return false;
-        };
+    pub fn text_mode(&self) -> TextMode {
+        self.text_mode
+    }
+
+    fn text_font(&self) -> Option<&Font> {
+        self.text_font
+            .and_then(|index| self.loaded_fonts.get(&index))
+    }
+
+    // Loads the font text is drawn with; `None` falls back to the builtin bitmap glyphs.
+    fn resolve_text_font(&mut self) {
+        self.text_font = match self.text_mode {
+            TextMode::Bitmap => None,
+            TextMode::Auto => self
+                .ensure_font_loaded(self.font_index)
+                .then_some(self.font_index),
+            TextMode::AntiAliased => {
+                let candidates = [self.font_index, default_font_index(&self.fonts)]
+                    .into_iter()
+                    .chain(0..self.fonts.len());
+                candidates
+                    .into_iter()
+                    .find(|&index| self.ensure_font_loaded(index))
+            }
+        };
+    }
+
+    fn finish_frame(&mut self) -> &[u8] {
+        if self.grayscale {
+            grayscale_rgb(&mut self.pixels);
+        }
+        if self.invert {
+            invert_rgb(&mut self.pixels);
+        }
+        if self.rotation != Rotation::Deg0 {
+            rotate_rgba(
+                &self.pixels,
+                self.width,
+                self.height,
+                self.rotation,
+                &mut self.rotated,
+            );
+        }
+        let (width, height) = self.presented_size();
+        let frame = if self.rotation == Rotation::Deg0 {
+            &mut self.pixels
+        } else {
+            &mut self.rotated
+        };
+        if let Some(spotlight) = self.spotlight {
+            apply_spotlight(frame, width, height, spotlight);
+        }
+        let frame = &*frame;
+        self.dirty_rects = dirty_row_bands(&self.previous_frame, frame, width, height);
+        self.previous_frame.clear();
+        self.previous_frame.extend_from_slice(frame);
+        frame
+    }
+
+    fn font_is_ready(&mut self, index: usize) -> bool {
+        match self.fonts.get(index) {
+            Some(FontChoice { path: None, .. }) => true,
+            Some(FontChoice { path: Some(_), .. }) => self.ensure_font_loaded(index),
+            None => false,
+        }
+    }
+
+    fn ensure_font_loaded(&mut self, index: usize) -> bool {
+        if self.loaded_fonts.contains_key(&index) {
+            return true;
+        }
+        let Some(choice) = self.fonts.get(index) else {
+            return false;
+        };
+        let Some(path) = &choice.path else {
+            return false;
+        };
+
+        let Ok(bytes) = fs::read(path) else {
+            return false;
+        };
         let Ok(font) = Font::from_bytes(bytes, FontSettings::default()) else {
             return false;
         };
@@ -314,13 +1034,32 @@ fn text_px(scale: u32) -> f32 {
     12.0 + (scale.max(1) as f32 * 2.0)
 }
 
-fn discover_fonts() -> Vec<FontChoice> {
-    let mut fonts = Vec::new();
-    fonts.push(FontChoice {
-        name: "Pixel 5x7".to_string(),
-        path: None,
-    });
+const MIN_FONT_PX: f32 = 10.0;
+
+pub const TEXT_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=4.0;
+
+// Shrinks the font so ascent-to-descent fits the 7 * scale rows a bitmap glyph occupies,
+// which is what engine layout boxes are sized for.
+fn fitted_text_px(font: &Font, scale: u32) -> f32 {
+    let px = text_px(scale);
+    let Some(metrics) = font.horizontal_line_metrics(px) else {
+        return px;
+    };
+    let line_height = metrics.ascent - metrics.descent;
+    let target = (7 * scale.max(1)) as f32;
+    if line_height <= target {
+        px
+    } else {
+        (px * target / line_height).max(MIN_FONT_PX)
+    }
+}
 
+fn line_ascent(font: &Font, px: f32) -> f32 {
+    font.horizontal_line_metrics(px)
+        .map_or(px, |metrics| metrics.ascent)
+}
+
+fn default_font_roots() -> Vec<PathBuf> {
     let mut roots = Vec::new();
     #[cfg(target_os = "macos")]
     {
@@ -339,12 +1078,21 @@ fn discover_fonts() -> Vec<FontChoice> {
         roots.push(PathBuf::from("/usr/share/fonts"));
         roots.push(PathBuf::from("/usr/local/share/fonts"));
         if let Ok(home) = env::var("HOME") {
-            roots.push(PathBuf::from(home).join(".fonts"));
+            roots.push(PathBuf::from(&home).join(".fonts"));
             roots.push(PathBuf::from(home).join(".local/share/fonts"));
         }
     }
+    roots
+}
+
+fn discover_fonts(roots: &[PathBuf]) -> Vec<FontChoice> {
+    let mut fonts = Vec::new();
+    fonts.push(FontChoice {
+        name: "Pixel 5x7".to_string(),
+        path: None,
+    });
 
-    let files = collect_font_files(&roots);
+    let files = collect_font_files(roots);
     let mut used_paths = HashSet::new();
 
     // Curated families first so the popup defaults to sane UI/text fonts.
@@ -503,6 +1251,94 @@ fn clear_rgba(framebuffer: &mut [u8], r: u8, g: u8, b: u8, a: u8) {
     }
 }
 
+fn invert_rgb(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        px[0] = 255 - px[0];
+        px[1] = 255 - px[1];
+        px[2] = 255 - px[2];
+    }
+}
+
+// Rec. 601 luma of an RGB(A) pixel in fixed point: 0.299R + 0.587G + 0.114B.
+pub fn luma(px: &[u8]) -> u8 {
+    ((299 * px[0] as u32 + 587 * px[1] as u32 + 114 * px[2] as u32 + 500) / 1000) as u8
+}
+
+fn grayscale_rgb(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        let luma = luma(px);
+        px[0] = luma;
+        px[1] = luma;
+        px[2] = luma;
+    }
+}
+
+pub const CARET_BLINK_FRAMES: u64 = 30;
+
+// Caret blink phase for `frame_index`: shown for CARET_BLINK_FRAMES frames, then hidden for as
+// many.
+pub fn caret_visible(frame_index: u64) -> bool {
+    (frame_index / CARET_BLINK_FRAMES).is_multiple_of(2)
+}
+
+// Merges consecutive changed rows into full-width rects; a size change dirties everything.
+fn dirty_row_bands(previous: &[u8], current: &[u8], width: u32, height: u32) -> Vec<DirtyRect> {
+    let full = DirtyRect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    let row_bytes = width as usize * 4;
+    if row_bytes == 0 || height == 0 {
+        return Vec::new();
+    }
+    if previous.len() != current.len() {
+        return vec![full];
+    }
+
+    let mut rects: Vec<DirtyRect> = Vec::new();
+    let rows = previous.chunks(row_bytes).zip(current.chunks(row_bytes));
+    for (y, (before, after)) in (0..height).zip(rows) {
+        if before == after {
+            continue;
+        }
+        match rects.last_mut() {
+            Some(rect) if rect.y + rect.height == y => rect.height += 1,
+            _ => rects.push(DirtyRect {
+                y,
+                height: 1,
+                ..full
+            }),
+        }
+    }
+    rects
+}
+
+fn rotate_rgba(src: &[u8], width: u32, height: u32, rotation: Rotation, dst: &mut Vec<u8>) {
+    let w = width as usize;
+    let h = height as usize;
+    dst.resize(src.len(), 0);
+    if src.len() < w * h * 4 {
+        return;
+    }
+
+    let out_w = if rotation.swaps_axes() { h } else { w };
+    for y in 0..h {
+        for x in 0..w {
+            let (dx, dy) = match rotation {
+                Rotation::Deg0 => (x, y),
+                Rotation::Deg90 => (h - 1 - y, x),
+                Rotation::Deg180 => (w - 1 - x, h - 1 - y),
+                Rotation::Deg270 => (y, w - 1 - x),
+            };
+            let s = (y * w + x) * 4;
+            let d = (dy * out_w + dx) * 4;
+            dst[d..d + 4].copy_from_slice(&src[s..s + 4]);
+        }
+    }
+}
+
 fn render_gradient(framebuffer: &mut [u8], width: u32, height: u32, frame_index: u64) {
     let w = width as usize;
     let h = height as usize;
@@ -526,6 +1362,61 @@ fn render_gradient(framebuffer: &mut [u8], width: u32, height: u32, frame_index:
     }
 }
 
+fn render_stop_gradient(
+    framebuffer: &mut [u8],
+    width: u32,
+    height: u32,
+    frame_index: u64,
+    stops: &[(f32, [u8; 4])],
+) {
+    let w = width as usize;
+    let h = height as usize;
+
+    if framebuffer.len() < w * h * 4 || w == 0 || h == 0 {
+        return;
+    }
+
+    // Horizontal ramp that scrolls one column per frame.
+    let span = (w - 1).max(1) as f32;
+    let offset = (frame_index % w as u64) as usize;
+    let mut row = vec![0_u8; w * 4];
+    for x in 0..w {
+        let t = ((x + offset) % w) as f32 / span;
+        row[x * 4..x * 4 + 4].copy_from_slice(&sample_stops(stops, t));
+    }
+    for line in framebuffer[..w * h * 4].chunks_exact_mut(w * 4) {
+        line.copy_from_slice(&row);
+    }
+}
+
+fn sample_stops(stops: &[(f32, [u8; 4])], t: f32) -> [u8; 4] {
+    let Some(&(first_pos, first_color)) = stops.first() else {
+        return [0, 0, 0, 255];
+    };
+    if t <= first_pos {
+        return first_color;
+    }
+    for pair in stops.windows(2) {
+        let (start, from) = pair[0];
+        let (end, to) = pair[1];
+        if t <= end {
+            let local = if end > start {
+                (t - start) / (end - start)
+            } else {
+                1.0
+            };
+            let mut color = [0_u8; 4];
+            for channel in 0..4 {
+                let a = from[channel] as f32;
+                let b = to[channel] as f32;
+                color[channel] = (a + (b - a) * local).round() as u8;
+            }
+            return color;
+        }
+    }
+    stops[stops.len() - 1].1
+}
+
 fn render_rects(framebuffer: &mut [u8], width: u32, height: u32, frame_index: u64) {
     clear_rgba(framebuffer, 20, 20, 24, 255);
 
@@ -569,35 +1460,311 @@ fn render_rects(framebuffer: &mut [u8], width: u32, height: u32, frame_index: u6
 }
 
 fn draw_overlay(framebuffer: &mut [u8], width: u32, height: u32, overlay: OverlayInfo) {
+    draw_overlay_text(framebuffer, width, height, &overlay.label(), overlay.corner);
+}
+
+fn draw_overlay_text(
+    framebuffer: &mut [u8],
+    width: u32,
+    height: u32,
+    text: &str,
+    corner: OverlayCorner,
+) {
     if width < 24 || height < 16 {
         return;
     }
 
+    let margin = 6;
     let panel_width = width.min(360) as i32;
+    let lines = wrap_text(text, (panel_width - 8) as u32);
+    let line_height = 10;
+    let panel_height = 12 + line_height * lines.len().max(1) as i32;
+    let panel_x = match corner {
+        OverlayCorner::TopLeft | OverlayCorner::BottomLeft => margin,
+        OverlayCorner::TopRight | OverlayCorner::BottomRight => {
+            (width as i32 - margin - panel_width).max(0)
+        }
+    };
+    let panel_y = match corner {
+        OverlayCorner::TopLeft | OverlayCorner::TopRight => margin,
+        OverlayCorner::BottomLeft | OverlayCorner::BottomRight => {
+            (height as i32 - margin - panel_height).max(0)
+        }
+    };
     fill_rect(
         framebuffer,
         width,
         height,
-        6,
-        6,
+        panel_x,
+        panel_y,
         panel_width,
-        22,
+        panel_height,
         [0, 0, 0, 180],
     );
 
-    let text = format!(
-        "F{} P{:.1} W{} H{}",
-        overlay.frame_index, overlay.fps, overlay.width, overlay.height
-    );
-    draw_text(
-        framebuffer,
-        width,
-        height,
-        10,
-        10,
-        &text,
-        [230, 230, 230, 255],
-    );
+    for (index, line) in lines.iter().enumerate() {
+        draw_text(
+            framebuffer,
+            width,
+            height,
+            panel_x + 4,
+            panel_y + 4 + index as i32 * line_height,
+            line,
+            [230, 230, 230, 255],
+        );
+    }
+}
+
+// Greedy word wrap against the bitmap font's advance; words wider than a line are split.
+fn wrap_text(text: &str, max_width: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{line} {word}")
+        };
+        if measure_text(&candidate, 1) <= max_width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for ch in word.chars() {
+            line.push(ch);
+            if measure_text(&line, 1) > max_width && line.chars().count() > 1 {
+                line.pop();
+                lines.push(std::mem::replace(&mut line, ch.to_string()));
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+// Downsamples the frame to `cols` x `rows` characters for terminal previews. Each character is
+// the average luma of the pixels it covers, picked from ASCII_RAMP (dark to light);
+// every row ends in a newline and alpha is ignored.
+pub fn to_ascii(pixels: &[u8], width: u32, height: u32, cols: u32, rows: u32) -> String {
+    if width == 0
+        || height == 0
+        || cols == 0
+        || rows == 0
+        || pixels.len() < pixel_len(width, height)
+    {
+        return String::new();
+    }
+
+    // Cell `index` of `count` along an axis of `len` pixels; never empty, even when upsampling.
+    let span = |index: u32, count: u32, len: u32| {
+        let start = (u64::from(index) * u64::from(len) / u64::from(count)) as usize;
+        let end = (u64::from(index + 1) * u64::from(len) / u64::from(count)) as usize;
+        start..end.max(start + 1)
+    };
+    let stride = width as usize * 4;
+    let mut out = String::with_capacity((cols as usize + 1) * rows as usize);
+    for row in 0..rows {
+        let ys = span(row, rows, height);
+        for col in 0..cols {
+            let xs = span(col, cols, width);
+            let mut total = 0_u64;
+            for y in ys.clone() {
+                for x in xs.clone() {
+                    total += u64::from(luma(&pixels[y * stride + x * 4..]));
+                }
+            }
+            let luma = total / (ys.len() * xs.len()) as u64;
+            let level = (luma * (ASCII_RAMP.len() as u64 - 1) + 127) / 255;
+            out.push(ASCII_RAMP[level as usize] as char);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// FNV-1a (64-bit) over raw RGBA bytes; the golden hashes are stored in this format.
+pub fn frame_hash(pixels: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325_u64;
+    for b in pixels {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub const RGBA_HEADER_MAGIC: [u8; 4] = *b"BRGB";
+pub const RGBA_HEADER_VERSION: u32 = 1;
+pub const RGBA_HEADER_LEN: usize = 32;
+
+// Optional framing for raw RGBA files: magic, format version, width, height, payload length and
+// the payload's `frame_hash`, all little-endian, ahead of the pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RgbaHeader {
+    pub width: u32,
+    pub height: u32,
+    pub byte_len: u64,
+    pub checksum: u64,
+}
+
+impl RgbaHeader {
+    pub fn for_frame(width: u32, height: u32, pixels: &[u8]) -> Self {
+        Self {
+            width,
+            height,
+            byte_len: pixels.len() as u64,
+            checksum: frame_hash(pixels),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; RGBA_HEADER_LEN] {
+        let mut bytes = [0; RGBA_HEADER_LEN];
+        bytes[0..4].copy_from_slice(&RGBA_HEADER_MAGIC);
+        bytes[4..8].copy_from_slice(&RGBA_HEADER_VERSION.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.width.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.height.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.byte_len.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    pub fn is_present(bytes: &[u8]) -> bool {
+        bytes.starts_with(&RGBA_HEADER_MAGIC)
+    }
+
+    // Splits a headered file into its header and pixels, checking the version, that the
+    // payload is exactly `width * height * 4` bytes and that it matches the checksum.
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), String> {
+        if bytes.len() < RGBA_HEADER_LEN || !Self::is_present(bytes) {
+            return Err("missing rgba header".to_string());
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let version = u32_at(4);
+        if version != RGBA_HEADER_VERSION {
+            return Err(format!("unsupported rgba header version {version}"));
+        }
+        let header = Self {
+            width: u32_at(8),
+            height: u32_at(12),
+            byte_len: u64_at(16),
+            checksum: u64_at(24),
+        };
+
+        let expected = u64::from(header.width) * u64::from(header.height) * 4;
+        if header.byte_len != expected {
+            return Err(format!(
+                "rgba header length {} does not match {}x{} rgba8 ({expected} bytes)",
+                header.byte_len, header.width, header.height
+            ));
+        }
+        let pixels = &bytes[RGBA_HEADER_LEN..];
+        if pixels.len() as u64 != header.byte_len {
+            return Err(format!(
+                "rgba payload is {} bytes, header expects {} (truncated?)",
+                pixels.len(),
+                header.byte_len
+            ));
+        }
+        if frame_hash(pixels) != header.checksum {
+            return Err("rgba payload does not match the header checksum".to_string());
+        }
+        Ok((header, pixels))
+    }
+}
+
+const HIGHLIGHT_BORDER: i32 = 2;
+const LOADING_DOTS: u64 = 8;
+const LOADING_FRAMES_PER_STEP: u64 = 6;
+const LOADING_TEXT_GAP: i32 = 12;
+const LOADING_TEXT_COLOR: [u8; 4] = [205, 216, 240, 255];
+
+// Translucent `alpha` fill over existing pixels with an opaque border band, for selections and
+// focus outlines. `rect.color` is ignored in favour of `color`.
+pub fn draw_highlight(
+    framebuffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rect: DrawRect,
+    color: [u8; 4],
+    alpha: u8,
+) {
+    if rect.width <= 0 || rect.height <= 0 || framebuffer.len() < pixel_len(width, height) {
+        return;
+    }
+
+    let max_x = i32::try_from(width).unwrap_or(i32::MAX);
+    let max_y = i32::try_from(height).unwrap_or(i32::MAX);
+    let x0 = rect.x.clamp(0, max_x);
+    let y0 = rect.y.clamp(0, max_y);
+    let x1 = rect.x.saturating_add(rect.width).clamp(0, max_x);
+    let y1 = rect.y.saturating_add(rect.height).clamp(0, max_y);
+    let fill = [color[0], color[1], color[2], alpha];
+    let stride = width as usize * 4;
+    for py in y0 as usize..y1 as usize {
+        for px in x0 as usize..x1 as usize {
+            let i = py * stride + px * 4;
+            blend_pixel(&mut framebuffer[i..i + 4], fill, 255);
+        }
+    }
+
+    let border = DrawRect {
+        color: [color[0], color[1], color[2], 255],
+        ..rect
+    };
+    for edge in border.outline(HIGHLIGHT_BORDER) {
+        fill_rect(
+            framebuffer,
+            width,
+            height,
+            edge.x,
+            edge.y,
+            edge.width,
+            edge.height,
+            edge.color,
+        );
+    }
+}
+
+// A circle of full brightness centred on `x`, `y`. Past `radius` the frame darkens over another
+// `radius` of soft edge, then stays at the full dim level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spotlight {
+    pub x: i32,
+    pub y: i32,
+    pub radius: u32,
+}
+
+const SPOTLIGHT_DIM_ALPHA: f32 = 170.0;
+
+// Blends black over each pixel with an alpha that grows with its distance from the spotlight.
+pub fn apply_spotlight(framebuffer: &mut [u8], width: u32, height: u32, spotlight: Spotlight) {
+    if width == 0 || height == 0 || framebuffer.len() < pixel_len(width, height) {
+        return;
+    }
+    let radius = spotlight.radius.max(1) as f32;
+    for (py, row) in framebuffer
+        .chunks_exact_mut(width as usize * 4)
+        .take(height as usize)
+        .enumerate()
+    {
+        let dy = py as f32 - spotlight.y as f32;
+        for (px, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let dx = px as f32 - spotlight.x as f32;
+            let edge = ((dx.hypot(dy) - radius) / radius).clamp(0.0, 1.0);
+            let alpha = (edge * SPOTLIGHT_DIM_ALPHA).round() as u8;
+            blend_pixel(pixel, [0, 0, 0, 255], alpha);
+        }
+    }
+}
+
+pub fn measure_text(text: &str, scale: u32) -> u32 {
+    (text.chars().count() as u32).saturating_mul(6 * scale.max(1))
 }
 
 fn draw_text(
@@ -612,6 +1779,7 @@ fn draw_text(
     draw_text_scaled(framebuffer, width, height, x, y, text, color, 1);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_text_fontdue(
     framebuffer: &mut [u8],
     width: u32,
@@ -620,57 +1788,188 @@ fn draw_text_fontdue(
     y: i32,
     text: &str,
     color: [u8; 4],
-    font: &Font,
+    (font_index, font): (usize, &Font),
     px: f32,
+    atlas: &mut GlyphAtlas,
 ) {
     let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
     layout.reset(&LayoutSettings::default());
     layout.append(&[font], &TextStyle::new(text, px, 0));
 
+    // `y` is the top of the line box; glyphs hang from a baseline one ascent below it.
+    let baseline = y + line_ascent(font, px).round() as i32;
     for glyph in layout.glyphs() {
-        let (metrics, bitmap) = font.rasterize_config(glyph.key);
-        if metrics.width == 0 || metrics.height == 0 {
+        let glyph_x = x + glyph.x.floor() as i32;
+        let key = GlyphKey {
+            font: font_index,
+            ch: glyph.parent,
+            px_bits: px.to_bits(),
+        };
+        if let Some(slot) = atlas.glyph(key, font, glyph) {
+            if slot.width > 0 && slot.height > 0 {
+                draw_alpha_bitmap(
+                    framebuffer,
+                    width,
+                    height,
+                    glyph_x,
+                    baseline - (slot.ymin + slot.height as i32),
+                    slot.width,
+                    slot.height,
+                    atlas.coverage(&slot),
+                    GLYPH_ATLAS_WIDTH,
+                    color,
+                );
+            }
             continue;
         }
+
+        // Too large for the atlas: rasterize and draw it directly.
+        let (metrics, bitmap) = font.rasterize_config(glyph.key);
         draw_alpha_bitmap(
             framebuffer,
             width,
             height,
-            x + glyph.x.floor() as i32,
-            y + glyph.y.floor() as i32,
+            glyph_x,
+            baseline - (metrics.ymin + metrics.height as i32),
             metrics.width,
             metrics.height,
             &bitmap,
+            metrics.width,
             color,
         );
     }
 }
 
-fn draw_alpha_bitmap(
-    framebuffer: &mut [u8],
-    width: u32,
-    height: u32,
-    x: i32,
-    y: i32,
-    bmp_w: usize,
-    bmp_h: usize,
-    bitmap: &[u8],
-    color: [u8; 4],
-) {
-    let stride = width as usize * 4;
-    for row in 0..bmp_h {
-        let py = y + row as i32;
-        if py < 0 || py >= height as i32 {
-            continue;
-        }
-        for col in 0..bmp_w {
-            let px = x + col as i32;
-            if px < 0 || px >= width as i32 {
-                continue;
+const GLYPH_ATLAS_WIDTH: usize = 512;
+const GLYPH_ATLAS_MAX_HEIGHT: usize = 2048;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlyphAtlasStats {
+    // Distinct (font, char, px) glyphs currently packed, including blank ones like spaces.
+    pub entries: usize,
+    // Glyph rasterizations since the renderer was built; repeats are served from the atlas.
+    pub rasterized: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: usize,
+    ch: char,
+    px_bits: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AtlasSlot {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    ymin: i32,
+}
+
+// Rasterized glyph coverage packed into one buffer GLYPH_ATLAS_WIDTH wide. Shelf packing: glyphs
+// fill a shelf left to right and a full shelf starts a new one below its tallest glyph. The
+// buffer grows downward and starts over once it would pass GLYPH_ATLAS_MAX_HEIGHT.
+#[derive(Debug, Default)]
+struct GlyphAtlas {
+    coverage: Vec<u8>,
+    slots: HashMap<GlyphKey, AtlasSlot>,
+    shelf_x: usize,
+    shelf_y: usize,
+    shelf_height: usize,
+    rasterized: usize,
+}
+
+impl GlyphAtlas {
+    // The glyph's slot, rasterizing and packing it on first use; None when it cannot fit.
+    fn glyph(&mut self, key: GlyphKey, font: &Font, position: &GlyphPosition) -> Option<AtlasSlot> {
+        if let Some(slot) = self.slots.get(&key) {
+            return Some(*slot);
+        }
+        if position.width > GLYPH_ATLAS_WIDTH || position.height > GLYPH_ATLAS_MAX_HEIGHT {
+            return None;
+        }
+
+        let (metrics, bitmap) = font.rasterize_config(position.key);
+        self.rasterized += 1;
+        if self.shelf_x + metrics.width > GLYPH_ATLAS_WIDTH {
+            self.shelf_x = 0;
+            self.shelf_y += self.shelf_height;
+            self.shelf_height = 0;
+        }
+        if self.shelf_y + metrics.height > GLYPH_ATLAS_MAX_HEIGHT {
+            self.coverage.clear();
+            self.slots.clear();
+            self.shelf_x = 0;
+            self.shelf_y = 0;
+            self.shelf_height = 0;
+        }
+
+        let slot = AtlasSlot {
+            x: self.shelf_x,
+            y: self.shelf_y,
+            width: metrics.width,
+            height: metrics.height,
+            ymin: metrics.ymin,
+        };
+        let rows = (slot.y + slot.height) * GLYPH_ATLAS_WIDTH;
+        if self.coverage.len() < rows {
+            self.coverage.resize(rows, 0);
+        }
+        for (row, line) in bitmap.chunks_exact(slot.width.max(1)).enumerate() {
+            let start = (slot.y + row) * GLYPH_ATLAS_WIDTH + slot.x;
+            self.coverage[start..start + slot.width].copy_from_slice(line);
+        }
+        self.shelf_x += slot.width;
+        self.shelf_height = self.shelf_height.max(slot.height);
+        self.slots.insert(key, slot);
+        Some(slot)
+    }
+
+    // Coverage from the slot's top-left corner; rows are GLYPH_ATLAS_WIDTH apart.
+    fn coverage(&self, slot: &AtlasSlot) -> &[u8] {
+        &self.coverage[slot.y * GLYPH_ATLAS_WIDTH + slot.x..]
+    }
+
+    fn stats(&self) -> GlyphAtlasStats {
+        GlyphAtlasStats {
+            entries: self.slots.len(),
+            rasterized: self.rasterized,
+            width: GLYPH_ATLAS_WIDTH,
+            height: self.coverage.len() / GLYPH_ATLAS_WIDTH,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_alpha_bitmap(
+    framebuffer: &mut [u8],
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    bmp_w: usize,
+    bmp_h: usize,
+    bitmap: &[u8],
+    bitmap_stride: usize,
+    color: [u8; 4],
+) {
+    let stride = width as usize * 4;
+    for row in 0..bmp_h {
+        let py = y + row as i32;
+        if py < 0 || py >= height as i32 {
+            continue;
+        }
+        for col in 0..bmp_w {
+            let px = x + col as i32;
+            if px < 0 || px >= width as i32 {
+                continue;
             }
 
-            let src_row = bmp_h - 1 - row;
-            let coverage = bitmap[src_row * bmp_w + col];
+            // Coverage is row-major from the top of the glyph.
+            let coverage = bitmap[row * bitmap_stride + col];
             if coverage == 0 {
                 continue;
             }
@@ -696,6 +1995,7 @@ fn blend_pixel(dst: &mut [u8], src: [u8; 4], coverage: u8) {
     dst[3] = 255;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_text_scaled(
     framebuffer: &mut [u8],
     width: u32,
@@ -713,6 +2013,7 @@ fn draw_text_scaled(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_char_scaled(
     framebuffer: &mut [u8],
     width: u32,
@@ -723,7 +2024,7 @@ fn draw_char_scaled(
     color: [u8; 4],
     scale: u32,
 ) {
-    let rows = glyph_rows(ch.to_ascii_uppercase());
+    let rows = builtin_glyph(ch);
     let pixel = scale.max(1) as i32;
 
     for (row_index, row_bits) in rows.iter().enumerate() {
@@ -733,7 +2034,7 @@ fn draw_char_scaled(
                 continue;
             }
 
-            fill_rect(
+            blend_rect(
                 framebuffer,
                 width,
                 height,
@@ -747,8 +2048,52 @@ fn draw_char_scaled(
     }
 }
 
-fn glyph_rows(ch: char) -> [u8; 7] {
-    match ch {
+// Characters with a dedicated bitmap; lowercase letters share the uppercase glyphs.
+pub const BUILTIN_GLYPH_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789<>/:;,.-_='\"()[]+!?& ";
+
+pub fn has_builtin_glyph(ch: char) -> bool {
+    BUILTIN_GLYPH_CHARS.contains(ch.to_ascii_uppercase())
+}
+
+// Drops trailing characters until the rest plus "..." fits; empty when not even "..." fits.
+fn elide_to_width(text: &str, max_width: u32, advance: impl Fn(&str) -> f32) -> Cow<'_, str> {
+    let max_width = max_width as f32;
+    if advance(text) <= max_width {
+        return Cow::Borrowed(text);
+    }
+    for (end, _) in text.char_indices().rev() {
+        let candidate = format!("{}...", text[..end].trim_end());
+        if advance(&candidate) <= max_width {
+            return Cow::Owned(candidate);
+        }
+    }
+    Cow::Borrowed("")
+}
+
+// Replaces characters the active font cannot draw; borrowed when nothing needs replacing.
+fn substitute_missing(
+    text: &str,
+    placeholder: Option<char>,
+    has_glyph: impl Fn(char) -> bool,
+) -> Cow<'_, str> {
+    match placeholder {
+        Some(placeholder) if text.chars().any(|ch| !ch.is_whitespace() && !has_glyph(ch)) => text
+            .chars()
+            .map(|ch| {
+                if ch.is_whitespace() || has_glyph(ch) {
+                    ch
+                } else {
+                    placeholder
+                }
+            })
+            .collect(),
+        _ => Cow::Borrowed(text),
+    }
+}
+
+// 5x7 bitmap for `ch`: one byte per row, bit 4 is the leftmost column.
+pub fn builtin_glyph(ch: char) -> [u8; 7] {
+    match ch.to_ascii_uppercase() {
         'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
         'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
         'C' => [0b01111, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b01111],
@@ -810,6 +2155,34 @@ fn glyph_rows(ch: char) -> [u8; 7] {
     }
 }
 
+// The part of a rect that lies inside a `width` x `height` frame, as pixel ranges; far edges
+// saturate rather than overflow.
+fn clip_rect(
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    rect_width: i32,
+    rect_height: i32,
+) -> Option<(Range<usize>, Range<usize>)> {
+    if rect_width <= 0 || rect_height <= 0 {
+        return None;
+    }
+
+    let max_x = i32::try_from(width).unwrap_or(i32::MAX);
+    let max_y = i32::try_from(height).unwrap_or(i32::MAX);
+    let x0 = x.clamp(0, max_x);
+    let y0 = y.clamp(0, max_y);
+    let x1 = x.saturating_add(rect_width).clamp(0, max_x);
+    let y1 = y.saturating_add(rect_height).clamp(0, max_y);
+
+    if x0 >= x1 || y0 >= y1 {
+        return None;
+    }
+    Some((x0 as usize..x1 as usize, y0 as usize..y1 as usize))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn fill_rect(
     framebuffer: &mut [u8],
     width: u32,
@@ -820,25 +2193,57 @@ fn fill_rect(
     rect_height: i32,
     color: [u8; 4],
 ) {
-    if rect_width <= 0 || rect_height <= 0 {
+    let Some((xs, ys)) = clip_rect(width, height, x, y, rect_width, rect_height) else {
         return;
-    }
+    };
 
-    let x0 = x.max(0).min(width as i32);
-    let y0 = y.max(0).min(height as i32);
-    let x1 = (x + rect_width).max(0).min(width as i32);
-    let y1 = (y + rect_height).max(0).min(height as i32);
+    let stride = width as usize * 4;
+    for py in ys {
+        let row = py * stride;
+        for px in xs.clone() {
+            let i = row + px * 4;
+            framebuffer[i..i + 4].copy_from_slice(&color);
+        }
+    }
+}
 
-    if x0 >= x1 || y0 >= y1 {
+// Like `fill_rect`, but translucent colors (element opacity, rgba backgrounds) blend over the
+// frame. Display-list rects and text use this; debug chrome such as the HUD panel still
+// overwrites, alpha included.
+#[allow(clippy::too_many_arguments)]
+fn blend_rect(
+    framebuffer: &mut [u8],
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    rect_width: i32,
+    rect_height: i32,
+    color: [u8; 4],
+) {
+    if color[3] == 255 {
+        fill_rect(
+            framebuffer,
+            width,
+            height,
+            x,
+            y,
+            rect_width,
+            rect_height,
+            color,
+        );
         return;
     }
+    let Some((xs, ys)) = clip_rect(width, height, x, y, rect_width, rect_height) else {
+        return;
+    };
 
     let stride = width as usize * 4;
-    for py in y0 as usize..y1 as usize {
+    for py in ys {
         let row = py * stride;
-        for px in x0 as usize..x1 as usize {
+        for px in xs.clone() {
             let i = row + px * 4;
-            framebuffer[i..i + 4].copy_from_slice(&color);
+            blend_pixel(&mut framebuffer[i..i + 4], color, 255);
         }
     }
 }
@@ -847,6 +2252,11 @@ fn fill_rect(
 mod tests {
     use super::*;
 
+    const TEST_FONT_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/fixtures/fonts/DejaVuSans.ttf"
+    );
+
     #[test]
     fn pixels_are_rgba8() {
         let mut renderer = Renderer::new(8, 4);
@@ -860,13 +2270,51 @@ mod tests {
         assert_eq!(&frame[0..4], &[70, 180, 240, 255]);
     }
 
+    #[test]
+    fn pattern_from_index_covers_variants() {
+        assert_eq!(Pattern::from_index(0), Some(Pattern::Gradient));
+        assert_eq!(Pattern::from_index(1), Some(Pattern::Solid));
+        assert_eq!(Pattern::from_index(2), Some(Pattern::Rects));
+        assert_eq!(Pattern::from_index(3), None);
+        assert_eq!(Pattern::from_index(u8::MAX), None);
+    }
+
+    #[test]
+    fn overlay_label_includes_all_fields() {
+        let overlay = OverlayInfo::builder()
+            .frame_index(3)
+            .fps(59.94)
+            .width(640)
+            .height(480)
+            .build();
+
+        assert_eq!(overlay.label(), "F3 P59.9 W640 H480");
+    }
+
+    #[test]
+    fn overlay_label_omits_absent_fields() {
+        let label = OverlayInfo::builder().fps(30.0).build().label();
+
+        assert_eq!(label, "P30.0");
+        assert!(!label.contains('W'));
+        assert!(!label.contains('H'));
+    }
+
     #[test]
     fn deterministic_frame_hash() {
         let mut renderer = Renderer::new(64, 32);
         renderer.set_pattern(Pattern::Gradient);
         let frame = renderer.render(42, 1.25);
 
-        assert_eq!(fnv1a64(frame), 0xaa3e6ff366d761a5);
+        assert_eq!(frame_hash(frame), 0xaa3e6ff366d761a5);
+    }
+
+    #[test]
+    fn frame_hash_matches_fnv1a_vectors() {
+        assert_eq!(frame_hash(&[]), 0xcbf29ce484222325);
+        assert_eq!(frame_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(frame_hash(&[0, 0, 0, 255]), frame_hash(&[0, 0, 0, 255]));
+        assert_ne!(frame_hash(&[0, 0, 0, 255]), frame_hash(&[0, 0, 255, 0]));
     }
 
     #[test]
@@ -875,7 +2323,7 @@ mod tests {
         renderer.set_pattern(Pattern::Solid);
         let frame = renderer.render(77, 1.5);
 
-        assert_eq!(fnv1a64(frame), 0xb10375b873063325);
+        assert_eq!(frame_hash(frame), 0xb10375b873063325);
     }
 
     #[test]
@@ -889,6 +2337,1010 @@ mod tests {
         assert_eq!(shrunk_capacity, initial_capacity);
     }
 
+    #[test]
+    fn outline_covers_rect_edges() {
+        let rect = DrawRect {
+            x: 2,
+            y: 3,
+            width: 10,
+            height: 6,
+            color: [1, 2, 3, 255],
+        };
+        let [top, bottom, left, right] = rect.outline(2);
+
+        assert_eq!((top.x, top.y, top.width, top.height), (2, 3, 10, 2));
+        assert_eq!(
+            (bottom.x, bottom.y, bottom.width, bottom.height),
+            (2, 7, 10, 2)
+        );
+        assert_eq!((left.x, left.y, left.width, left.height), (2, 3, 2, 6));
+        assert_eq!((right.x, right.y, right.width, right.height), (10, 3, 2, 6));
+    }
+
+    #[test]
+    fn rotation_180_maps_top_left_to_bottom_right() {
+        let mut renderer = Renderer::new(4, 3);
+        renderer.set_rotation(Rotation::Deg180);
+        let rects = [DrawRect {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            color: [255, 0, 0, 255],
+        }];
+
+        assert_eq!(renderer.presented_size(), (4, 3));
+        let frame = renderer.render_display_list(0, 0.0, &rects, &[], None);
+        let last = (3 * 4 - 1) * 4;
+        assert_eq!(&frame[last..last + 4], &[255, 0, 0, 255]);
+        assert_ne!(&frame[0..4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn rotation_90_swaps_presented_axes() {
+        let mut renderer = Renderer::new(4, 3);
+        renderer.set_rotation(Rotation::Deg90);
+        let rects = [DrawRect {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            color: [255, 0, 0, 255],
+        }];
+
+        assert_eq!(renderer.presented_size(), (3, 4));
+        let frame = renderer.render_display_list(0, 0.0, &rects, &[], None);
+        // Top-left of a clockwise rotation lands in the top-right column.
+        let top_right = 2 * 4;
+        assert_eq!(&frame[top_right..top_right + 4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn invert_flips_rgb_and_keeps_alpha() {
+        let mut renderer = Renderer::new(2, 2);
+        renderer.set_invert(true);
+        let rects = [DrawRect {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+            color: [10, 20, 30, 255],
+        }];
+
+        let frame = renderer.render_display_list(0, 0.0, &rects, &[], None);
+        assert_eq!(&frame[0..4], &[245, 235, 225, 255]);
+    }
+
+    #[test]
+    fn grayscale_converts_red_to_luma() {
+        let mut renderer = Renderer::new(2, 2);
+        renderer.set_grayscale(true);
+        let rects = [DrawRect {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+            color: [255, 0, 0, 255],
+        }];
+
+        let frame = renderer.render_display_list(0, 0.0, &rects, &[], None);
+        assert_eq!(&frame[0..4], &[76, 76, 76, 255]);
+    }
+
+    #[test]
+    fn builtin_glyph_exposes_bitmap_table() {
+        assert_eq!(
+            builtin_glyph('A'),
+            [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]
+        );
+        assert_eq!(builtin_glyph('a'), builtin_glyph('A'));
+
+        let fallback = builtin_glyph('\u{2603}');
+        assert_eq!(
+            fallback,
+            [0b00000, 0b00000, 0b01110, 0b00010, 0b00100, 0b00000, 0b00100]
+        );
+        for ch in BUILTIN_GLYPH_CHARS.chars() {
+            assert_ne!(
+                builtin_glyph(ch),
+                fallback,
+                "{ch:?} uses the fallback glyph"
+            );
+        }
+    }
+
+    #[test]
+    fn measure_text_uses_bitmap_advance() {
+        assert_eq!(measure_text("abc", 1), 18);
+        assert_eq!(measure_text("abc", 2), 36);
+        assert_eq!(measure_text("", 3), 0);
+    }
+
+    #[test]
+    fn text_advance_follows_proportional_glyphs() {
+        let mut renderer = Renderer::new(8, 8);
+        renderer.fonts.push(FontChoice {
+            name: "DejaVu Sans".to_string(),
+            path: Some(PathBuf::from(TEST_FONT_PATH)),
+        });
+        assert!(renderer.set_font_index(renderer.font_count() - 1));
+
+        let wide = renderer.text_advance("WWWW", 1);
+        let narrow = renderer.text_advance("iiii", 1);
+        assert!(wide > narrow, "wide={wide} narrow={narrow}");
+    }
+
+    #[test]
+    fn descenders_stay_inside_the_text_box() {
+        // Engine text boxes are 18px tall with the label drawn 4px below their top.
+        let (box_y, box_height) = (10_i32, 18_i32);
+        let mut renderer = Renderer::new(120, 48);
+        renderer.fonts.push(FontChoice {
+            name: "DejaVu Sans".to_string(),
+            path: Some(PathBuf::from(TEST_FONT_PATH)),
+        });
+        assert!(renderer.set_font_index(renderer.font_count() - 1));
+        renderer.set_clear_color(Some([255, 255, 255, 255]));
+
+        let texts = [DrawText {
+            x: 4,
+            y: box_y + 4,
+            text: "gjpqy".to_string(),
+            color: [0, 0, 0, 255],
+            scale: 2,
+            underline: false,
+            max_width: None,
+            rtl: false,
+            align: None,
+        }];
+        let frame = renderer.render_display_list(0, 0.0, &[], &texts, None);
+
+        let inked_rows: Vec<i32> = (0..48)
+            .filter(|&y| {
+                frame[y as usize * 120 * 4..(y as usize + 1) * 120 * 4]
+                    .chunks_exact(4)
+                    .any(|px| px != [255, 255, 255, 255])
+            })
+            .collect();
+        let (top, bottom) = (inked_rows[0], *inked_rows.last().unwrap());
+        assert!(top >= box_y, "ink starts at {top}");
+        assert!(bottom < box_y + box_height, "ink ends at {bottom}");
+        // The descenders must actually hang below the x-height, not be clipped away.
+        assert!(bottom - top >= 8, "ink spans {top}..={bottom}");
+    }
+
+    #[test]
+    fn missing_glyph_placeholder_replaces_unknown_chars() {
+        let render = |renderer: &mut Renderer, text: &str| {
+            let texts = [DrawText {
+                x: 1,
+                y: 1,
+                text: text.to_string(),
+                color: [255, 255, 255, 255],
+                scale: 1,
+                underline: false,
+                max_width: None,
+                rtl: false,
+                align: None,
+            }];
+            renderer
+                .render_display_list(0, 0.0, &[], &texts, None)
+                .to_vec()
+        };
+        let mut renderer = Renderer::new(16, 10);
+        renderer.set_clear_color(Some([0, 0, 0, 255]));
+        assert!(renderer.set_font_index(0));
+
+        let question = render(&mut renderer, "?");
+        assert_ne!(render(&mut renderer, "\u{263a}"), question);
+
+        assert!(!renderer.set_missing_glyph('\u{25a1}'));
+        assert!(renderer.set_missing_glyph('?'));
+        assert_eq!(renderer.missing_glyph(), Some('?'));
+        assert_eq!(render(&mut renderer, "\u{263a}"), question);
+    }
+
+    #[test]
+    fn max_width_elides_long_labels() {
+        assert_eq!(
+            elide_to_width("Hello world", 40, |s| measure_text(s, 1) as f32),
+            "Hel..."
+        );
+        assert_eq!(
+            elide_to_width("Hi", 40, |s| measure_text(s, 1) as f32),
+            "Hi"
+        );
+        assert_eq!(
+            elide_to_width("Hello", 12, |s| measure_text(s, 1) as f32),
+            ""
+        );
+
+        let render = |max_width: Option<u32>| {
+            let mut renderer = Renderer::new(100, 10);
+            renderer.set_clear_color(Some([0, 0, 0, 255]));
+            let texts = [DrawText {
+                x: 0,
+                y: 1,
+                text: "Hello world, this is long".to_string(),
+                color: [255, 255, 255, 255],
+                scale: 1,
+                underline: true,
+                max_width,
+                rtl: false,
+                align: None,
+            }];
+            renderer
+                .render_display_list(0, 0.0, &[], &texts, None)
+                .to_vec()
+        };
+        let inked_past = |frame: &[u8], x: usize| {
+            frame
+                .chunks_exact(4)
+                .enumerate()
+                .any(|(index, px)| index % 100 >= x && px != [0, 0, 0, 255])
+        };
+        assert!(inked_past(&render(None), 40));
+        let elided = render(Some(40));
+        assert!(!inked_past(&elided, 40));
+        assert!(inked_past(&elided, 30), "the trailing dots should be drawn");
+    }
+
+    #[test]
+    fn rtl_text_ends_at_its_anchor() {
+        let inked_columns = |rtl: bool| {
+            let mut renderer = Renderer::new(64, 10);
+            renderer.set_clear_color(Some([0, 0, 0, 255]));
+            assert!(renderer.set_font_index(0));
+            let texts = [DrawText {
+                x: 40,
+                y: 1,
+                text: "abc".to_string(),
+                color: [255, 255, 255, 255],
+                scale: 1,
+                underline: false,
+                max_width: None,
+                rtl,
+                align: None,
+            }];
+            let frame = renderer.render_display_list(0, 0.0, &[], &texts, None);
+            let columns: Vec<usize> = (0..64)
+                .filter(|&x| (0..10).any(|y| frame[(y * 64 + x) * 4] != 0))
+                .collect();
+            (columns[0], *columns.last().unwrap())
+        };
+
+        let (ltr_left, _) = inked_columns(false);
+        assert_eq!(ltr_left, 40);
+        let (rtl_left, rtl_right) = inked_columns(true);
+        assert!(rtl_right < 40, "rtl ink ends at {rtl_right}");
+        assert_eq!(rtl_left, 40 - measure_text("abc", 1) as usize);
+
+        // An anchor near i32::MIN saturates instead of overflowing.
+        let mut renderer = Renderer::new(64, 10);
+        renderer.set_clear_color(Some([0, 0, 0, 255]));
+        let texts = [DrawText {
+            x: i32::MIN + 1,
+            y: 1,
+            text: "abc".to_string(),
+            color: [255, 255, 255, 255],
+            scale: 1,
+            underline: true,
+            max_width: None,
+            rtl: true,
+            align: None,
+        }];
+        let frame = renderer.render_display_list(0, 0.0, &[], &texts, None);
+        assert!(frame.chunks_exact(4).all(|pixel| pixel == [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn aligned_text_is_placed_within_its_width() {
+        let first_inked_column = |align: Option<(TextAlign, u32)>, rtl: bool| {
+            let mut renderer = Renderer::new(64, 10);
+            renderer.set_clear_color(Some([0, 0, 0, 255]));
+            assert!(renderer.set_font_index(0));
+            let texts = [DrawText {
+                x: 4,
+                y: 1,
+                text: "abc".to_string(),
+                color: [255, 255, 255, 255],
+                scale: 1,
+                underline: false,
+                max_width: None,
+                rtl,
+                align,
+            }];
+            let frame = renderer.render_display_list(0, 0.0, &[], &texts, None);
+            (0..64)
+                .find(|&x| (0..10).any(|y| frame[(y * 64 + x) * 4] != 0))
+                .unwrap()
+        };
+
+        let slack = 40 - measure_text("abc", 1) as usize;
+        assert_eq!(first_inked_column(Some((TextAlign::Left, 40)), true), 4);
+        assert_eq!(
+            first_inked_column(Some((TextAlign::Center, 40)), false),
+            4 + slack / 2
+        );
+        assert_eq!(
+            first_inked_column(Some((TextAlign::Right, 40)), false),
+            4 + slack
+        );
+        // Too narrow to align: the line starts at the box edge.
+        assert_eq!(first_inked_column(Some((TextAlign::Right, 4)), false), 4);
+    }
+
+    #[test]
+    fn pattern_list_round_trips_labels() {
+        let all = Pattern::all();
+        for (index, pattern) in all.iter().enumerate() {
+            assert_eq!(all.iter().filter(|&other| other == pattern).count(), 1);
+            assert_eq!(Pattern::parse(pattern.label()), Some(*pattern));
+            assert_eq!(Pattern::from_index(index as u8), Some(*pattern));
+            assert_eq!(pattern.next(), all[(index + 1) % all.len()]);
+        }
+        for variant in [Pattern::Gradient, Pattern::Solid, Pattern::Rects] {
+            assert!(all.contains(&variant));
+        }
+        assert_eq!(Pattern::from_index(all.len() as u8), None);
+        assert_eq!(Pattern::parse("unknown"), None);
+    }
+
+    #[test]
+    fn ascii_preview_maps_luma_onto_the_ramp() {
+        let (width, height) = (40_u32, 30_u32);
+        let black = [0_u8, 0, 0, 255].repeat((width * height) as usize);
+        let white = [255_u8; 4].repeat((width * height) as usize);
+        assert_eq!(
+            to_ascii(&black, width, height, 8, 3),
+            "        \n".repeat(3)
+        );
+        assert_eq!(
+            to_ascii(&white, width, height, 8, 3),
+            "@@@@@@@@\n".repeat(3)
+        );
+
+        // Left half white: each column of characters averages only its own pixels.
+        let mut split = black.clone();
+        for row in split.chunks_exact_mut(width as usize * 4) {
+            row[..width as usize * 2].fill(255);
+        }
+        assert_eq!(to_ascii(&split, width, height, 4, 1), "@@  \n");
+        assert_eq!(to_ascii(&split, width, height, 80, 1).len(), 81);
+        assert_eq!(to_ascii(&white, width, height, 0, 3), "");
+    }
+
+    #[test]
+    fn spotlight_darkens_far_pixels_more_than_near_ones() {
+        let (width, height) = (64_u32, 8_u32);
+        let mut framebuffer = [200_u8, 200, 200, 255].repeat((width * height) as usize);
+        let spotlight = Spotlight {
+            x: 4,
+            y: 4,
+            radius: 10,
+        };
+        apply_spotlight(&mut framebuffer, width, height, spotlight);
+
+        let red = |x: u32| framebuffer[((4 * width + x) * 4) as usize];
+        assert_eq!(red(4), 200, "inside the radius is untouched");
+        assert_eq!(red(14), 200);
+        assert!(red(19) < red(14), "{} vs {}", red(19), red(14));
+        assert!(red(24) < red(19), "{} vs {}", red(24), red(19));
+        // Past the soft edge the dimming levels off.
+        assert_eq!(red(40), red(60));
+        assert!(framebuffer.chunks_exact(4).all(|px| px[3] == 255));
+    }
+
+    #[test]
+    fn highlight_blends_interior_and_draws_solid_border() {
+        let (width, height) = (12_u32, 10_u32);
+        let mut framebuffer = [0_u8, 0, 200, 255].repeat((width * height) as usize);
+        let rect = DrawRect {
+            x: 1,
+            y: 1,
+            width: 10,
+            height: 8,
+            color: [0, 0, 0, 0],
+        };
+        draw_highlight(
+            &mut framebuffer,
+            width,
+            height,
+            rect,
+            [255, 200, 0, 255],
+            128,
+        );
+
+        let pixel = |x: u32, y: u32| {
+            let index = ((y * width + x) * 4) as usize;
+            [
+                framebuffer[index],
+                framebuffer[index + 1],
+                framebuffer[index + 2],
+                framebuffer[index + 3],
+            ]
+        };
+        assert_eq!(pixel(1, 1), [255, 200, 0, 255]);
+        assert_eq!(pixel(2, 4), [255, 200, 0, 255]);
+        assert_eq!(pixel(10, 8), [255, 200, 0, 255]);
+
+        let interior = pixel(5, 4);
+        assert!(interior[0] > 100 && interior[0] < 155, "{interior:?}");
+        assert!(interior[2] > 70 && interior[2] < 130, "{interior:?}");
+        assert_eq!(interior[3], 255);
+
+        assert_eq!(pixel(0, 0), [0, 0, 200, 255]);
+        assert_eq!(pixel(11, 9), [0, 0, 200, 255]);
+
+        // Edges past i32::MAX saturate instead of overflowing; only the top-left border shows.
+        let mut framebuffer = [0_u8, 0, 200, 255].repeat((width * height) as usize);
+        let huge = DrawRect {
+            x: 4,
+            y: 4,
+            width: i32::MAX,
+            height: i32::MAX,
+            color: [0, 0, 0, 0],
+        };
+        draw_highlight(
+            &mut framebuffer,
+            width,
+            height,
+            huge,
+            [255, 200, 0, 255],
+            128,
+        );
+        assert_eq!(&framebuffer[..4], &[0, 0, 200, 255]);
+        let corner = ((4 * width + 4) * 4) as usize;
+        assert_eq!(&framebuffer[corner..corner + 4], &[255, 200, 0, 255]);
+    }
+
+    #[test]
+    fn translucent_rects_blend_but_the_overlay_panel_overwrites() {
+        let mut renderer = Renderer::new(160, 90);
+        renderer.set_clear_color(Some([200, 100, 0, 255]));
+        let rects = [DrawRect {
+            x: 100,
+            y: 60,
+            width: 20,
+            height: 20,
+            color: [0, 0, 200, 128],
+        }];
+        let overlay = OverlayInfo::builder().frame_index(1).build();
+        let frame = renderer.render_display_list(0, 0.0, &rects, &[], Some(overlay));
+
+        let pixel = |x: usize, y: usize| &frame[(y * 160 + x) * 4..][..4];
+        assert_eq!(pixel(110, 70), &[99, 49, 100, 255]);
+        // The HUD panel keeps its old overwrite, so its alpha lands in the frame as-is.
+        assert_eq!(pixel(7, 7), &[0, 0, 0, 180]);
+    }
+
+    #[test]
+    fn clear_color_fills_untouched_pixels() {
+        let mut renderer = Renderer::new(4, 4);
+        renderer.set_clear_color(Some([12, 34, 56, 255]));
+        let rects = [DrawRect {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            color: [255, 0, 0, 255],
+        }];
+
+        let frame = renderer.render_display_list(7, 0.5, &rects, &[], None);
+        assert_eq!(&frame[0..4], &[255, 0, 0, 255]);
+        let last = frame.len() - 4;
+        assert_eq!(&frame[last..], &[12, 34, 56, 255]);
+    }
+
+    #[test]
+    fn long_overlay_text_wraps_inside_panel() {
+        let (width, height) = (400_u32, 200_u32);
+        let mut framebuffer = vec![0_u8; pixel_len(width, height)];
+        let text = "overlay ".repeat(20);
+        draw_overlay_text(
+            &mut framebuffer,
+            width,
+            height,
+            &text,
+            OverlayCorner::TopLeft,
+        );
+
+        let pixel = |x: u32, y: u32| {
+            let index = ((y * width + x) * 4) as usize;
+            &framebuffer[index..index + 4]
+        };
+        // A single-line panel covers rows 6..28; wrapped text must extend it.
+        assert_ne!(pixel(8, 40), &[0, 0, 0, 0]);
+
+        let panel_right = 6 + 360;
+        for y in 0..height {
+            for x in panel_right..width {
+                assert_eq!(
+                    pixel(x, y),
+                    &[0, 0, 0, 0],
+                    "drawn outside panel at ({x}, {y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bottom_right_overlay_hugs_bottom_right_corner() {
+        let (width, height) = (200_u32, 120_u32);
+        let mut framebuffer = vec![0_u8; pixel_len(width, height)];
+        let overlay = OverlayInfo::builder()
+            .frame_index(3)
+            .corner(OverlayCorner::BottomRight)
+            .build();
+        draw_overlay(&mut framebuffer, width, height, overlay);
+
+        let pixel = |x: u32, y: u32| {
+            let index = ((y * width + x) * 4) as usize;
+            &framebuffer[index..index + 4]
+        };
+        // Single-line panel is 22px tall, inset 6px from the bottom edge.
+        assert_ne!(pixel(width - 8, height - 8), &[0, 0, 0, 0]);
+        assert_ne!(pixel(width / 2, height - 20), &[0, 0, 0, 0]);
+        assert_eq!(pixel(width - 8, height - 3), &[0, 0, 0, 0]);
+        assert_eq!(pixel(width / 2, 10), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn clear_fills_every_pixel() {
+        let mut renderer = Renderer::new(5, 3);
+        renderer.render(1, 0.0);
+        renderer.clear([0, 255, 0, 255]);
+        assert!(renderer
+            .pixels
+            .chunks_exact(4)
+            .all(|px| px == [0, 255, 0, 255]));
+
+        let mut empty = Renderer::new(0, 0);
+        empty.clear([0, 255, 0, 255]);
+        assert!(empty.pixels.is_empty());
+    }
+
+    #[test]
+    fn zero_sized_frames_render_empty() {
+        let rect = DrawRect {
+            x: 0,
+            y: 0,
+            width: 4,
+            height: 4,
+            color: [255, 0, 0, 255],
+        };
+        let overlay = OverlayInfo::builder().frame_index(1).fps(60.0).build();
+        for (width, height) in [(0, 24), (32, 0), (0, 0)] {
+            for rotation in [Rotation::Deg0, Rotation::Deg90] {
+                let mut renderer = Renderer::new(width, height);
+                renderer.set_rotation(rotation);
+                renderer.set_invert(true);
+                let frame = renderer.render_display_list(1, 0.0, &[rect], &[], Some(overlay));
+                assert!(frame.is_empty(), "{width}x{height} {rotation:?}");
+                for &pattern in Pattern::all() {
+                    renderer.set_pattern(pattern);
+                    assert!(renderer.render_pattern(1, 0.0, Some(overlay)).is_empty());
+                }
+                assert!(renderer.framebuffer().is_empty());
+            }
+        }
+
+        // Shrinking to nothing must not leave a stale rotated frame behind.
+        let mut renderer = Renderer::new(8, 4);
+        renderer.set_rotation(Rotation::Deg90);
+        renderer.render(0, 0.0);
+        renderer.resize(0, 4);
+        assert!(renderer.render(1, 0.0).is_empty());
+        assert!(renderer.framebuffer().is_empty());
+    }
+
+    #[test]
+    fn loading_frame_centers_message_and_animates() {
+        let mut renderer = Renderer::new(240, 120);
+        let first = renderer.render_loading(0, "Loading").to_vec();
+
+        let text_pixels: Vec<(usize, usize)> = first
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, px)| *px == LOADING_TEXT_COLOR)
+            .map(|(index, _)| (index % 240, index / 240))
+            .collect();
+        assert!(!text_pixels.is_empty());
+        let left = text_pixels.iter().map(|&(x, _)| x).min().unwrap();
+        let right = text_pixels.iter().map(|&(x, _)| x).max().unwrap();
+        let top = text_pixels.iter().map(|&(_, y)| y).min().unwrap();
+        let text_center = (left + right) / 2;
+        assert!(
+            text_center.abs_diff(120) <= 4,
+            "text spans {left}..={right}"
+        );
+        assert!((60..100).contains(&top), "text starts at row {top}");
+
+        let same_step = renderer
+            .render_loading(LOADING_FRAMES_PER_STEP - 1, "Loading")
+            .to_vec();
+        assert_eq!(same_step, first);
+        let next_step = renderer
+            .render_loading(LOADING_FRAMES_PER_STEP, "Loading")
+            .to_vec();
+        assert_ne!(next_step, first);
+        let full_turn = renderer
+            .render_loading(LOADING_FRAMES_PER_STEP * LOADING_DOTS, "Loading")
+            .to_vec();
+        assert_eq!(full_turn, first);
+    }
+
+    #[test]
+    fn builder_applies_every_option() {
+        let font_dir = Path::new(TEST_FONT_PATH).parent().unwrap();
+        let renderer = Renderer::builder()
+            .width(12)
+            .height(8)
+            .pattern(Pattern::Rects)
+            .clear_color(Some([1, 2, 3, 255]))
+            .rotation(Rotation::Deg90)
+            .grayscale(true)
+            .font_roots([font_dir])
+            .build();
+
+        assert_eq!((renderer.width(), renderer.height()), (12, 8));
+        assert_eq!(renderer.presented_size(), (8, 12));
+        assert_eq!(renderer.pattern(), Pattern::Rects);
+        assert_eq!(renderer.clear_color(), Some([1, 2, 3, 255]));
+        assert_eq!(renderer.rotation(), Rotation::Deg90);
+        assert!(renderer.is_grayscale());
+        assert_eq!(renderer.font_count(), 2);
+        assert_eq!(renderer.current_font_name(), "DejaVuSans");
+
+        let bare = Renderer::builder()
+            .font_roots(Vec::<PathBuf>::new())
+            .build();
+        assert_eq!(bare.font_count(), 1);
+        assert_eq!(bare.current_font_name(), "Pixel 5x7");
+        assert!(bare.framebuffer().is_empty());
+    }
+
+    #[test]
+    fn bitmap_text_mode_ignores_system_fonts() {
+        let font_dir = Path::new(TEST_FONT_PATH).parent().unwrap();
+        let render = |roots: Vec<&Path>, mode: TextMode| {
+            let mut renderer = Renderer::builder()
+                .width(48)
+                .height(12)
+                .clear_color(Some([0, 0, 0, 255]))
+                .font_roots(roots)
+                .build();
+            renderer.set_text_mode(mode);
+            assert_eq!(renderer.text_mode(), mode);
+            let texts = [DrawText {
+                x: 2,
+                y: 2,
+                text: "Hello".to_string(),
+                color: [255, 255, 255, 255],
+                scale: 1,
+                underline: true,
+                max_width: None,
+                rtl: false,
+                align: None,
+            }];
+            renderer
+                .render_display_list(0, 0.0, &[], &texts, None)
+                .to_vec()
+        };
+
+        let bitmap = render(Vec::new(), TextMode::Bitmap);
+        assert_eq!(render(vec![font_dir], TextMode::Bitmap), bitmap);
+        assert_eq!(render(Vec::new(), TextMode::AntiAliased), bitmap);
+        assert_ne!(render(vec![font_dir], TextMode::AntiAliased), bitmap);
+    }
+
+    #[test]
+    fn antialiased_text_mode_skips_the_bitmap_font() {
+        let font_dir = Path::new(TEST_FONT_PATH).parent().unwrap();
+        let mut renderer = Renderer::builder()
+            .font_roots([font_dir])
+            .text_mode(TextMode::AntiAliased)
+            .build();
+        assert!(renderer.set_font_index(0));
+        assert_eq!(renderer.current_font_name(), "Pixel 5x7");
+        assert_ne!(
+            renderer.text_advance("Hello", 1),
+            measure_text("Hello", 1) as f32
+        );
+
+        renderer.set_text_mode(TextMode::Auto);
+        assert_eq!(
+            renderer.text_advance("Hello", 1),
+            measure_text("Hello", 1) as f32
+        );
+    }
+
+    #[test]
+    fn repeated_glyphs_rasterize_once_into_the_atlas() {
+        let font_dir = Path::new(TEST_FONT_PATH).parent().unwrap();
+        let mut renderer = Renderer::builder()
+            .width(320)
+            .height(120)
+            .clear_color(Some([255, 255, 255, 255]))
+            .font_roots([font_dir])
+            .text_mode(TextMode::AntiAliased)
+            .build();
+        let line = |y, text: &str| DrawText {
+            x: 4,
+            y,
+            text: text.to_string(),
+            color: [0, 0, 0, 255],
+            scale: 2,
+            underline: false,
+            max_width: None,
+            rtl: false,
+            align: None,
+        };
+        let texts: Vec<DrawText> = (0..4).map(|row| line(row * 24, &"W".repeat(40))).collect();
+
+        renderer.render_display_list(0, 0.0, &[], &texts, None);
+        let stats = renderer.glyph_atlas_stats();
+        assert_eq!((stats.entries, stats.rasterized), (1, 1));
+        assert!(stats.height > 0 && stats.height < 64, "{stats:?}");
+        let first = renderer.framebuffer().to_vec();
+        assert!(first.chunks_exact(4).any(|px| px[0] < 128));
+
+        // Later frames blit from the atlas and draw identically.
+        renderer.render_display_list(1, 0.0, &[], &texts, None);
+        assert_eq!(renderer.glyph_atlas_stats().rasterized, 1);
+        assert_eq!(renderer.framebuffer(), first.as_slice());
+
+        renderer.render_display_list(2, 0.0, &[], &[line(0, "WAW")], None);
+        let stats = renderer.glyph_atlas_stats();
+        assert_eq!((stats.entries, stats.rasterized), (2, 2));
+    }
+
+    #[test]
+    fn font_metrics_follow_the_active_font() {
+        let font_dir = Path::new(TEST_FONT_PATH).parent().unwrap();
+        let mut renderer = Renderer::builder().font_roots([font_dir]).build();
+        renderer.set_text_mode(TextMode::Bitmap);
+        for scale in 1..=3 {
+            let metrics = renderer.font_metrics(scale);
+            assert_eq!(metrics.line_height, (scale * 8) as f32);
+            assert_eq!(metrics.ascent + metrics.descent, (scale * 7) as f32);
+        }
+
+        renderer.set_text_mode(TextMode::Auto);
+        let metrics = renderer.font_metrics(2);
+        assert_ne!(metrics.line_height, 16.0);
+        assert!(metrics.ascent > 0.0 && metrics.descent > 0.0);
+        assert!(metrics.ascent + metrics.descent <= metrics.line_height);
+    }
+
+    #[test]
+    fn load_font_file_adds_a_selectable_font() {
+        let mut renderer = Renderer::builder()
+            .width(48)
+            .height(16)
+            .clear_color(Some([0, 0, 0, 255]))
+            .font_roots(Vec::<PathBuf>::new())
+            .build();
+        let texts = [DrawText {
+            x: 2,
+            y: 2,
+            text: "Hello".to_string(),
+            color: [255, 255, 255, 255],
+            scale: 1,
+            underline: false,
+            max_width: None,
+            rtl: false,
+            align: None,
+        }];
+        let bitmap_frame = renderer
+            .render_display_list(0, 0.0, &[], &texts, None)
+            .to_vec();
+
+        let index = renderer.load_font_file(TEST_FONT_PATH).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(renderer.font_count(), 2);
+        assert_eq!(renderer.load_font_file(TEST_FONT_PATH), Ok(1));
+        assert!(renderer.set_font_index(index));
+        assert_eq!(renderer.current_font_name(), "DejaVuSans");
+
+        let frame = renderer.render_display_list(0, 0.0, &[], &texts, None);
+        assert!(frame.chunks_exact(4).any(|pixel| pixel[0] != 0));
+        assert_ne!(frame, bitmap_frame.as_slice());
+
+        let missing = renderer.load_font_file("does/not/exist.ttf");
+        assert!(missing.unwrap_err().contains("does/not/exist.ttf"));
+        assert_eq!(renderer.font_count(), 2);
+    }
+
+    #[test]
+    fn text_scale_enlarges_measured_text() {
+        let font_dir = Path::new(TEST_FONT_PATH).parent().unwrap();
+        for mode in [TextMode::Bitmap, TextMode::AntiAliased] {
+            let mut renderer = Renderer::builder().font_roots([font_dir]).build();
+            renderer.set_text_mode(mode);
+            let normal = renderer.text_advance("Readable text", 1);
+            let normal_line = renderer.font_metrics(1).line_height;
+            renderer.set_text_scale(2.0);
+            let doubled = renderer.text_advance("Readable text", 1);
+            assert!(
+                (doubled / normal - 2.0).abs() < 0.1,
+                "{mode:?}: {normal} -> {doubled}"
+            );
+            assert!(renderer.font_metrics(1).line_height > normal_line * 1.9);
+        }
+
+        let mut renderer = Renderer::builder()
+            .font_roots(Vec::<PathBuf>::new())
+            .build();
+        renderer.set_text_scale(100.0);
+        assert_eq!(renderer.text_scale(), *TEXT_SCALE_RANGE.end());
+        renderer.set_text_scale(0.0);
+        assert_eq!(renderer.text_scale(), *TEXT_SCALE_RANGE.start());
+        renderer.set_text_scale(f32::NAN);
+        assert_eq!(renderer.text_scale(), 1.0);
+    }
+
+    #[test]
+    fn present_to_copies_the_rendered_frame() {
+        let mut renderer = Renderer::new(12, 8);
+        renderer.set_rotation(Rotation::Deg90);
+        let expected = renderer.render(3, 0.5).to_vec();
+
+        let mut external = vec![0xaa; 12 * 8 * 4];
+        renderer.present_to(&mut external).unwrap();
+        assert_eq!(external, expected);
+
+        let mut short = vec![0; 12 * 8 * 4 - 1];
+        let err = renderer.present_to(&mut short).unwrap_err();
+        assert!(err.contains("8x12"), "{err}");
+    }
+
+    #[test]
+    fn dirty_rects_cover_rows_that_changed() {
+        let mut renderer = Renderer::new(10, 8);
+        renderer.set_clear_color(Some([0, 0, 0, 255]));
+        let rect = |y, color| DrawRect {
+            x: 2,
+            y,
+            width: 3,
+            height: 2,
+            color,
+        };
+
+        renderer.render_display_list(0, 0.0, &[rect(1, [255, 0, 0, 255])], &[], None);
+        let full = DirtyRect {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 8,
+        };
+        assert_eq!(renderer.last_dirty_rects(), [full]);
+
+        renderer.render_display_list(1, 0.0, &[rect(1, [255, 0, 0, 255])], &[], None);
+        assert!(renderer.last_dirty_rects().is_empty());
+
+        renderer.render_display_list(2, 0.0, &[rect(4, [255, 0, 0, 255])], &[], None);
+        assert_eq!(
+            renderer.last_dirty_rects(),
+            [
+                DirtyRect {
+                    y: 1,
+                    height: 2,
+                    ..full
+                },
+                DirtyRect {
+                    y: 4,
+                    height: 2,
+                    ..full
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn caret_fills_a_vertical_column() {
+        let mut renderer = Renderer::new(12, 24);
+        renderer.set_clear_color(Some([0, 0, 0, 255]));
+        renderer.render_display_list(0, 0.0, &[], &[], None);
+        let caret = [255, 255, 255, 255];
+        renderer.draw_caret(5, 3, 10, caret);
+
+        let frame = renderer.framebuffer();
+        let pixel = |x: usize, y: usize| &frame[(y * 12 + x) * 4..(y * 12 + x) * 4 + 4];
+        for y in 0..24 {
+            let expected: &[u8] = if (3..13).contains(&y) {
+                &caret
+            } else {
+                &[0, 0, 0, 255]
+            };
+            assert_eq!(pixel(5, y), expected, "row {y}");
+            assert_eq!(pixel(6, y), &[0, 0, 0, 255], "row {y}");
+        }
+        assert_eq!(
+            renderer.last_dirty_rects().last(),
+            Some(&DirtyRect {
+                x: 0,
+                y: 3,
+                width: 12,
+                height: 10,
+            })
+        );
+
+        // Tall carets are two pixels wide.
+        renderer.draw_caret(8, 0, 20, caret);
+        let frame = renderer.framebuffer();
+        let offset = (19 * 12 + 9) * 4;
+        assert_eq!(frame[offset..offset + 4], caret);
+
+        assert!(caret_visible(0) && caret_visible(CARET_BLINK_FRAMES - 1));
+        assert!(!caret_visible(CARET_BLINK_FRAMES));
+        assert!(caret_visible(2 * CARET_BLINK_FRAMES));
+    }
+
+    #[test]
+    fn rgba_header_roundtrips_and_rejects_truncation() {
+        let pixels: Vec<u8> = (0..3 * 2 * 4).map(|i| i as u8).collect();
+        let header = RgbaHeader::for_frame(3, 2, &pixels);
+        let mut file = header.to_bytes().to_vec();
+        file.extend_from_slice(&pixels);
+
+        assert!(RgbaHeader::is_present(&file));
+        assert!(!RgbaHeader::is_present(&pixels));
+        assert_eq!(RgbaHeader::parse(&file), Ok((header, pixels.as_slice())));
+
+        let err = RgbaHeader::parse(&file[..file.len() - 4]).unwrap_err();
+        assert!(err.contains("truncated"), "{err}");
+        let err = RgbaHeader::parse(&file[..RGBA_HEADER_LEN - 1]).unwrap_err();
+        assert!(err.contains("missing"), "{err}");
+
+        let mut corrupted = file.clone();
+        corrupted[RGBA_HEADER_LEN] ^= 0xff;
+        let err = RgbaHeader::parse(&corrupted).unwrap_err();
+        assert!(err.contains("checksum"), "{err}");
+
+        let mut future = file;
+        future[4] = 2;
+        let err = RgbaHeader::parse(&future).unwrap_err();
+        assert!(err.contains("version 2"), "{err}");
+    }
+
+    #[test]
+    fn framebuffer_returns_last_frame() {
+        let mut renderer = Renderer::new(6, 4);
+        renderer.set_pattern(Pattern::Rects);
+        let frame = renderer.render(3, 0.1).to_vec();
+        assert_eq!(renderer.framebuffer(), frame.as_slice());
+
+        renderer.set_rotation(Rotation::Deg90);
+        let rotated = renderer.render(3, 0.1).to_vec();
+        assert_eq!(renderer.framebuffer(), rotated.as_slice());
+
+        // Until the next render there is no frame at the new rotation or size to hand out.
+        renderer.set_rotation(Rotation::Deg270);
+        assert!(renderer.framebuffer().is_empty());
+        let rotated = renderer.render(3, 0.1).to_vec();
+        assert_eq!(renderer.framebuffer(), rotated.as_slice());
+        renderer.resize(8, 4);
+        assert!(renderer.framebuffer().is_empty());
+        assert_eq!(renderer.render(3, 0.1).len(), 8 * 4 * 4);
+        assert_eq!(renderer.framebuffer().len(), 8 * 4 * 4);
+    }
+
+    #[test]
+    fn gradient_stops_interpolate_across_width() {
+        let mut renderer = Renderer::new(16, 2);
+        renderer.set_pattern(Pattern::Gradient);
+        renderer.set_gradient_stops(vec![(1.0, [255, 255, 255, 255]), (0.0, [0, 0, 0, 255])]);
+        let frame = renderer.render(0, 0.0);
+
+        let row = 16 * 4;
+        assert_eq!(&frame[0..4], &[0, 0, 0, 255]);
+        assert_eq!(&frame[row - 4..row], &[255, 255, 255, 255]);
+        assert_eq!(&frame[row..row + 4], &[0, 0, 0, 255]);
+        let mid = 8 * 4;
+        assert!((120..=140).contains(&frame[mid]));
+    }
+
     #[test]
     fn display_list_renders_rects() {
         let mut renderer = Renderer::new(32, 16);
@@ -905,13 +3357,4 @@ mod tests {
         let idx = (2 * stride) + (2 * 4);
         assert_eq!(&frame[idx..idx + 4], &[255, 10, 10, 255]);
     }
-
-    fn fnv1a64(bytes: &[u8]) -> u64 {
-        let mut hash = 0xcbf29ce484222325_u64;
-        for b in bytes {
-            hash ^= u64::from(*b);
-            hash = hash.wrapping_mul(0x100000001b3);
-        }
-        hash
-    }
 }
@@ -33,6 +33,45 @@ impl ScriptHost for StubScriptHost {
     }
 }
 
+// Source text of the arguments of each `console.log(...)` call in `code`, in order. Parentheses
+// inside string literals do not end a call; an unterminated call is skipped.
+pub fn console_log_args(code: &str) -> Vec<&str> {
+    const CALL: &str = "console.log(";
+    let mut args = Vec::new();
+    let mut rest = code;
+    while let Some(start) = rest.find(CALL) {
+        let body = &rest[start + CALL.len()..];
+        let Some(end) = closing_paren(body) else {
+            break;
+        };
+        args.push(body[..end].trim());
+        rest = &body[end + 1..];
+    }
+    args
+}
+
+fn closing_paren(body: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, ch) in body.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if ch == '\\' => escaped = true,
+            Some(open) if ch == open => quote = None,
+            Some(_) => {}
+            None => match ch {
+                '\'' | '"' | '`' => quote = Some(ch),
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(index),
+                ')' => depth -= 1,
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,6 +82,7 @@ mod tests {
         let scripts = vec![ScriptSnippet {
             node_id: 2,
             code: "console.log('hi')".to_string(),
+            leading_offset: 0,
         }];
 
         let err = host.execute(&scripts).unwrap_err();
@@ -56,4 +96,11 @@ mod tests {
         host.execute(&[]).unwrap();
         assert!(host.captured().is_empty());
     }
+
+    #[test]
+    fn console_log_args_returns_each_call() {
+        let code = "let a = f(1);\nconsole.log('a)', a);\nconsole.log( g(2) );\nconsole.log(";
+        assert_eq!(console_log_args(code), ["'a)', a", "g(2)"]);
+        assert!(console_log_args("alert('hi')").is_empty());
+    }
 }
@@ -13,7 +13,7 @@
 // - To extend a struct safely, append trailing fields and include a size field.
 // - Never reorder or remove existing fields.
 // - Bump PLATFORM_ABI_VERSION on any breaking ABI change.
-#define PLATFORM_ABI_VERSION ((uint32_t)2u)
+#define PLATFORM_ABI_VERSION ((uint32_t)6u)
 
 #define PLATFORM_FALSE ((uint8_t)0u)
 #define PLATFORM_TRUE ((uint8_t)1u)
@@ -24,20 +24,31 @@ enum platform_event_kind {
   PLATFORM_EVENT_KEY_DOWN = 2,
   PLATFORM_EVENT_KEY_UP = 3,
   PLATFORM_EVENT_RESIZE = 4,
+  PLATFORM_EVENT_MOUSE_MOVE = 5,
 };
 
 enum platform_key_code {
   PLATFORM_KEY_UNKNOWN = 0,
+  PLATFORM_KEY_TAB = 9,
   PLATFORM_KEY_ESCAPE = 27,
   PLATFORM_KEY_ENTER = 13,
   PLATFORM_KEY_SPACE = 32,
+  PLATFORM_KEY_1 = 49,
+  PLATFORM_KEY_2 = 50,
+  PLATFORM_KEY_3 = 51,
   PLATFORM_KEY_F = 70,
   PLATFORM_KEY_H = 72,
+  PLATFORM_KEY_I = 73,
   PLATFORM_KEY_J = 74,
   PLATFORM_KEY_K = 75,
   PLATFORM_KEY_S = 83,
 };
 
+enum platform_key_modifier {
+  PLATFORM_MODIFIER_NONE = 0,
+  PLATFORM_MODIFIER_SHIFT = 1,
+};
+
 typedef struct platform_config {
   // Size in bytes of this struct provided by the caller.
   // Allows forward/backward-compatible trailing field extensions.
@@ -64,12 +75,26 @@ typedef struct platform_event {
   uint32_t key_code;
   uint32_t width;
   uint32_t height;
+  // Bitmask of platform_key_modifier values held during key events.
+  uint32_t modifiers;
+  // Pointer position for mouse events, in window pixels from the top-left corner.
+  int32_t x;
+  int32_t y;
 } platform_event;
 
+typedef struct platform_dirty_rect {
+  // Region of the frame, in frame pixels, that changed since the previous present.
+  uint32_t x;
+  uint32_t y;
+  uint32_t width;
+  uint32_t height;
+} platform_dirty_rect;
+
 // ABI sanity checks. Pointer-sized structs are checked in Rust tests for both
 // 32-bit and 64-bit expectations.
 #if defined(__STDC_VERSION__) && __STDC_VERSION__ >= 201112L
-_Static_assert(sizeof(platform_event) == 20u, "platform_event ABI size changed");
+_Static_assert(sizeof(platform_event) == 32u, "platform_event ABI size changed");
+_Static_assert(sizeof(platform_dirty_rect) == 16u, "platform_dirty_rect ABI size changed");
 #endif
 
 #ifdef __cplusplus
@@ -80,6 +105,12 @@ uint32_t platform_get_abi_version(void);
 uint8_t platform_init_window(const platform_config *config);
 uint8_t platform_poll_event(platform_event *out_event);
 uint8_t platform_present_frame(const platform_frame *frame);
+// Presents `frame` where only `rects[0..rect_count]` changed since the previous present.
+// Backends may redraw just those regions; with rect_count == 0 nothing is presented.
+uint8_t platform_present_frame_partial(const platform_frame *frame,
+                                       const platform_dirty_rect *rects, uint32_t rect_count);
+// Replaces the window title after init. `title_utf8` must be NUL-terminated UTF-8.
+uint8_t platform_set_title(const char *title_utf8);
 void platform_shutdown(void);
 
 #ifdef __cplusplus
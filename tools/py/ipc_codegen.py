@@ -10,10 +10,10 @@ OUT_PATH = ROOT / "docs" / "ipc-schema.md"
 
 
 def render_section(title: str, rows: list[dict]) -> str:
-    lines = [f"## {title}", "", "| Message | Fields |", "| --- | --- |"]
+    lines = [f"## {title}", "", "| Message | Version | Fields |", "| --- | --- | --- |"]
     for row in rows:
         fields = ", ".join(f"`{name}: {ty}`" for name, ty in row.get("fields", [])) or "(none)"
-        lines.append(f"| `{row['name']}` | {fields} |")
+        lines.append(f"| `{row['name']}` | `{row['version']}` | {fields} |")
     lines.append("")
     return "\n".join(lines)
 
@@ -26,6 +26,9 @@ def main() -> int:
         "",
         f"Schema version: `{schema['version']}`",
         "",
+        "Each message is the schema version (u32), the message tag (u8), the message version (u8),",
+        "then its fields. A newer message version only appends fields, so older decoders skip them.",
+        "",
         render_section("Browser -> Content", schema["browser_to_content"]),
         render_section("Content -> Browser", schema["content_to_browser"]),
     ]
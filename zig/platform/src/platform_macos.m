@@ -185,6 +185,37 @@ static void enqueue_quit_if_needed(void) {
 }
 @end
 
+static uint32_t translate_key(NSEvent *event) {
+  if ([event keyCode] == 53) {
+    return PLATFORM_KEY_ESCAPE;
+  }
+  if ([event keyCode] == 48) {
+    return PLATFORM_KEY_TAB;
+  }
+  if ([event keyCode] == 36) {
+    return PLATFORM_KEY_ENTER;
+  }
+
+  NSString *chars = [event charactersIgnoringModifiers];
+  if ([chars length] == 0) {
+    return PLATFORM_KEY_UNKNOWN;
+  }
+  switch ([chars characterAtIndex:0]) {
+    case ' ':
+      return PLATFORM_KEY_SPACE;
+    case '1':
+      return PLATFORM_KEY_1;
+    case '2':
+      return PLATFORM_KEY_2;
+    case '3':
+      return PLATFORM_KEY_3;
+    case 'i':
+      return PLATFORM_KEY_I;
+    default:
+      return PLATFORM_KEY_UNKNOWN;
+  }
+}
+
 static BrowserWindowDelegate *g_window_delegate = nil;
 static id g_menu_target = nil;
 
@@ -287,6 +318,7 @@ uint8_t platform_init_window(const platform_config *config) {
 
     g_window_delegate = [[BrowserWindowDelegate alloc] init];
     [g_window setDelegate:g_window_delegate];
+    [g_window setAcceptsMouseMovedEvents:YES];
 
     g_view = [[BrowserView alloc] initWithFrame:frame];
     [g_window setContentView:g_view];
@@ -324,17 +356,34 @@ uint8_t platform_poll_event(platform_event *out_event) {
                                           inMode:NSDefaultRunLoopMode
                                          dequeue:YES])) {
       if ([event type] == NSEventTypeKeyDown) {
-        uint32_t key_code = PLATFORM_KEY_UNKNOWN;
-        if ([event keyCode] == 53) {
-          key_code = PLATFORM_KEY_ESCAPE;
-        }
+        uint32_t key_code = translate_key(event);
 
         platform_event next;
         memset(&next, 0, sizeof(next));
         next.struct_size = sizeof(platform_event);
         next.kind = PLATFORM_EVENT_KEY_DOWN;
         next.key_code = key_code;
+        next.modifiers = ([event modifierFlags] & NSEventModifierFlagShift)
+                             ? PLATFORM_MODIFIER_SHIFT
+                             : PLATFORM_MODIFIER_NONE;
         push_event(&next);
+      } else if (([event type] == NSEventTypeMouseMoved ||
+                  [event type] == NSEventTypeLeftMouseDragged) &&
+                 [event window] == g_window) {
+        // BrowserView is flipped, so converted points are already top-left based.
+        NSPoint point = [g_view convertPoint:[event locationInWindow] fromView:nil];
+
+        platform_event next;
+        memset(&next, 0, sizeof(next));
+        next.struct_size = sizeof(platform_event);
+        next.kind = PLATFORM_EVENT_MOUSE_MOVE;
+        next.x = (int32_t)point.x;
+        next.y = (int32_t)point.y;
+        next.modifiers = ([event modifierFlags] & NSEventModifierFlagShift)
+                             ? PLATFORM_MODIFIER_SHIFT
+                             : PLATFORM_MODIFIER_NONE;
+        push_event(&next);
+        [NSApp sendEvent:event];
       } else {
         [NSApp sendEvent:event];
       }
@@ -370,6 +419,33 @@ uint8_t platform_present_frame(const platform_frame *frame) {
   }
 }
 
+// Repaints the whole frame whenever anything changed; the dirty rects only decide whether
+// presenting is needed at all.
+uint8_t platform_present_frame_partial(const platform_frame *frame,
+                                       const platform_dirty_rect *rects, uint32_t rect_count) {
+  if (rect_count == 0) {
+    return g_initialized ? PLATFORM_TRUE : PLATFORM_FALSE;
+  }
+  if (rects == NULL) {
+    return PLATFORM_FALSE;
+  }
+  return platform_present_frame(frame);
+}
+
+uint8_t platform_set_title(const char *title_utf8) {
+  @autoreleasepool {
+    if (g_window == nil || title_utf8 == NULL) {
+      return PLATFORM_FALSE;
+    }
+    NSString *title = [NSString stringWithUTF8String:title_utf8];
+    if (title == nil) {
+      return PLATFORM_FALSE;
+    }
+    [g_window setTitle:title];
+    return PLATFORM_TRUE;
+  }
+}
+
 void platform_shutdown(void) {
   @autoreleasepool {
     if (g_window != nil) {
@@ -17,4 +17,17 @@ uint8_t platform_present_frame(const platform_frame *frame) {
   return PLATFORM_FALSE;
 }
 
+uint8_t platform_present_frame_partial(const platform_frame *frame,
+                                       const platform_dirty_rect *rects, uint32_t rect_count) {
+  (void)frame;
+  (void)rects;
+  (void)rect_count;
+  return PLATFORM_FALSE;
+}
+
+uint8_t platform_set_title(const char *title_utf8) {
+  (void)title_utf8;
+  return PLATFORM_FALSE;
+}
+
 void platform_shutdown(void) {}
@@ -84,6 +84,33 @@ static bool utf8_to_utf16_alloc(const char *utf8, wchar_t **out_wide) {
   return true;
 }
 
+static uint32_t translate_key(WPARAM wparam) {
+  switch (wparam) {
+    case VK_ESCAPE:
+      return PLATFORM_KEY_ESCAPE;
+    case VK_TAB:
+      return PLATFORM_KEY_TAB;
+    case VK_RETURN:
+      return PLATFORM_KEY_ENTER;
+    case VK_SPACE:
+      return PLATFORM_KEY_SPACE;
+    case '1':
+      return PLATFORM_KEY_1;
+    case '2':
+      return PLATFORM_KEY_2;
+    case '3':
+      return PLATFORM_KEY_3;
+    case 'I':
+      return PLATFORM_KEY_I;
+    default:
+      return PLATFORM_KEY_UNKNOWN;
+  }
+}
+
+static uint32_t current_modifiers(void) {
+  return (GetKeyState(VK_SHIFT) & 0x8000) ? PLATFORM_MODIFIER_SHIFT : PLATFORM_MODIFIER_NONE;
+}
+
 static LRESULT CALLBACK window_proc(HWND hwnd, UINT msg, WPARAM wparam, LPARAM lparam) {
   (void)hwnd;
   platform_event event;
@@ -101,20 +128,21 @@ static LRESULT CALLBACK window_proc(HWND hwnd, UINT msg, WPARAM wparam, LPARAM l
       return 0;
     case WM_KEYDOWN:
       event.kind = PLATFORM_EVENT_KEY_DOWN;
-      if (wparam == VK_ESCAPE) {
-        event.key_code = PLATFORM_KEY_ESCAPE;
-      } else {
-        event.key_code = PLATFORM_KEY_UNKNOWN;
-      }
+      event.key_code = translate_key(wparam);
+      event.modifiers = current_modifiers();
       push_event(&event);
       return 0;
     case WM_KEYUP:
       event.kind = PLATFORM_EVENT_KEY_UP;
-      if (wparam == VK_ESCAPE) {
-        event.key_code = PLATFORM_KEY_ESCAPE;
-      } else {
-        event.key_code = PLATFORM_KEY_UNKNOWN;
-      }
+      event.key_code = translate_key(wparam);
+      event.modifiers = current_modifiers();
+      push_event(&event);
+      return 0;
+    case WM_MOUSEMOVE:
+      event.kind = PLATFORM_EVENT_MOUSE_MOVE;
+      event.x = (int32_t)(short)LOWORD(lparam);
+      event.y = (int32_t)(short)HIWORD(lparam);
+      event.modifiers = current_modifiers();
       push_event(&event);
       return 0;
     case WM_SIZE: {
@@ -275,6 +303,32 @@ uint8_t platform_present_frame(const platform_frame *frame) {
   return (result != GDI_ERROR) ? PLATFORM_TRUE : PLATFORM_FALSE;
 }
 
+// Repaints the whole frame whenever anything changed; the dirty rects only decide whether
+// presenting is needed at all.
+uint8_t platform_present_frame_partial(const platform_frame *frame,
+                                       const platform_dirty_rect *rects, uint32_t rect_count) {
+  if (rect_count == 0) {
+    return (g_hwnd != NULL && g_dc != NULL) ? PLATFORM_TRUE : PLATFORM_FALSE;
+  }
+  if (rects == NULL) {
+    return PLATFORM_FALSE;
+  }
+  return platform_present_frame(frame);
+}
+
+uint8_t platform_set_title(const char *title_utf8) {
+  if (g_hwnd == NULL || title_utf8 == NULL) {
+    return PLATFORM_FALSE;
+  }
+  wchar_t *title = NULL;
+  if (!utf8_to_utf16_alloc(title_utf8, &title)) {
+    return PLATFORM_FALSE;
+  }
+  BOOL ok = SetWindowTextW(g_hwnd, title);
+  free(title);
+  return ok ? PLATFORM_TRUE : PLATFORM_FALSE;
+}
+
 void platform_shutdown(void) {
   if (g_dc != NULL && g_hwnd != NULL) {
     ReleaseDC(g_hwnd, g_dc);